@@ -23,7 +23,7 @@ extern crate mlua_uvector4 as mlua;
 
 use haystack_types::h_number::HNumber;
 use haystack_types::h_val::HBox;
-use haystack_types::{HGrid, HType, Parser, io};
+use haystack_types::{HGrid, HType, io};
 use mlua::{
     Error as LuaError, Function as LuaFunction, Lua, Result as LuaResult, Table as LuaTable, Value,
 };
@@ -110,9 +110,8 @@ pub fn haystack(lua: &Lua) -> LuaResult<LuaTable> {
     zinc_table.set(
         "grid",
         lua.create_function(|_, args: String| {
-            let (_, grid) = io::parse::zinc::grid::<LuaFloat>
-                .parse(args.as_str())
-                .or_else(|e| Err(LuaError::RuntimeError(e.to_string())))?;
+            let grid = io::parse::zinc::parse_grid::<LuaFloat>(args.as_str())
+                .map_err(|e| LuaError::RuntimeError(e.to_string()))?;
             Ok(H::new(grid))
         })?,
     )?;
@@ -120,9 +119,8 @@ pub fn haystack(lua: &Lua) -> LuaResult<LuaTable> {
     zinc_table.set(
         "list",
         lua.create_function(|_, args: String| {
-            let (_, grid) = io::parse::zinc::list::<LuaFloat>
-                .parse(args.as_str())
-                .or_else(|e| Err(LuaError::RuntimeError(e.to_string())))?;
+            let grid = io::parse::zinc::parse_list::<LuaFloat>(args.as_str())
+                .map_err(|e| LuaError::RuntimeError(e.to_string()))?;
             Ok(H::new(grid))
         })?,
     )?;
@@ -130,9 +128,8 @@ pub fn haystack(lua: &Lua) -> LuaResult<LuaTable> {
     zinc_table.set(
         "dict",
         lua.create_function(|_, args: String| {
-            let (_, grid) = io::parse::zinc::dict::<LuaFloat>
-                .parse(args.as_str())
-                .or_else(|e| Err(LuaError::RuntimeError(e.to_string())))?;
+            let grid = io::parse::zinc::parse_dict::<LuaFloat>(args.as_str())
+                .map_err(|e| LuaError::RuntimeError(e.to_string()))?;
             Ok(H::new(grid))
         })?,
     )?;
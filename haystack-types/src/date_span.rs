@@ -0,0 +1,307 @@
+//! Date ranges for `hisRead`, parsed and written as the op's range strings and resolved to
+//! start and end times in a timezone
+
+use crate::err::ZincParseErr;
+use crate::h_date::HDate;
+use crate::h_datetime::HDateTime;
+use crate::io::ParseHint;
+use crate::io::parse::{self, zinc};
+use chrono::{Datelike, Days, Months, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use nom::Parser;
+use nom::combinator::all_consuming;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// A `hisRead` range, written and parsed in the forms the op accepts
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DateSpan {
+    Today,
+    Yesterday,
+    Date(HDate),
+    /// Both days inclusive, ie. `2025-01-01,2025-01-07`
+    Dates(HDate, HDate),
+    DateTimes(HDateTime, HDateTime),
+    /// Open-ended, from the time until now
+    Since(HDateTime),
+}
+
+enum Bound {
+    Date(HDate),
+    DateTime(HDateTime),
+}
+
+fn parse_bound(input: &str, part: &str) -> Result<Bound, ZincParseErr> {
+    if let Ok((_, dt)) = all_consuming(zinc::datetime(&mut ParseHint::default())).parse(part) {
+        return Ok(Bound::DateTime(dt));
+    }
+    match all_consuming(parse::date).parse(part) {
+        Ok((_, date)) => Ok(Bound::Date(date)),
+        Err(_) => Err(ZincParseErr::new(input, part, "a date or dateTime")),
+    }
+}
+
+impl FromStr for DateSpan {
+    type Err = ZincParseErr;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+        match trimmed {
+            "today" => return Ok(DateSpan::Today),
+            "yesterday" => return Ok(DateSpan::Yesterday),
+            _ => (),
+        }
+
+        let (start, end) = match trimmed.split_once(',') {
+            Some((start, end)) => (start.trim(), Some(end.trim())),
+            None => (trimmed, None),
+        };
+        let start_bound = parse_bound(input, start)?;
+        let end_bound = match end {
+            Some(end) if !end.is_empty() => Some((end, parse_bound(input, end)?)),
+            _ => None,
+        };
+
+        match (start_bound, end_bound) {
+            (Bound::Date(date), None) if end.is_none() => Ok(DateSpan::Date(date)),
+            (Bound::DateTime(dt), None) => Ok(DateSpan::Since(dt)),
+            (Bound::Date(start), Some((end_str, Bound::Date(end)))) => match end < start {
+                true => Err(ZincParseErr::new(
+                    input,
+                    end_str,
+                    "an end date after the start",
+                )),
+                false => Ok(DateSpan::Dates(start, end)),
+            },
+            (Bound::DateTime(start), Some((end_str, Bound::DateTime(end)))) => {
+                match end.to_chrono() < start.to_chrono() {
+                    true => Err(ZincParseErr::new(
+                        input,
+                        end_str,
+                        "an end dateTime after the start",
+                    )),
+                    false => Ok(DateSpan::DateTimes(start, end)),
+                }
+            }
+            (Bound::Date(_), None) => Err(ZincParseErr::new(
+                input,
+                &trimmed[trimmed.len()..],
+                "an end date",
+            )),
+            (Bound::Date(_), Some((end, _))) => Err(ZincParseErr::new(input, end, "a date")),
+            (Bound::DateTime(_), Some((end, _))) => {
+                Err(ZincParseErr::new(input, end, "a dateTime"))
+            }
+        }
+    }
+}
+
+impl Display for DateSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateSpan::Today => write!(f, "today"),
+            DateSpan::Yesterday => write!(f, "yesterday"),
+            DateSpan::Date(date) => date.to_zinc(f),
+            DateSpan::Dates(start, end) => {
+                start.to_zinc(f)?;
+                write!(f, ",")?;
+                end.to_zinc(f)
+            }
+            DateSpan::DateTimes(start, end) => {
+                start.to_zinc(f)?;
+                write!(f, ",")?;
+                end.to_zinc(f)
+            }
+            DateSpan::Since(start) => start.to_zinc(f),
+        }
+    }
+}
+
+/// Midnight, or the end of the DST gap in zones that skip midnight
+pub(crate) fn start_of_day(date: NaiveDate, tz: Tz) -> HDateTime {
+    (0..24)
+        .find_map(|hour| HDateTime::from_local(date.and_hms_opt(hour, 0, 0)?, tz).ok())
+        .unwrap_or_else(|| HDateTime::from(tz.from_utc_datetime(&date.into())))
+}
+
+fn next_day(date: NaiveDate) -> NaiveDate {
+    date.succ_opt().unwrap_or(date)
+}
+
+impl DateSpan {
+    /// The Sunday to Saturday week containing `date`
+    pub fn week(date: &HDate) -> Self {
+        let start = date.val() - Days::new(date.val().weekday().num_days_from_sunday() as u64);
+        DateSpan::Dates(start.into(), (start + Days::new(6)).into())
+    }
+
+    pub fn month(date: &HDate) -> Self {
+        let start = date.val().with_day(1).unwrap();
+        let end = start + Months::new(1) - Days::new(1);
+        DateSpan::Dates(start.into(), end.into())
+    }
+
+    pub fn year(date: &HDate) -> Self {
+        let year = date.val().year();
+        let (start, end) = (
+            NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(year, 12, 31).unwrap(),
+        );
+        DateSpan::Dates(start.into(), end.into())
+    }
+
+    /// Start and exclusive end of the span in `tz`, open-ended spans end now
+    pub fn resolve(&self, tz: Tz) -> (HDateTime, HDateTime) {
+        self.resolve_at(Utc::now().with_timezone(&tz))
+    }
+
+    /// Resolves relative to `now` and in its timezone
+    pub fn resolve_at(&self, now: chrono::DateTime<Tz>) -> (HDateTime, HDateTime) {
+        let tz = now.timezone();
+        let today = now.date_naive();
+        let days = |start: NaiveDate, end: NaiveDate| {
+            (start_of_day(start, tz), start_of_day(next_day(end), tz))
+        };
+
+        match self {
+            DateSpan::Today => days(today, today),
+            DateSpan::Yesterday => {
+                let yesterday = today.pred_opt().unwrap_or(today);
+                days(yesterday, yesterday)
+            }
+            DateSpan::Date(date) => days(date.val(), date.val()),
+            DateSpan::Dates(start, end) => days(start.val(), end.val()),
+            DateSpan::DateTimes(start, end) => (start.to_tz(tz), end.to_tz(tz)),
+            DateSpan::Since(start) => (start.to_tz(tz), HDateTime::from(now)),
+        }
+    }
+
+    /// Every day the span touches in `tz`
+    pub fn days(&self, tz: Tz) -> impl Iterator<Item = HDate> + use<> {
+        let (start, end) = self.resolve(tz);
+        days_between(start, end)
+    }
+}
+
+fn days_between(start: HDateTime, end: HDateTime) -> impl Iterator<Item = HDate> {
+    let last = match end.val().time() == chrono::NaiveTime::MIN {
+        true => end.val().date().pred_opt().unwrap_or(end.val().date()),
+        false => end.val().date(),
+    };
+    start
+        .val()
+        .date()
+        .iter_days()
+        .take_while(move |date| *date <= last)
+        .map(HDate::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> HDate {
+        NaiveDate::from_str(s).unwrap().into()
+    }
+
+    fn now() -> chrono::DateTime<Tz> {
+        Tz::America__New_York
+            .with_ymd_and_hms(2025, 3, 12, 15, 30, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_and_write_forms() {
+        for range in [
+            "today",
+            "yesterday",
+            "2025-01-01",
+            "2025-01-01,2025-01-07",
+            "2025-01-01T00:00:00-05:00 New_York,2025-01-02T00:00:00-05:00 New_York",
+            "2025-01-01T00:00:00Z UTC",
+        ] {
+            let span: DateSpan = range.parse().unwrap();
+            assert_eq!(span.to_string(), range);
+        }
+        assert_eq!(
+            "2025-01-01T00:00:00Z UTC,".parse::<DateSpan>().unwrap(),
+            "2025-01-01T00:00:00Z UTC".parse::<DateSpan>().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = "2025-01-01,2025-01-07T00:00:00Z UTC"
+            .parse::<DateSpan>()
+            .unwrap_err();
+        assert_eq!((err.col, err.expected.as_str()), (12, "a date"));
+        let err = "2025-01-01,".parse::<DateSpan>().unwrap_err();
+        assert_eq!(err.expected, "an end date");
+        let err = "2025-01-07,2025-01-01".parse::<DateSpan>().unwrap_err();
+        assert_eq!(
+            (err.col, err.expected.as_str()),
+            (12, "an end date after the start")
+        );
+        let err = "2025-01-02T00:00:00Z UTC,2025-01-01T18:00:00-05:00 New_York"
+            .parse::<DateSpan>()
+            .unwrap_err();
+        assert_eq!(err.expected, "an end dateTime after the start");
+        assert!(
+            "2025-01-01T00:00:00Z UTC,2025-01-01T00:00:00Z UTC"
+                .parse::<DateSpan>()
+                .is_ok()
+        );
+        let err = "last week".parse::<DateSpan>().unwrap_err();
+        assert_eq!((err.col, err.expected.as_str()), (1, "a date or dateTime"));
+    }
+
+    #[test]
+    fn resolve_in_tz() {
+        let (start, end) = DateSpan::Yesterday.resolve_at(now());
+        assert_eq!(start.val().to_string(), "2025-03-11 00:00:00");
+        assert_eq!(start.offset().local_minus_utc(), -4 * 3600);
+        assert_eq!(end.val().to_string(), "2025-03-12 00:00:00");
+
+        // The DST change on 2025-03-09 makes the day an hour short
+        let (start, end) = DateSpan::Date(date("2025-03-09")).resolve_at(now());
+        assert_eq!((end.to_chrono() - start.to_chrono()).num_hours(), 23);
+
+        let dt = "2025-03-12T12:00:00Z UTC".parse::<DateSpan>().unwrap();
+        let (start, end) = dt.resolve_at(now());
+        assert_eq!(start.val().to_string(), "2025-03-12 08:00:00");
+        assert_eq!(end, HDateTime::from(now()));
+    }
+
+    #[test]
+    fn calendar_spans() {
+        let day = date("2025-02-12");
+        assert_eq!(
+            DateSpan::week(&day),
+            DateSpan::Dates(date("2025-02-09"), date("2025-02-15"))
+        );
+        assert_eq!(
+            DateSpan::month(&day),
+            DateSpan::Dates(date("2025-02-01"), date("2025-02-28"))
+        );
+        assert_eq!(
+            DateSpan::year(&day),
+            DateSpan::Dates(date("2025-01-01"), date("2025-12-31"))
+        );
+    }
+
+    #[test]
+    fn iterate_days() {
+        let span = DateSpan::Dates(date("2025-02-27"), date("2025-03-02"));
+        let days: Vec<_> = span.days(Tz::UTC).map(|d| d.val().to_string()).collect();
+        assert_eq!(
+            days,
+            ["2025-02-27", "2025-02-28", "2025-03-01", "2025-03-02"]
+        );
+
+        let (start, end) = "2025-01-01T12:00:00Z UTC,2025-01-02T06:00:00Z UTC"
+            .parse::<DateSpan>()
+            .unwrap()
+            .resolve(Tz::UTC);
+        assert_eq!(days_between(start, end).count(), 2);
+    }
+}
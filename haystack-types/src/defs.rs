@@ -0,0 +1,492 @@
+//! The def namespace of a server or library, answering questions about the Haystack ontology
+
+use crate::NumTrait;
+use crate::err::ZincParseErr;
+use crate::h_dict::HDict;
+use crate::h_grid::HGrid;
+use crate::io::parse::{trio, zinc};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DefsErr {
+    ParseErr(ZincParseErr),
+    ErrGrid(String),
+    MissingDef(usize),
+    DuplicateDef(String),
+}
+
+impl Display for DefsErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefsErr::ParseErr(e) => write!(f, "Error: Invalid defs: {}", e),
+            DefsErr::ErrGrid(dis) => write!(f, "Error: Defs request failed: {}", dis),
+            DefsErr::MissingDef(idx) => write!(f, "Error: Record {} has no def symbol", idx),
+            DefsErr::DuplicateDef(name) => write!(f, "Error: Duplicate def {:?}", name),
+        }
+    }
+}
+
+impl Error for DefsErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DefsErr::ParseErr(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ZincParseErr> for DefsErr {
+    fn from(e: ZincParseErr) -> Self {
+        DefsErr::ParseErr(e)
+    }
+}
+
+/// The symbols of a tag holding either one symbol or a list of them
+fn symbols<'d, 'a, T: NumTrait + 'a>(def: &'d HDict<'a, T>, tag: &str) -> Vec<&'d str> {
+    let Some(val) = def.get(tag) else {
+        return vec![];
+    };
+    match val.get_list() {
+        Some(list) => list
+            .iter()
+            .filter_map(|item| Some(item.get_symbol()?.as_str()))
+            .collect(),
+        None => val
+            .get_symbol()
+            .map(|sym| sym.as_str())
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Defs keyed by their symbol, with the inverse of `is` and `tagOn` indexed on load
+#[derive(Clone)]
+pub struct Namespace<'a, T: NumTrait + 'a> {
+    defs: HashMap<String, HDict<'a, T>>,
+    subtypes: HashMap<String, Vec<String>>,
+    tags_on: HashMap<String, Vec<String>>,
+}
+
+impl<'a, T: NumTrait + 'a> Namespace<'a, T> {
+    /// Each dict must have a `def` symbol, unique across the namespace
+    pub fn from_dicts(dicts: impl IntoIterator<Item = HDict<'a, T>>) -> Result<Self, DefsErr> {
+        let mut defs = HashMap::new();
+        for (idx, dict) in dicts.into_iter().enumerate() {
+            let name = match symbols(&dict, "def").as_slice() {
+                [name] => name.to_string(),
+                _ => return Err(DefsErr::MissingDef(idx)),
+            };
+            if defs.contains_key(&name) {
+                return Err(DefsErr::DuplicateDef(name));
+            }
+            defs.insert(name, dict);
+        }
+
+        let mut subtypes: HashMap<String, Vec<String>> = HashMap::new();
+        let mut tags_on: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, def) in defs.iter() {
+            for sup in symbols(def, "is") {
+                subtypes
+                    .entry(sup.to_owned())
+                    .or_default()
+                    .push(name.clone());
+            }
+            for entity in symbols(def, "tagOn") {
+                tags_on
+                    .entry(entity.to_owned())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+        for names in subtypes.values_mut().chain(tags_on.values_mut()) {
+            names.sort();
+        }
+
+        Ok(Self {
+            defs,
+            subtypes,
+            tags_on,
+        })
+    }
+
+    /// One def per row, as returned by the `defs` op
+    pub fn from_grid(grid: &HGrid<'a, T>) -> Result<Self, DefsErr> {
+        match grid {
+            HGrid::Grid { .. } => Self::from_dicts(grid.iter().map(|row| row.to_dict())),
+            HGrid::Empty { .. } => Self::from_dicts([]),
+            HGrid::Error { dis, .. } => Err(DefsErr::ErrGrid(dis.clone())),
+        }
+    }
+
+    pub fn from_trio(input: &str) -> Result<Self, DefsErr> {
+        let (_, grid) = trio::grid::<T>(input).map_err(|err| {
+            let pos = match err {
+                nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+                nom::Err::Incomplete(_) => &input[input.len()..],
+            };
+            ZincParseErr::new(input, pos, "a trio record")
+        })?;
+        Self::from_grid(&grid)
+    }
+
+    pub fn from_zinc(input: &str) -> Result<Self, DefsErr> {
+        let grid = zinc::parse_grid::<T>(input)?;
+        Self::from_grid(&grid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.defs.contains_key(name)
+    }
+
+    pub fn def(&self, name: &str) -> Option<&HDict<'a, T>> {
+        self.defs.get(name)
+    }
+
+    /// Every def name in sorted order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.defs.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// The direct supertypes named by `is`
+    pub fn supertypes(&self, name: &str) -> Vec<&str> {
+        self.def(name)
+            .map(|def| symbols(def, "is"))
+            .unwrap_or_default()
+    }
+
+    /// The defs that name `name` in their `is`
+    pub fn subtypes(&self, name: &str) -> Vec<&str> {
+        self.subtypes
+            .get(name)
+            .map(|names| names.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// `name` followed by all its supertypes, nearest first. Empty if `name` isn't defined.
+    pub fn inheritance(&self, name: &str) -> Vec<&str> {
+        let Some((name, _)) = self.defs.get_key_value(name) else {
+            return vec![];
+        };
+        let mut seen = vec![name.as_str()];
+        let mut queue = VecDeque::from([name.as_str()]);
+        while let Some(next) = queue.pop_front() {
+            for sup in self.supertypes(next) {
+                if !seen.contains(&sup) {
+                    seen.push(sup);
+                    queue.push_back(sup);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Whether `name` is `sup` or inherits from it, ie. `ahu` is `equip`
+    pub fn is(&self, name: &str, sup: &str) -> bool {
+        self.inheritance(name).contains(&sup)
+    }
+
+    /// All defs inheriting from `name`, in sorted order
+    fn descendants(&self, name: &str) -> Vec<&str> {
+        let mut found = BTreeSet::new();
+        let mut queue = VecDeque::from([name]);
+        while let Some(next) = queue.pop_front() {
+            for sub in self.subtypes(next) {
+                if found.insert(sub) {
+                    queue.push_back(sub);
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    /// The entities a tag def declares it applies to with `tagOn`
+    pub fn tag_on(&self, name: &str) -> Vec<&str> {
+        self.def(name)
+            .map(|def| symbols(def, "tagOn"))
+            .unwrap_or_default()
+    }
+
+    /// The tags that apply to an entity, including those declared on its supertypes
+    pub fn tags(&self, entity: &str) -> Vec<&str> {
+        let tags: BTreeSet<_> = self
+            .inheritance(entity)
+            .into_iter()
+            .filter_map(|name| self.tags_on.get(name))
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        tags.into_iter().collect()
+    }
+
+    pub fn is_conjunct(&self, name: &str) -> bool {
+        name.contains('-')
+    }
+
+    /// The tags a conjunct like `hot-water` combines, empty for other defs
+    pub fn conjunct_parts<'n>(&self, name: &'n str) -> Vec<&'n str> {
+        match self.is_conjunct(name) {
+            true => name.split('-').collect(),
+            false => vec![],
+        }
+    }
+
+    /// The target entity of a ref or choice def
+    pub fn of(&self, name: &str) -> Option<&str> {
+        self.def(name)?
+            .get("of")?
+            .get_symbol()
+            .map(|sym| sym.as_str())
+    }
+
+    /// The entity a ref def points up to in the containment hierarchy, ie. `siteRef` to `site`
+    pub fn contained_by(&self, name: &str) -> Option<&str> {
+        self.def(name)?
+            .get("containedBy")?
+            .get_symbol()
+            .map(|sym| sym.as_str())
+    }
+
+    /// The ref tags that apply to an entity
+    pub fn refs(&self, entity: &str) -> Vec<&str> {
+        self.tags(entity)
+            .into_iter()
+            .filter(|tag| self.is(tag, "ref"))
+            .collect()
+    }
+
+    /// The options of a choice def, ie. every subtype of its `of`. Empty for other defs.
+    pub fn choices(&self, name: &str) -> Vec<&str> {
+        match self.of(name) {
+            Some(of) if self.is(name, "choice") => self.descendants(of),
+            _ => vec![],
+        }
+    }
+
+    /// The defs an entity implements through its tags, with conjuncts matched by all their parts
+    pub fn reflect(&self, entity: &HDict<'a, T>) -> Vec<&str> {
+        let mut found: Vec<_> = self
+            .defs
+            .keys()
+            .map(String::as_str)
+            .filter(|name| match self.is_conjunct(name) {
+                true => name.split('-').all(|part| entity.has(part)),
+                false => entity.has(name),
+            })
+            .collect();
+        found.sort();
+        found
+    }
+
+    /// Whether an entity implements `name` or one of its subtypes, ie. an `ahu` fits `equip`
+    pub fn fits(&self, entity: &HDict<'a, T>, name: &str) -> bool {
+        self.reflect(entity)
+            .into_iter()
+            .any(|found| self.is(found, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_marker::HMarker;
+    use crate::h_symbol::HSymbol;
+    use crate::h_val::Ptr;
+
+    const DEFS: &str = "def: ^marker
+---
+def: ^entity
+is: ^marker
+---
+def: ^equip
+is: ^entity
+---
+def: ^airHandlingEquip
+is: ^equip
+---
+def: ^ahu
+is: ^airHandlingEquip
+---
+def: ^site
+is: ^entity
+---
+def: ^area
+tagOn: [^site]
+---
+def: ^dis
+tagOn: [^entity]
+---
+def: ^ref
+---
+def: ^siteRef
+is: ^ref
+of: ^site
+tagOn: [^equip]
+containedBy: ^site
+---
+def: ^substance
+is: ^marker
+---
+def: ^fluid
+is: ^substance
+---
+def: ^water
+is: ^fluid
+---
+def: ^air
+is: ^fluid
+---
+def: ^hot
+is: ^marker
+---
+def: ^hot-water
+is: ^water
+---
+def: ^choice
+---
+def: ^pipe
+is: ^equip
+---
+def: ^pipeFluid
+is: ^choice
+of: ^fluid
+tagOn: [^pipe]
+";
+
+    fn ns() -> Namespace<'static, f64> {
+        Namespace::from_trio(DEFS).unwrap()
+    }
+
+    fn entity(tags: &[&str]) -> HDict<'static, f64> {
+        let mut dict = HDict::new();
+        for tag in tags {
+            dict.set(tag.to_string(), Ptr::new(HMarker));
+        }
+        dict
+    }
+
+    #[test]
+    fn load_defs() {
+        let ns = ns();
+        assert_eq!(ns.len(), 19);
+        assert!(ns.has("hot-water"));
+        assert!(!ns.has("vav"));
+        assert_eq!(ns.names()[..3], ["ahu", "air", "airHandlingEquip"]);
+
+        let zinc = "ver:\"3.0\"\ndef,is\n^equip,\n^ahu,^equip\n";
+        let ns = Namespace::<f64>::from_zinc(zinc).unwrap();
+        assert!(ns.is("ahu", "equip"));
+    }
+
+    #[test]
+    fn load_errors() {
+        let grid = zinc::parse_grid::<f64>("ver:\"3.0\"\ndef,is\n^equip,\n,^equip\n").unwrap();
+        assert_eq!(
+            Namespace::from_grid(&grid).err(),
+            Some(DefsErr::MissingDef(1))
+        );
+
+        let dup = "def: ^equip\n---\ndef: ^equip\n";
+        assert_eq!(
+            Namespace::<f64>::from_trio(dup).err(),
+            Some(DefsErr::DuplicateDef("equip".to_owned()))
+        );
+
+        let err: HGrid<f64> = HGrid::Error {
+            dis: "Not allowed".to_owned(),
+            errTrace: None,
+        };
+        assert_eq!(
+            Namespace::from_grid(&err).err(),
+            Some(DefsErr::ErrGrid("Not allowed".to_owned()))
+        );
+
+        assert!(matches!(
+            Namespace::<f64>::from_trio("def: ^equip\n---\ndef ^ahu\n"),
+            Err(DefsErr::ParseErr(ZincParseErr {
+                line: 3,
+                col: 4,
+                ..
+            }))
+        ));
+        let err = Namespace::<f64>::from_zinc("ver:\"3.0\"\ndef\n^equip,^ahu\n").err();
+        assert!(matches!(
+            err,
+            Some(DefsErr::ParseErr(ZincParseErr { line: 3, .. }))
+        ));
+        assert!(err.unwrap().source().is_some());
+    }
+
+    #[test]
+    fn inheritance() {
+        let ns = ns();
+        assert_eq!(
+            ns.inheritance("ahu"),
+            ["ahu", "airHandlingEquip", "equip", "entity", "marker"]
+        );
+        assert!(ns.is("ahu", "equip"));
+        assert!(ns.is("ahu", "ahu"));
+        assert!(!ns.is("equip", "ahu"));
+        assert!(!ns.is("vav", "equip"));
+        assert_eq!(ns.supertypes("hot-water"), ["water"]);
+        assert_eq!(ns.subtypes("equip"), ["airHandlingEquip", "pipe"]);
+    }
+
+    #[test]
+    fn tags_on_entities() {
+        let ns = ns();
+        assert_eq!(ns.tag_on("siteRef"), ["equip"]);
+        assert_eq!(ns.tags("ahu"), ["dis", "siteRef"]);
+        assert_eq!(ns.tags("site"), ["area", "dis"]);
+        assert_eq!(ns.tags("pipe"), ["dis", "pipeFluid", "siteRef"]);
+        assert!(ns.tags("vav").is_empty());
+    }
+
+    #[test]
+    fn conjuncts_and_choices() {
+        let ns = ns();
+        assert!(ns.is_conjunct("hot-water"));
+        assert_eq!(ns.conjunct_parts("hot-water"), ["hot", "water"]);
+        assert!(ns.conjunct_parts("water").is_empty());
+
+        assert_eq!(ns.choices("pipeFluid"), ["air", "hot-water", "water"]);
+        assert!(ns.choices("fluid").is_empty());
+    }
+
+    #[test]
+    fn refs() {
+        let ns = ns();
+        assert_eq!(ns.of("siteRef"), Some("site"));
+        assert_eq!(ns.of("pipeFluid"), Some("fluid"));
+        assert_eq!(ns.contained_by("siteRef"), Some("site"));
+        assert_eq!(ns.contained_by("equip"), None);
+        assert_eq!(ns.refs("ahu"), ["siteRef"]);
+        assert!(ns.refs("site").is_empty());
+    }
+
+    #[test]
+    fn reflect_entities() {
+        let ns = ns();
+        let mut ahu = entity(&["ahu", "equip", "hot", "water"]);
+        ahu.set("dis".to_owned(), Ptr::new(HSymbol::new("AHU-1".to_owned())));
+        assert_eq!(
+            ns.reflect(&ahu),
+            ["ahu", "dis", "equip", "hot", "hot-water", "water"]
+        );
+        assert!(ns.fits(&ahu, "airHandlingEquip"));
+        assert!(ns.fits(&ahu, "fluid"));
+        assert!(!ns.fits(&ahu, "site"));
+        assert!(!ns.fits(&entity(&["hot", "air"]), "water"));
+    }
+}
@@ -1,3 +1,160 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum HError {
-    ParseZincErr,
+    ParseZincErr(ZincParseErr),
+    UnitErr(UnitErr),
+}
+
+impl Display for HError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HError::ParseZincErr(e) => e.fmt(f),
+            HError::UnitErr(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for HError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HError::ParseZincErr(e) => Some(e),
+            HError::UnitErr(e) => Some(e),
+        }
+    }
+}
+
+impl From<ZincParseErr> for HError {
+    fn from(e: ZincParseErr) -> Self {
+        HError::ParseZincErr(e)
+    }
+}
+
+impl From<UnitErr> for HError {
+    fn from(e: UnitErr) -> Self {
+        HError::UnitErr(e)
+    }
+}
+
+const SNIPPET_LEN: usize = 40;
+
+/// Where and why zinc failed to parse, lines and columns count from 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZincParseErr {
+    pub line: usize,
+    pub col: usize,
+    /// The input from the point of failure to the end of that line
+    pub snippet: String,
+    pub expected: String,
+}
+
+impl ZincParseErr {
+    /// `pos` is the remaining input at the point of failure and must be a slice of `input`
+    pub fn new(input: &str, pos: &str, expected: impl Into<String>) -> Self {
+        let offset = (pos.as_ptr() as usize)
+            .checked_sub(input.as_ptr() as usize)
+            .filter(|offset| *offset <= input.len() && input.is_char_boundary(*offset))
+            .unwrap_or(0);
+        let consumed = &input[..offset];
+        let line_start = consumed.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+
+        Self {
+            line: consumed.matches('\n').count() + 1,
+            col: consumed[line_start..].chars().count() + 1,
+            snippet: input[offset..]
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(SNIPPET_LEN)
+                .collect(),
+            expected: expected.into(),
+        }
+    }
+
+    /// Shifts the position down by `lines`, for input that was read part way through a document
+    pub fn offset_lines(mut self, lines: usize) -> Self {
+        self.line += lines;
+        self
+    }
+}
+
+impl Display for ZincParseErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: Invalid zinc at line {}, column {}: expected {}",
+            self.line, self.col, self.expected
+        )?;
+        match self.snippet.is_empty() {
+            true => write!(f, ", found end of input"),
+            false => write!(f, ", found {:?}", self.snippet),
+        }
+    }
+}
+
+impl Error for ZincParseErr {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnitErr {
+    /// The unit is not in the units database
+    Unknown(String),
+    /// The units measure different quantities
+    Incompatible(String, String),
+    /// The number has no unit to convert from
+    Unitless,
+    /// No unit in the database measures the product or quotient, ie. `kW*m`
+    NoDerivedUnit(String),
+}
+
+impl Display for UnitErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitErr::Unknown(unit) => write!(f, "Error: Unknown unit {:?}", unit),
+            UnitErr::Incompatible(from, to) => {
+                write!(f, "Error: Cannot convert {:?} to {:?}", from, to)
+            }
+            UnitErr::Unitless => write!(f, "Error: Number has no unit"),
+            UnitErr::NoDerivedUnit(expr) => write!(f, "Error: No unit for {:?}", expr),
+        }
+    }
+}
+
+impl Error for UnitErr {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn err_position() {
+        let input = "ver:\"3.0\"\na,b\n1,\"unterminated\n";
+        let err = ZincParseErr::new(input, &input[16..], "a value");
+        assert_eq!((err.line, err.col), (3, 3));
+        assert_eq!(err.snippet, "\"unterminated");
+        assert_eq!(
+            err.to_string(),
+            "Error: Invalid zinc at line 3, column 3: expected a value, found \"\\\"unterminated\""
+        );
+    }
+
+    #[test]
+    fn err_position_counts_chars() {
+        let input = "a:\"°C\" b:?";
+        let err = ZincParseErr::new(input, &input[input.len() - 1..], "a value");
+        assert_eq!((err.line, err.col), (1, 10));
+        assert_eq!(err.offset_lines(4).line, 5);
+    }
+
+    #[test]
+    fn err_at_end_of_input() {
+        let input = "{a:1";
+        let err = HError::from(ZincParseErr::new(input, &input[4..], "'}'"));
+        assert_eq!(
+            err.to_string(),
+            "Error: Invalid zinc at line 1, column 5: expected '}', found end of input"
+        );
+        assert!(err.source().is_some());
+    }
 }
@@ -0,0 +1,232 @@
+use std::cmp::Ordering;
+
+use super::{CmpOp, HFilter, Path, Resolver};
+use crate::h_dict::HDict;
+use crate::h_grid::HRow;
+use crate::h_val::{HBox, HVal};
+use crate::{HType, NumTrait};
+
+pub trait Entity<'a, T: NumTrait> {
+    fn get_tag(&self, name: &str) -> Option<HBox<'a, T>>;
+}
+
+impl<'a, T: NumTrait + 'a> Entity<'a, T> for HDict<'a, T> {
+    fn get_tag(&self, name: &str) -> Option<HBox<'a, T>> {
+        self.get(name).cloned()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Entity<'a, T> for HRow<'a, T> {
+    fn get_tag(&self, name: &str) -> Option<HBox<'a, T>> {
+        self.get(name)
+    }
+}
+
+impl<'a, T: NumTrait + 'a> HFilter<'a, T> {
+    /// Paths containing `->` never match, use `matches_with` to dereference them
+    pub fn matches<E: Entity<'a, T> + ?Sized>(&self, entity: &E) -> bool {
+        self.eval(entity, None)
+    }
+
+    pub fn matches_with<E: Entity<'a, T> + ?Sized>(
+        &self,
+        entity: &E,
+        resolver: &dyn Resolver<'a, T>,
+    ) -> bool {
+        self.eval(entity, Some(resolver))
+    }
+
+    fn eval<E: Entity<'a, T> + ?Sized>(
+        &self,
+        entity: &E,
+        resolver: Option<&dyn Resolver<'a, T>>,
+    ) -> bool {
+        match self {
+            HFilter::Has(path) => resolve_path(entity, path, resolver).is_some(),
+            HFilter::Missing(path) => resolve_path(entity, path, resolver).is_none(),
+            HFilter::Cmp(path, op, val) => match resolve_path(entity, path, resolver) {
+                Some(tag) => cmp_vals(op, tag.as_ref(), val.as_ref()),
+                None => false,
+            },
+            HFilter::And(lhs, rhs) => lhs.eval(entity, resolver) && rhs.eval(entity, resolver),
+            HFilter::Or(lhs, rhs) => lhs.eval(entity, resolver) || rhs.eval(entity, resolver),
+        }
+    }
+}
+
+fn resolve_path<'a, T, E>(
+    entity: &E,
+    path: &Path,
+    resolver: Option<&dyn Resolver<'a, T>>,
+) -> Option<HBox<'a, T>>
+where
+    T: NumTrait + 'a,
+    E: Entity<'a, T> + ?Sized,
+{
+    let not_null = |v: &HBox<'a, T>| v.haystack_type() != HType::Null;
+    let mut names = path.names().iter();
+    let mut val = entity.get_tag(names.next()?).filter(not_null)?;
+
+    for name in names {
+        let rec = resolver?.resolve(val.get_ref()?)?;
+        val = rec.get_tag(name).filter(not_null)?;
+    }
+    Some(val)
+}
+
+fn cmp_vals<'a, T: NumTrait + 'a>(
+    op: &CmpOp,
+    lhs: &dyn HVal<'a, T>,
+    rhs: &dyn HVal<'a, T>,
+) -> bool {
+    match op {
+        CmpOp::Eq => vals_eq(lhs, rhs),
+        CmpOp::Ne => !vals_eq(lhs, rhs),
+        CmpOp::Lt => vals_ord(lhs, rhs) == Some(Ordering::Less),
+        CmpOp::Le => matches!(
+            vals_ord(lhs, rhs),
+            Some(Ordering::Less) | Some(Ordering::Equal)
+        ),
+        CmpOp::Gt => vals_ord(lhs, rhs) == Some(Ordering::Greater),
+        CmpOp::Ge => matches!(
+            vals_ord(lhs, rhs),
+            Some(Ordering::Greater) | Some(Ordering::Equal)
+        ),
+    }
+}
+
+fn vals_eq<'a, T: NumTrait + 'a>(lhs: &dyn HVal<'a, T>, rhs: &dyn HVal<'a, T>) -> bool {
+    // Refs are identified by id alone, the display string is informational
+    match (lhs.get_ref(), rhs.get_ref()) {
+        (Some(l), Some(r)) => l.id == r.id,
+        _ => lhs.haystack_type() == rhs.haystack_type() && lhs._eq(rhs),
+    }
+}
+
+fn vals_ord<'a, T: NumTrait + 'a>(
+    lhs: &dyn HVal<'a, T>,
+    rhs: &dyn HVal<'a, T>,
+) -> Option<Ordering> {
+    if lhs.haystack_type() != rhs.haystack_type() {
+        return None;
+    }
+
+    match lhs.haystack_type() {
+        HType::Bool => Some(lhs.get_bool()?.0.cmp(&rhs.get_bool()?.0)),
+        HType::Number => {
+            let (l, r) = (lhs.get_number()?, rhs.get_number()?);
+            match (l.unit(), r.unit()) {
+                (Some(lu), Some(ru)) if lu != ru => None,
+                _ => l.val().partial_cmp(&r.val()),
+            }
+        }
+        HType::Str => Some(lhs.get_string()?.as_str().cmp(rhs.get_string()?.as_str())),
+        HType::Uri => Some(
+            lhs.get_uri()?
+                .to_owned_string()
+                .cmp(&rhs.get_uri()?.to_owned_string()),
+        ),
+        HType::Ref => Some(lhs.get_ref()?.id.cmp(&rhs.get_ref()?.id)),
+        HType::Date => Some(lhs.get_date()?.val().cmp(&rhs.get_date()?.val())),
+        HType::Time => Some(lhs.get_time()?.val().cmp(&rhs.get_time()?.val())),
+        HType::DateTime => {
+            let (l, r) = (lhs.get_datetime()?, rhs.get_datetime()?);
+            let l_utc = l.val() - l.offset();
+            let r_utc = r.val() - r.offset();
+            Some(l_utc.cmp(&r_utc))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Filter;
+    use crate::h_grid::HGrid;
+    use crate::h_marker::MARKER;
+    use crate::h_null::NULL;
+    use crate::h_number::HNumber;
+    use crate::h_ref::HRef;
+    use crate::h_str::HStr;
+    use std::collections::HashMap;
+
+    fn point() -> HDict<'static, f64> {
+        let mut dict = HDict::new();
+        dict.set("point".into(), MARKER.to_hbox());
+        dict.set("his".into(), MARKER.to_hbox());
+        dict.set("unit".into(), HStr::new("kWh".into()).to_hbox());
+        dict.set(
+            "curVal".into(),
+            HNumber::new(42.5, Some("kWh".to_owned().into())).to_hbox(),
+        );
+        dict.set(
+            "equipRef".into(),
+            HRef::new("e1".into(), Some("AHU-1".into())).to_hbox(),
+        );
+        dict.set("disabled".into(), NULL.to_hbox());
+        dict
+    }
+
+    fn matches(filter: &str, dict: &HDict<'static, f64>) -> bool {
+        Filter::<f64>::parse(filter).unwrap().matches(dict)
+    }
+
+    #[test]
+    fn eval_has_missing() {
+        let dict = point();
+        assert!(matches("point", &dict));
+        assert!(matches("point and his", &dict));
+        assert!(!matches("point and not his", &dict));
+        assert!(matches("not sensor", &dict));
+        assert!(matches("not disabled", &dict));
+        assert!(!matches("disabled", &dict));
+    }
+
+    #[test]
+    fn eval_cmp() {
+        let dict = point();
+        assert!(matches("unit == \"kWh\"", &dict));
+        assert!(!matches("unit != \"kWh\"", &dict));
+        assert!(matches("curVal > 40kWh and curVal <= 42.5kWh", &dict));
+        assert!(matches("curVal < 50", &dict));
+        assert!(!matches("curVal < 50kW", &dict));
+        assert!(!matches("curVal > \"40\"", &dict));
+        assert!(matches("equipRef == @e1", &dict));
+        assert!(!matches("missingTag == \"kWh\"", &dict));
+    }
+
+    #[test]
+    fn eval_or() {
+        let dict = point();
+        assert!(matches("sensor or his", &dict));
+        assert!(!matches("sensor or cmd", &dict));
+        assert!(matches("(sensor or his) and unit == \"kWh\"", &dict));
+    }
+
+    #[test]
+    fn eval_path_without_resolver() {
+        let dict = point();
+        assert!(!matches("equipRef->siteRef", &dict));
+        assert!(matches("not equipRef->siteRef", &dict));
+    }
+
+    #[test]
+    fn eval_row() {
+        let mut row_1: HashMap<String, HBox<f64>> = HashMap::new();
+        row_1.insert("point".into(), MARKER.to_hbox());
+        row_1.insert("dis".into(), HStr::new("Point 1".into()).to_hbox());
+        let mut row_2: HashMap<String, HBox<f64>> = HashMap::new();
+        row_2.insert("equip".into(), MARKER.to_hbox());
+        row_2.insert("dis".into(), HStr::new("Equip 1".into()).to_hbox());
+
+        let grid = HGrid::new(None, vec![row_1, row_2]);
+        let filter = Filter::<f64>::parse("point and dis").unwrap();
+        let matched: Vec<_> = grid.iter().filter(|r| filter.matches(r)).collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(
+            matched[0].get("dis").unwrap().get_string(),
+            Some(&HStr("Point 1".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::h_val::HBox;
+use crate::{HType, NumTrait};
+use std::fmt::{self, Display};
+
+pub mod eval;
+pub use eval::Entity;
+
+pub mod parse;
+
+pub mod resolve;
+pub use resolve::{GridResolver, Resolver};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path(Vec<String>);
+
+impl Path {
+    pub fn new(names: Vec<String>) -> Self {
+        Path(names)
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.0
+    }
+
+    pub fn first(&self) -> &str {
+        &self.0[0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join("->"))
+    }
+}
+
+impl From<&str> for Path {
+    fn from(value: &str) -> Self {
+        Path(value.split("->").map(|s| s.to_owned()).collect())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+#[derive(Clone)]
+pub enum HFilter<'a, T: NumTrait> {
+    Has(Path),
+    Missing(Path),
+    Cmp(Path, CmpOp, HBox<'a, T>),
+    And(Box<HFilter<'a, T>>, Box<HFilter<'a, T>>),
+    Or(Box<HFilter<'a, T>>, Box<HFilter<'a, T>>),
+}
+
+pub type Filter<'a, T> = HFilter<'a, T>;
+
+#[derive(Debug)]
+pub enum HFilterErr {
+    ParseErr(String),
+    TrailingInput(String),
+}
+
+impl Display for HFilterErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HFilterErr::ParseErr(e) => write!(f, "Error: Invalid filter: {}", e),
+            HFilterErr::TrailingInput(rest) => {
+                write!(f, "Error: Unexpected input after filter: {:?}", rest)
+            }
+        }
+    }
+}
+
+impl<'a, T: NumTrait + 'a> HFilter<'a, T> {
+    pub fn parse(input: &str) -> Result<Self, HFilterErr> {
+        use nom::Parser;
+        use nom::character::complete::multispace0;
+        use nom::sequence::terminated;
+
+        let (rest, filter) = terminated(parse::filter::<T>, multispace0)
+            .parse(input)
+            .map_err(|e| HFilterErr::ParseErr(e.to_string()))?;
+
+        if !rest.is_empty() {
+            return Err(HFilterErr::TrailingInput(rest.to_owned()));
+        }
+        Ok(filter)
+    }
+
+    pub fn has(path: &str) -> Self {
+        HFilter::Has(path.into())
+    }
+
+    pub fn missing(path: &str) -> Self {
+        HFilter::Missing(path.into())
+    }
+
+    pub fn cmp(path: &str, op: CmpOp, val: HBox<'a, T>) -> Self {
+        HFilter::Cmp(path.into(), op, val)
+    }
+
+    pub fn and(self, other: Self) -> Self {
+        HFilter::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Self) -> Self {
+        HFilter::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HFilter::Has(path) => path.to_zinc(f),
+            HFilter::Missing(path) => {
+                write!(f, "not ")?;
+                path.to_zinc(f)
+            }
+            HFilter::Cmp(path, op, val) => {
+                path.to_zinc(f)?;
+                write!(f, " {} ", op)?;
+                match val.haystack_type() {
+                    HType::Bool => write!(f, "{}", val.get_bool().unwrap().0),
+                    HType::Ref => write!(f, "@{}", val.get_ref().unwrap().id),
+                    _ => val.to_zinc(f),
+                }
+            }
+            HFilter::And(lhs, rhs) => {
+                lhs.to_zinc_operand(f)?;
+                write!(f, " and ")?;
+                rhs.to_zinc_operand(f)
+            }
+            HFilter::Or(lhs, rhs) => {
+                lhs.to_zinc(f)?;
+                write!(f, " or ")?;
+                rhs.to_zinc(f)
+            }
+        }
+    }
+
+    fn to_zinc_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let HFilter::Or(..) = self {
+            write!(f, "(")?;
+            self.to_zinc(f)?;
+            write!(f, ")")
+        } else {
+            self.to_zinc(f)
+        }
+    }
+}
+
+impl<'a, T: NumTrait> fmt::Debug for HFilter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HFilter({})", crate::io::write::ZincWriter::new(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::write::ZincWriter;
+    use crate::{HVal, h_number::HNumber, h_str::HStr};
+    use std::fmt::Write;
+
+    fn to_zinc(filter: &Filter<f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(filter)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_path() {
+        let path = Path::from("equipRef->siteRef->geoCity");
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.first(), "equipRef");
+        assert_eq!(path.names()[2], "geoCity");
+    }
+
+    #[test]
+    fn test_write_has_and_missing() {
+        let filter = Filter::<f64>::has("point").and(Filter::missing("his"));
+        assert_eq!(to_zinc(&filter), "point and not his");
+    }
+
+    #[test]
+    fn test_write_cmp() {
+        let filter = Filter::<f64>::cmp("unit", CmpOp::Eq, HStr::new("kWh".into()).to_hbox()).and(
+            Filter::cmp(
+                "equipRef->siteRef->area",
+                CmpOp::Gt,
+                HNumber::new(1000f64, Some("m²".to_owned().into())).to_hbox(),
+            ),
+        );
+        assert_eq!(
+            to_zinc(&filter),
+            "unit == \"kWh\" and equipRef->siteRef->area > 1000m²"
+        );
+    }
+
+    #[test]
+    fn test_write_precedence() {
+        let filter = Filter::<f64>::has("a")
+            .or(Filter::has("b"))
+            .and(Filter::has("c"));
+        assert_eq!(to_zinc(&filter), "(a or b) and c");
+
+        let filter = Filter::<f64>::has("a").or(Filter::has("b").and(Filter::has("c")));
+        assert_eq!(to_zinc(&filter), "a or b and c");
+    }
+}
@@ -0,0 +1,241 @@
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{multispace0, multispace1, satisfy};
+use nom::combinator::{map, not, peek, value, verify};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, preceded, terminated};
+use nom::{IResult, Parser};
+
+use super::{CmpOp, HFilter, Path};
+use crate::NumTrait;
+use crate::common::id;
+use crate::h_bool::HBool;
+use crate::h_val::{HBox, Ptr};
+use crate::io::ParseHint;
+use crate::io::parse::zinc::{datetime, reference, string, symbol, uri};
+use crate::io::parse::{date, number, time};
+
+const KEYWORDS: [&str; 5] = ["and", "or", "not", "true", "false"];
+
+fn keyword<'i>(
+    kw: &'static str,
+) -> impl Parser<&'i str, Output = &'i str, Error = nom::error::Error<&'i str>> {
+    terminated(
+        tag(kw),
+        not(peek(satisfy(|c: char| {
+            c.is_ascii_alphanumeric() || c == '_'
+        }))),
+    )
+}
+
+pub fn name(input: &str) -> IResult<&str, &str> {
+    verify(id, |s: &str| !KEYWORDS.contains(&s)).parse(input)
+}
+
+pub fn path(input: &str) -> IResult<&str, Path> {
+    map(separated_list1(tag("->"), name), |names| {
+        Path::new(names.into_iter().map(|s| s.to_owned()).collect())
+    })
+    .parse(input)
+}
+
+pub fn cmp_op(input: &str) -> IResult<&str, CmpOp> {
+    alt((
+        value(CmpOp::Eq, tag("==")),
+        value(CmpOp::Ne, tag("!=")),
+        value(CmpOp::Le, tag("<=")),
+        value(CmpOp::Ge, tag(">=")),
+        value(CmpOp::Lt, tag("<")),
+        value(CmpOp::Gt, tag(">")),
+    ))
+    .parse(input)
+}
+
+pub fn val<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HBox<'out, T>> {
+    let mut parse_hint = ParseHint::default();
+    alt((
+        map(keyword("true"), |_| Ptr::new(HBool(true)) as HBox<'out, T>),
+        map(keyword("false"), |_| {
+            Ptr::new(HBool(false)) as HBox<'out, T>
+        }),
+        map(reference, |v| Ptr::new(v) as HBox<'out, T>),
+        map(symbol, |v| Ptr::new(v) as HBox<'out, T>),
+        map(string, |v| Ptr::new(v) as HBox<'out, T>),
+        map(uri, |v| Ptr::new(v) as HBox<'out, T>),
+        map(datetime(&mut parse_hint), |v| Ptr::new(v) as HBox<'out, T>),
+        map(date, |v| Ptr::new(v) as HBox<'out, T>),
+        map(time, |v| Ptr::new(v) as HBox<'out, T>),
+        map(number::<T>, |v| Ptr::new(v) as HBox<'out, T>),
+    ))
+    .parse(input)
+}
+
+fn parens<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    delimited(
+        terminated(tag("("), multispace0),
+        filter::<T>,
+        preceded(multispace0, tag(")")),
+    )
+    .parse(input)
+}
+
+fn missing<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    map(
+        preceded((keyword("not"), multispace1), path),
+        HFilter::Missing,
+    )
+    .parse(input)
+}
+
+fn cmp<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    map(
+        (path, delimited(multispace0, cmp_op, multispace0), val::<T>),
+        |(path, op, val)| HFilter::Cmp(path, op, val),
+    )
+    .parse(input)
+}
+
+fn has<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    map(path, HFilter::Has).parse(input)
+}
+
+fn term<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    preceded(
+        multispace0,
+        alt((parens::<T>, missing::<T>, cmp::<T>, has::<T>)),
+    )
+    .parse(input)
+}
+
+fn cond_and<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    let (input, first) = term::<T>(input)?;
+    let (input, rest) = many0(preceded((multispace0, keyword("and")), term::<T>)).parse(input)?;
+    Ok((input, rest.into_iter().fold(first, HFilter::and)))
+}
+
+fn cond_or<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    let (input, first) = cond_and::<T>(input)?;
+    let (input, rest) =
+        many0(preceded((multispace0, keyword("or")), cond_and::<T>)).parse(input)?;
+    Ok((input, rest.into_iter().fold(first, HFilter::or)))
+}
+
+pub fn filter<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HFilter<'out, T>> {
+    cond_or::<T>(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_number::{HNumber, HUnit};
+    use crate::h_ref::HRef;
+    use crate::h_str::HStr;
+    use crate::io::write::ZincWriter;
+    use crate::{Filter, HFilterErr};
+    use std::fmt::Write;
+
+    fn round_trip(input: &str) -> String {
+        let filter = Filter::<f64>::parse(input).unwrap();
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(&filter)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn parse_path() {
+        let (rest, p) = path("equipRef->siteRef->geoCity").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(p.names(), &["equipRef", "siteRef", "geoCity"]);
+    }
+
+    #[test]
+    fn parse_keyword_is_not_a_name() {
+        assert!(name("and").is_err());
+        assert_eq!(name("notes").unwrap(), ("", "notes"));
+    }
+
+    #[test]
+    fn parse_has() {
+        match filter::<f64>("point").unwrap() {
+            ("", HFilter::Has(p)) => assert_eq!(p.first(), "point"),
+            _ => panic!("Expected has filter"),
+        }
+    }
+
+    #[test]
+    fn parse_missing() {
+        match filter::<f64>("not his").unwrap() {
+            ("", HFilter::Missing(p)) => assert_eq!(p.first(), "his"),
+            _ => panic!("Expected missing filter"),
+        }
+    }
+
+    #[test]
+    fn parse_cmp_values() {
+        match filter::<f64>("unit==\"kWh\"").unwrap() {
+            ("", HFilter::Cmp(_, CmpOp::Eq, v)) => {
+                assert_eq!(v.get_string(), Some(&HStr("kWh".to_owned())))
+            }
+            _ => panic!("Expected cmp filter"),
+        }
+        match filter::<f64>("area >= 1000m²").unwrap() {
+            ("", HFilter::Cmp(_, CmpOp::Ge, v)) => assert_eq!(
+                v.get_number(),
+                Some(&HNumber::new(1000.0, Some(HUnit::new("m²".to_owned()))))
+            ),
+            _ => panic!("Expected cmp filter"),
+        }
+        match filter::<f64>("siteRef == @p:demo:r:1").unwrap() {
+            ("", HFilter::Cmp(_, CmpOp::Eq, v)) => {
+                assert_eq!(v.get_ref(), Some(&HRef::new("p:demo:r:1".to_owned(), None)))
+            }
+            _ => panic!("Expected cmp filter"),
+        }
+        match filter::<f64>("enabled != false").unwrap() {
+            ("", HFilter::Cmp(_, CmpOp::Ne, v)) => assert_eq!(v.get_bool(), Some(&HBool(false))),
+            _ => panic!("Expected cmp filter"),
+        }
+        match filter::<f64>("occupiedStart < 08:30:00").unwrap() {
+            ("", HFilter::Cmp(_, CmpOp::Lt, v)) => assert!(v.get_time().is_some()),
+            _ => panic!("Expected cmp filter"),
+        }
+        match filter::<f64>("installed > 2020-01-31").unwrap() {
+            ("", HFilter::Cmp(_, CmpOp::Gt, v)) => assert!(v.get_date().is_some()),
+            _ => panic!("Expected cmp filter"),
+        }
+    }
+
+    #[test]
+    fn parse_precedence() {
+        assert_eq!(round_trip("a or b and c"), "a or b and c");
+        assert_eq!(round_trip("(a or b) and c"), "(a or b) and c");
+        assert_eq!(round_trip("((a))"), "a");
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let input = "point and unit == \"kWh\" and not his";
+        assert_eq!(round_trip(input), input);
+        assert_eq!(
+            round_trip("equipRef->siteRef->geoCity==\"Richmond\""),
+            "equipRef->siteRef->geoCity == \"Richmond\""
+        );
+        assert_eq!(
+            round_trip("  point   and(ahu or  vav)  "),
+            "point and (ahu or vav)"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            Filter::<f64>::parse("point and"),
+            Err(HFilterErr::TrailingInput(_))
+        ));
+        assert!(matches!(
+            Filter::<f64>::parse("== 1"),
+            Err(HFilterErr::ParseErr(_))
+        ));
+        assert!(Filter::<f64>::parse("(point").is_err());
+    }
+}
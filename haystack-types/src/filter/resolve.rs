@@ -0,0 +1,150 @@
+use crate::NumTrait;
+use crate::h_dict::HDict;
+use crate::h_grid::HGrid;
+use crate::h_ref::HRef;
+use std::collections::HashMap;
+
+/// Looks up the record a ref points at when evaluating `->` filter paths
+pub trait Resolver<'a, T: NumTrait> {
+    fn resolve(&self, r: &HRef) -> Option<HDict<'a, T>>;
+}
+
+impl<'a, T: NumTrait + 'a> Resolver<'a, T> for HashMap<String, HDict<'a, T>> {
+    fn resolve(&self, r: &HRef) -> Option<HDict<'a, T>> {
+        self.get(&r.id).cloned()
+    }
+}
+
+/// In-memory resolver over the rows of a grid, keyed by their `id` tag
+pub struct GridResolver<'a, T: NumTrait> {
+    index: HashMap<String, HDict<'a, T>>,
+}
+
+impl<'a, T: NumTrait + 'a> GridResolver<'a, T> {
+    pub fn new(grid: &HGrid<'a, T>) -> Self {
+        let mut index = HashMap::new();
+
+        if !grid.is_empty() {
+            for row in grid.iter() {
+                if let Some(id) = row.get("id").as_ref().and_then(|v| v.get_ref()) {
+                    index.insert(id.id.clone(), row.to_dict());
+                }
+            }
+        }
+        GridResolver { index }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&HDict<'a, T>> {
+        self.index.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HDict<'a, T>> {
+        self.index.values()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Resolver<'a, T> for GridResolver<'a, T> {
+    fn resolve(&self, r: &HRef) -> Option<HDict<'a, T>> {
+        self.index.get(&r.id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Filter;
+    use crate::h_marker::MARKER;
+    use crate::h_number::HNumber;
+    use crate::h_str::HStr;
+    use crate::h_val::{HBox, HVal};
+
+    fn rec(tags: Vec<(&str, HBox<'static, f64>)>) -> HashMap<String, HBox<'static, f64>> {
+        tags.into_iter().map(|(k, v)| (k.to_owned(), v)).collect()
+    }
+
+    fn db() -> HGrid<'static, f64> {
+        let site = rec(vec![
+            ("id", HRef::new("s1".into(), Some("Site".into())).to_hbox()),
+            ("site", MARKER.to_hbox()),
+            (
+                "area",
+                HNumber::new(1500f64, Some("m²".to_owned().into())).to_hbox(),
+            ),
+            ("geoCity", HStr::new("Richmond".into()).to_hbox()),
+        ]);
+        let equip = rec(vec![
+            ("id", HRef::new("e1".into(), None).to_hbox()),
+            ("equip", MARKER.to_hbox()),
+            ("siteRef", HRef::new("s1".into(), None).to_hbox()),
+        ]);
+        let point = rec(vec![
+            ("id", HRef::new("p1".into(), None).to_hbox()),
+            ("point", MARKER.to_hbox()),
+            ("equipRef", HRef::new("e1".into(), None).to_hbox()),
+        ]);
+        let orphan = rec(vec![
+            ("id", HRef::new("p2".into(), None).to_hbox()),
+            ("point", MARKER.to_hbox()),
+            ("equipRef", HRef::new("missing".into(), None).to_hbox()),
+        ]);
+        HGrid::new(None, vec![site, equip, point, orphan])
+    }
+
+    #[test]
+    fn resolve_by_id() {
+        let grid = db();
+        let resolver = GridResolver::new(&grid);
+        assert_eq!(resolver.len(), 4);
+
+        let site = resolver.resolve(&HRef::new("s1".into(), None)).unwrap();
+        assert!(site.has("site"));
+        assert!(resolver.resolve(&HRef::new("nope".into(), None)).is_none());
+    }
+
+    #[test]
+    fn filter_ref_paths() {
+        let grid = db();
+        let resolver = GridResolver::new(&grid);
+        let query = |filter: &str| -> Vec<String> {
+            let filter = Filter::<f64>::parse(filter).unwrap();
+            let mut ids: Vec<String> = resolver
+                .iter()
+                .filter(|r| filter.matches_with(*r, &resolver))
+                .map(|r| r.get("id").unwrap().get_ref().unwrap().id.clone())
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(query("equipRef->siteRef->area > 1000m²"), vec!["p1"]);
+        assert_eq!(
+            query("equipRef->siteRef->geoCity == \"Richmond\""),
+            vec!["p1"]
+        );
+        assert_eq!(query("siteRef->site"), vec!["e1"]);
+        assert_eq!(query("point and not equipRef->equip"), vec!["p2"]);
+        assert!(query("equipRef->siteRef->area > 2000m²").is_empty());
+        // Only refs can be dereferenced
+        assert!(query("area->site").is_empty());
+    }
+
+    #[test]
+    fn filter_rows_with_resolver() {
+        let grid = db();
+        let resolver = GridResolver::new(&grid);
+        let filter = Filter::<f64>::parse("point and equipRef->siteRef").unwrap();
+        let matched = grid
+            .iter()
+            .filter(|r| filter.matches_with(r, &resolver))
+            .count();
+        assert_eq!(matched, 1);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::common::Txt;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
 use crate::{HType, HVal, NumTrait};
 use std::fmt;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
 pub struct HBool(pub bool);
 
 pub type Bool = HBool;
@@ -26,11 +28,20 @@ impl HBool {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, _version: JsonVersion) -> fmt::Result {
         match self.0 {
             true => write!(f, "{}", JSON_TRUE),
             false => write!(f, "{}", JSON_FALSE),
         }
     }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(match self.0 {
+            true => ctrl::TRUE,
+            false => ctrl::FALSE,
+        });
+    }
 }
 
 impl<'a, T: NumTrait + 'a> HVal<'a, T> for HBool {
@@ -39,6 +50,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HBool {
     }
 
     set_trait_eq_method!(get_bool,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -1,7 +1,12 @@
+use crate::h_number::{cast, float_cmp, float_eq, hash_float};
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
 use crate::{HType, HVal, NumTrait};
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub struct HCoord<T> {
     lat: T,
     long: T,
@@ -11,10 +16,43 @@ pub type Coord<T> = HCoord<T>;
 
 const THIS_TYPE: HType = HType::Coord;
 
+impl<T: NumTrait> PartialEq for HCoord<T> {
+    fn eq(&self, other: &Self) -> bool {
+        float_eq(self.lat, other.lat) && float_eq(self.long, other.long)
+    }
+}
+
+impl<T: NumTrait> Eq for HCoord<T> {}
+
+impl<T: NumTrait> PartialOrd for HCoord<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: NumTrait> Ord for HCoord<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        float_cmp(self.lat, other.lat).then_with(|| float_cmp(self.long, other.long))
+    }
+}
+
+impl<T: NumTrait> Hash for HCoord<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_float(self.lat, state);
+        hash_float(self.long, state);
+    }
+}
+
 impl<T: NumTrait> HCoord<T> {
     pub fn new(lat: T, long: T) -> HCoord<T> {
         HCoord { lat, long }
     }
+    pub fn lat(&self) -> T {
+        self.lat
+    }
+    pub fn long(&self) -> T {
+        self.long
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "C({},{})", self.lat, self.long)
     }
@@ -22,7 +60,27 @@ impl<T: NumTrait> HCoord<T> {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "c:{},{}", self.lat, self.long)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        match version {
+            JsonVersion::V4 => write!(
+                f,
+                "{{\"_kind\":\"coord\",\"lat\":{},\"lng\":{}}}",
+                self.lat, self.long
+            ),
+            JsonVersion::V3 => write!(f, "\"c:{},{}\"", self.lat, self.long),
+        }
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        // Packed as millionths of a degree from the south pole and the antimeridian
+        let pack = |val: T, from: f64| {
+            let val: f64 = cast(val);
+            ((val + from) * 1_000_000.0).round() as i32
+        };
+        enc.u8(ctrl::COORD);
+        enc.i32(pack(self.lat, 90.0));
+        enc.i32(pack(self.long, 180.0));
     }
 }
 
@@ -32,6 +90,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HCoord<T> {
     }
 
     set_trait_eq_method!(get_coord,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -1,9 +1,12 @@
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use chrono::Datelike;
 use chrono::naive::NaiveDate;
 use std::fmt::{self, Display};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct HDate {
     inner: NaiveDate,
 }
@@ -33,6 +36,9 @@ impl HDate {
             inner: NaiveDate::from_ymd_opt(year, month, day).ok_or(HDateErr::InvalidDate)?,
         })
     }
+    pub fn val(&self) -> NaiveDate {
+        self.inner
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -46,8 +52,23 @@ impl HDate {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "d:")?;
-        self.to_zinc(f)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        let val = self.inner.format("%Y-%m-%d").to_string();
+        write_scalar(f, version, "date", &[("val", Some(&val))])
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::DATE);
+        enc.i16(self.inner.year() as i16);
+        enc.u8(self.inner.month() as u8);
+        enc.u8(self.inner.day() as u8);
+    }
+}
+
+impl From<NaiveDate> for HDate {
+    fn from(inner: NaiveDate) -> Self {
+        Self { inner }
     }
 }
 
@@ -57,6 +78,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HDate {
     }
 
     set_trait_eq_method!(get_date,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -1,19 +1,25 @@
 use crate::h_time::{HTime, HTimeErr};
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl, epoch};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
+use std::cmp::Ordering;
 use std::fmt::{self, Display};
 
 use crate::h_date::HDate;
 use chrono::offset::LocalResult;
-use chrono::{Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use chrono::{
+    Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike,
+};
 use chrono_tz::{OffsetComponents, Tz};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct HDateTime {
     inner: NaiveDateTime,
     tz: HTimezone,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct HTimezone {
     offset: chrono::FixedOffset,
     id: Tz,
@@ -92,9 +98,32 @@ impl HDateTime {
             .ok_or(TZError::TimeConstruction)?;
         Ok(Self { inner, tz })
     }
+
+    /// A local time in `tz` with the offset in effect at that time, failing for times skipped
+    /// by a DST change. Times repeated by a DST change resolve to the earlier instant.
+    pub fn from_local(inner: NaiveDateTime, tz: Tz) -> Result<Self, TZError> {
+        tz.from_local_datetime(&inner)
+            .earliest()
+            .map(HDateTime::from)
+            .ok_or(TZError::Nonexistent)
+    }
+
     pub fn val(&self) -> NaiveDateTime {
         self.inner
     }
+
+    fn utc(&self) -> NaiveDateTime {
+        self.inner - Duration::seconds(self.tz.offset.local_minus_utc() as i64)
+    }
+
+    /// The same instant in another timezone
+    pub fn to_tz(&self, tz: Tz) -> Self {
+        HDateTime::from(tz.from_utc_datetime(&self.utc()))
+    }
+
+    pub fn to_chrono(&self) -> chrono::DateTime<Tz> {
+        self.tz.id.from_utc_datetime(&self.utc())
+    }
     pub fn date(&self) -> Result<HDate, HDateTimeErr> {
         HDate::new(self.inner.year(), self.inner.month(), self.inner.day())
             .map_err(|_e| HDateTimeErr::ShouldNeverHappen)
@@ -157,29 +186,78 @@ impl HDateTime {
         self.tz.offset
     }
 
+    /// The date, time and offset, without the timezone name
+    pub(crate) fn iso(&self) -> String {
+        let offset = match self.tz.offset.local_minus_utc() {
+            0 => "Z".to_owned(),
+            _ => self.tz.offset.to_string(),
+        };
+        format!("{}{}", self.inner.format("%Y-%m-%dT%H:%M:%S%.f"), offset)
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{:0>4}-{:0>2}-{:0>2}T",
-            self.inner.year(),
-            self.inner.month(),
-            self.inner.day()
-        )?;
-        write!(
-            f,
-            "{:0>2}:{:0>2}:{:0>2}.{}",
-            self.inner.hour(),
-            self.inner.minute(),
-            self.inner.second(),
-            self.inner.nanosecond()
-        )
+        write!(f, "{} {}", self.iso(), crate::tz::name(self.tz.id))
     }
     pub fn to_trio(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "t:")?;
-        self.to_zinc(f)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        let val = self.iso();
+        let tz = crate::tz::name(self.tz.id);
+        write_scalar(
+            f,
+            version,
+            "dateTime",
+            &[("val", Some(&val)), ("tz", Some(tz))],
+        )
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        // Whole seconds fit in four bytes, anything finer is written in nanoseconds
+        let since = self.utc() - epoch();
+        match i32::try_from(since.num_seconds()) {
+            Ok(secs) if since.subsec_nanos() == 0 => {
+                enc.u8(ctrl::DATETIME_I4);
+                enc.i32(secs);
+            }
+            _ => {
+                enc.u8(ctrl::DATETIME_I8);
+                enc.i64(since.num_nanoseconds().unwrap_or(i64::MAX));
+            }
+        }
+        enc.str(crate::tz::name(self.tz.id));
+    }
+}
+
+impl PartialOrd for HDateTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders by the instant, then by timezone so the order agrees with equality
+impl Ord for HDateTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.utc()
+            .cmp(&other.utc())
+            .then_with(|| self.tz.id.name().cmp(other.tz.id.name()))
+            .then_with(|| self.inner.cmp(&other.inner))
+    }
+}
+
+impl From<chrono::DateTime<Tz>> for HDateTime {
+    fn from(dt: chrono::DateTime<Tz>) -> Self {
+        Self {
+            inner: dt.naive_local(),
+            tz: (dt.offset().fix(), dt.timezone()).into_timezone(),
+        }
+    }
+}
+
+impl From<&HDateTime> for chrono::DateTime<Tz> {
+    fn from(dt: &HDateTime) -> Self {
+        dt.to_chrono()
     }
 }
 
@@ -189,6 +267,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HDateTime {
     }
 
     set_trait_eq_method!(get_datetime,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -230,4 +309,50 @@ mod tests {
         let hval_type = HVal::<f64>::as_hval(&datetime);
         assert_eq!(hval_type.haystack_type(), HType::DateTime);
     }
+
+    fn naive(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_from_local_dst() {
+        let summer = HDateTime::from_local(naive("2024-07-01T12:00:00"), Tz::America__New_York);
+        assert_eq!(summer.unwrap().offset().local_minus_utc(), -4 * 3600);
+        let winter = HDateTime::from_local(naive("2024-01-01T12:00:00"), Tz::America__New_York);
+        let winter = winter.unwrap();
+        assert_eq!(winter.offset().local_minus_utc(), -5 * 3600);
+        assert!(!winter.is_dst());
+
+        let skipped = HDateTime::from_local(naive("2024-03-10T02:30:00"), Tz::America__New_York);
+        assert!(matches!(skipped, Err(TZError::Nonexistent)));
+    }
+
+    #[test]
+    fn test_to_tz() {
+        let ny =
+            HDateTime::from_local(naive("2024-07-01T12:00:00"), Tz::America__New_York).unwrap();
+        let london = ny.to_tz(Tz::Europe__London);
+        assert_eq!(london.val(), naive("2024-07-01T17:00:00"));
+        assert_eq!(london.offset().local_minus_utc(), 3600);
+        assert_eq!(london.tz_id(), Tz::Europe__London);
+        assert_ne!(london, ny);
+        assert_eq!(london.to_chrono(), ny.to_chrono());
+
+        // The offset follows DST in the target zone rather than the source
+        let winter = HDateTime::from_local(naive("2024-01-01T12:00:00"), Tz::America__New_York);
+        let sydney = winter.unwrap().to_tz(Tz::Australia__Sydney);
+        assert_eq!(sydney.val(), naive("2024-01-02T04:00:00"));
+        assert_eq!(sydney.offset().local_minus_utc(), 11 * 3600);
+    }
+
+    #[test]
+    fn test_chrono_round_trip() {
+        let dt = Tz::Europe__Berlin
+            .with_ymd_and_hms(2024, 10, 27, 2, 30, 0)
+            .latest()
+            .unwrap();
+        let hdt = HDateTime::from(dt);
+        assert_eq!(hdt.offset().local_minus_utc(), 3600);
+        assert_eq!(chrono::DateTime::<Tz>::from(&hdt), dt);
+    }
 }
@@ -1,7 +1,12 @@
-use crate::io::write::{JsonWriter, ZincWriter};
+use crate::io::JsonVersion;
+use crate::io::write::ZincWriter;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_str;
 use crate::{HType, HVal, NumTrait, h_val::HBox};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 #[derive(Clone)]
 pub struct HDict<'a, T: NumTrait> {
@@ -12,6 +17,20 @@ pub type Dict<'a, T> = HDict<'a, T>;
 
 const THIS_TYPE: HType = HType::Dict;
 
+/// Hashes tags in name order so that equal maps hash alike
+pub(crate) fn hash_tags<'a, T: NumTrait + 'a, H: Hasher>(
+    tags: &HashMap<String, HBox<'a, T>>,
+    state: &mut H,
+) {
+    let mut names: Vec<&String> = tags.keys().collect();
+    names.sort();
+    state.write_usize(names.len());
+    for name in names {
+        name.hash(state);
+        tags[name].hash(state);
+    }
+}
+
 impl<'a, T: NumTrait> HDict<'a, T> {
     pub fn new() -> HDict<'a, T> {
         HDict {
@@ -85,6 +104,10 @@ impl<'a, T: NumTrait> HDict<'a, T> {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_hayson(f, JsonVersion::V3)
+    }
+
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
         write!(f, "{{")?;
         let mut dict_iter = self
             .inner
@@ -92,14 +115,49 @@ impl<'a, T: NumTrait> HDict<'a, T> {
             .filter(|(_, v)| v.get_null().is_none())
             .peekable();
         while let Some((k, v)) = dict_iter.next() {
-            let v_ref = v.as_ref();
-            write!(f, "\"{}\":\"{}\"", k, JsonWriter::new(v_ref))?;
+            write_str(f, k)?;
+            write!(f, ":")?;
+            v.to_hayson(f, version)?;
             if dict_iter.peek().is_some() {
                 write!(f, ",")?;
             }
         }
         write!(f, "}}")
     }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        if self.inner.is_empty() {
+            return enc.u8(ctrl::DICT_EMPTY);
+        }
+        enc.u8(ctrl::DICT);
+        enc.u8(b'{');
+        enc.var_int(self.inner.len() as i64);
+        for (k, v) in self.inner.iter() {
+            enc.str(k);
+            v.to_brio(enc);
+        }
+        enc.u8(b'}');
+    }
+}
+
+impl<'a, T: NumTrait + 'a> PartialEq for HDict<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Eq for HDict<'a, T> {}
+
+/// Dicts have no natural order, they only compare when equal
+impl<'a, T: NumTrait + 'a> PartialOrd for HDict<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self == other).then_some(Ordering::Equal)
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Hash for HDict<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_tags(&self.inner, state);
+    }
 }
 
 impl<'a, T: NumTrait + 'a> HVal<'a, T> for HDict<'a, T> {
@@ -107,16 +165,15 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HDict<'a, T> {
         THIS_TYPE
     }
 
-    fn _eq(&self, _other: &dyn HVal<'a, T>) -> bool {
-        false
-    }
+    set_trait_eq_method!(get_dict,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::h_number::HNumber;
-    use std::rc::Rc;
+    use crate::h_val::Ptr;
 
     #[test]
     fn test_new_dict() {
@@ -127,7 +184,7 @@ mod tests {
     #[test]
     fn test_from_map() {
         let mut map: HashMap<String, HBox<f64>> = HashMap::new();
-        let val: Rc<HNumber<f64>> = Rc::new(HNumber::new(42.0, None));
+        let val: Ptr<HNumber<f64>> = Ptr::new(HNumber::new(42.0, None));
         map.insert("key1".to_string(), val);
         let dict = HDict::from_map(map.clone());
         assert_eq!(dict.inner.len(), 1);
@@ -150,15 +207,46 @@ mod tests {
     #[test]
     fn test_eq() {
         let mut map1 = HashMap::new();
-        let val1: Rc<dyn HVal<f64>> = Rc::new(HNumber::new(42.0, None));
+        let val1: Ptr<dyn HVal<f64>> = Ptr::new(HNumber::new(42.0, None));
         map1.insert("key1".to_string(), val1);
         let dict1 = HDict::from_map(map1);
 
         let mut map2 = HashMap::new();
-        let val2: Rc<dyn HVal<f64>> = Rc::new(HNumber::new(42.0, None));
+        let val2: Ptr<dyn HVal<f64>> = Ptr::new(HNumber::new(42.0, None));
         map2.insert("key1".to_string(), val2);
         let dict2 = HDict::from_map(map2);
 
-        assert!(!dict1._eq(&dict2));
+        assert!(dict1._eq(&dict2));
+    }
+
+    #[test]
+    fn test_deep_eq_and_hash() {
+        use crate::h_list::HList;
+        use crate::h_marker::MARKER;
+        use std::collections::HashSet;
+
+        let dict = |area: f64| {
+            let mut map: HashMap<String, HBox<f64>> = HashMap::new();
+            map.insert("site".into(), MARKER.to_hbox());
+            map.insert("area".into(), HNumber::new(area, None).to_hbox());
+            map.insert(
+                "tags".into(),
+                HList::from_vec(vec![MARKER.to_hbox(), HNumber::new(1.0, None).to_hbox()])
+                    .to_hbox(),
+            );
+            HDict::from_map(map)
+        };
+
+        assert!(dict(10.0) == dict(10.0));
+        assert!(dict(10.0) != dict(20.0));
+        let boxed: HBox<f64> = Ptr::new(dict(10.0));
+        assert!(*boxed == *(Ptr::new(dict(10.0)) as HBox<f64>));
+
+        let set: HashSet<HDict<f64>> = [dict(10.0), dict(10.0), dict(20.0)].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        let set: HashSet<HBox<f64>> = [boxed.clone(), boxed, dict(20.0).to_hbox()]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 2);
     }
 }
@@ -0,0 +1,327 @@
+use super::{Col, HGrid, HGridErr, Vector};
+use crate::common::id;
+use crate::h_bool::HBool;
+use crate::h_dict::HDict;
+use crate::h_number::{HNumber, cast};
+use crate::h_str::HStr;
+use crate::h_val::{HBox, Ptr};
+use crate::{HVal, NumTrait};
+use nom::Parser;
+use nom::combinator::all_consuming;
+use std::collections::HashMap;
+
+/// Values accepted as cells by `GridBuilder::add_row` and `grid!`, `None` is an empty cell
+pub trait IntoCell<'a, T: NumTrait> {
+    fn into_cell(self) -> Option<HBox<'a, T>>;
+}
+
+impl<'a, T: NumTrait> IntoCell<'a, T> for HBox<'a, T> {
+    fn into_cell(self) -> Option<HBox<'a, T>> {
+        Some(self)
+    }
+}
+
+impl<'a, T: NumTrait> IntoCell<'a, T> for Option<HBox<'a, T>> {
+    fn into_cell(self) -> Option<HBox<'a, T>> {
+        self
+    }
+}
+
+impl<'a, T: NumTrait + 'a, V: HVal<'a, T> + 'a> IntoCell<'a, T> for V {
+    fn into_cell(self) -> Option<HBox<'a, T>> {
+        Some(Ptr::new(self))
+    }
+}
+
+impl<'a, T: NumTrait + 'a> IntoCell<'a, T> for f64 {
+    fn into_cell(self) -> Option<HBox<'a, T>> {
+        Some(Ptr::new(HNumber::new(cast(self), None)))
+    }
+}
+
+impl<'a, T: NumTrait + 'a> IntoCell<'a, T> for bool {
+    fn into_cell(self) -> Option<HBox<'a, T>> {
+        Some(Ptr::new(HBool(self)))
+    }
+}
+
+impl<'a, T: NumTrait + 'a> IntoCell<'a, T> for &str {
+    fn into_cell(self) -> Option<HBox<'a, T>> {
+        Some(Ptr::new(HStr(self.to_owned())))
+    }
+}
+
+pub(crate) fn is_col_name(name: &str) -> bool {
+    all_consuming(id).parse(name).is_ok()
+}
+
+/// Builds an `HGrid` column by column and row by row, validating as it goes
+#[derive(Clone)]
+pub struct GridBuilder<'a, T: NumTrait + 'a> {
+    meta: HDict<'a, T>,
+    cols: Vec<Col<'a, T>>,
+    col_index: HashMap<String, usize>,
+    rows: Vec<Vec<Option<HBox<'a, T>>>>,
+}
+
+impl<'a, T: NumTrait + 'a> Default for GridBuilder<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> GridBuilder<'a, T> {
+    pub fn new() -> Self {
+        Self {
+            meta: HDict::new(),
+            cols: Vec::new(),
+            col_index: HashMap::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn set_meta(mut self, name: &str, val: impl IntoCell<'a, T>) -> Self {
+        if let Some(val) = val.into_cell() {
+            self.meta.set(name.to_owned(), val);
+        }
+        self
+    }
+
+    pub fn extend_meta(mut self, meta: HDict<'a, T>) -> Self {
+        self.meta.merge(meta);
+        self
+    }
+
+    /// Adds a column, names must be valid tag names and unique within the grid
+    pub fn add_col(mut self, name: &str, meta: HDict<'a, T>) -> Result<Self, HGridErr> {
+        if !is_col_name(name) {
+            return Err(HGridErr::InvalidColName(name.to_owned()));
+        }
+        if self.col_index.contains_key(name) {
+            return Err(HGridErr::DuplicateCol(name.to_owned()));
+        }
+        let meta = (!meta.is_empty()).then(|| meta.into_map());
+        self.col_index.insert(name.to_owned(), self.cols.len());
+        self.cols.push(Col::new(name.to_owned(), meta));
+        Ok(self)
+    }
+
+    pub fn set_col_meta(
+        mut self,
+        col: &str,
+        name: &str,
+        val: impl IntoCell<'a, T>,
+    ) -> Result<Self, HGridErr> {
+        let idx = *self
+            .col_index
+            .get(col)
+            .ok_or_else(|| HGridErr::UnknownCol(col.to_owned()))?;
+        if let Some(val) = val.into_cell() {
+            self.cols[idx].add_meta(HashMap::from([(name.to_owned(), val)]));
+        }
+        Ok(self)
+    }
+
+    /// Adds a row with one cell per column, in column order
+    pub fn add_row<C: IntoCell<'a, T>>(
+        mut self,
+        row: impl IntoIterator<Item = C>,
+    ) -> Result<Self, HGridErr> {
+        let row: Vec<_> = row.into_iter().map(IntoCell::into_cell).collect();
+        if row.len() != self.cols.len() {
+            return Err(HGridErr::RowLen {
+                expected: self.cols.len(),
+                found: row.len(),
+            });
+        }
+        self.rows.push(row);
+        Ok(self)
+    }
+
+    /// Adds a row from its tags, adding a column for each tag not yet in the grid
+    pub fn add_dict_row(mut self, row: HDict<'a, T>) -> Result<Self, HGridErr> {
+        let mut tags = row.into_map();
+        let mut new_cols: Vec<_> = tags
+            .keys()
+            .filter(|name| !self.col_index.contains_key(*name))
+            .cloned()
+            .collect();
+        new_cols.sort();
+        for name in new_cols {
+            self = self.add_col(&name, HDict::new())?;
+        }
+
+        let row = self.cols.iter().map(|c| tags.remove(c.name())).collect();
+        self.rows.push(row);
+        Ok(self)
+    }
+
+    /// A grid without columns is built as `HGrid::Empty`
+    pub fn build(self) -> HGrid<'a, T> {
+        from_parts(self.meta, self.cols, self.rows)
+    }
+}
+
+/// Assembles a grid from columns with unique names and rows of cells in column order, padding
+/// short rows. Without columns the grid is `HGrid::Empty`.
+pub(super) fn from_parts<'a, T: NumTrait + 'a>(
+    meta: HDict<'a, T>,
+    cols: Vec<Col<'a, T>>,
+    rows: Vec<Vec<Option<HBox<'a, T>>>>,
+) -> HGrid<'a, T> {
+    if cols.is_empty() {
+        let meta = (!meta.is_empty()).then(|| meta.into_map());
+        return HGrid::Empty { meta };
+    }
+
+    let width = cols.len();
+    let col_index: HashMap<_, _> = cols
+        .iter()
+        .enumerate()
+        .map(|(idx, col)| (col.name.clone(), idx))
+        .collect();
+    let rows = rows
+        .into_iter()
+        .map(|mut row| {
+            row.resize(width, None);
+            Ptr::new(row.into_iter().collect::<Vector<_>>())
+        })
+        .collect();
+
+    HGrid::Grid {
+        meta,
+        col_index: Ptr::new(col_index),
+        cols: cols.into_iter().collect(),
+        rows,
+    }
+}
+
+/// Builds a grid from literals, ie. `grid!({ hisStart: "2025-01-01" }, [id, val], [id, 1.0])`,
+/// panicking on invalid or duplicate columns and short rows
+#[macro_export]
+macro_rules! grid {
+    ( { $( $meta:ident : $meta_val:expr ),* $(,)? }, [ $( $col:ident ),* $(,)? ] $( , [ $( $val:expr ),* $(,)? ] )* $(,)? ) => {{
+        #[allow(unused_imports)]
+        use $crate::h_grid::builder::IntoCell;
+        $crate::h_grid::GridBuilder::new()
+            $( .set_meta(stringify!($meta), $meta_val) )*
+            $( .add_col(stringify!($col), $crate::h_dict::HDict::new()).unwrap() )*
+            $( .add_row([ $( IntoCell::into_cell($val) ),* ]).unwrap() )*
+            .build()
+    }};
+    ( [ $( $col:ident ),* $(,)? ] $( , [ $( $val:expr ),* $(,)? ] )* $(,)? ) => {
+        $crate::grid!({}, [ $( $col ),* ] $( , [ $( $val ),* ] )*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_marker::MARKER;
+    use crate::h_ref::HRef;
+
+    #[test]
+    fn build_grid() {
+        let grid: HGrid<f64> = GridBuilder::new()
+            .set_meta("hisStart", "2025-01-01")
+            .add_col("id", HDict::new())
+            .unwrap()
+            .add_col("val", HDict::new())
+            .unwrap()
+            .set_col_meta("val", "unit", "kW")
+            .unwrap()
+            .add_row([
+                Ptr::new(HRef::new("a".into(), None)) as HBox<f64>,
+                Ptr::new(HNumber::new(1.0, None)),
+            ])
+            .unwrap()
+            .add_row([
+                Some(Ptr::new(HRef::new("b".into(), None)) as HBox<f64>),
+                None,
+            ])
+            .unwrap()
+            .build();
+
+        assert_eq!(grid.len(), 2);
+        assert!(grid.meta().has("hisStart"));
+        assert!(grid.iter_cols().nth(1).unwrap().has("unit"));
+        assert!(grid.get(0).unwrap().has("val"));
+        assert!(!grid.get(1).unwrap().has("val"));
+    }
+
+    #[test]
+    fn invalid_cols_are_errors() {
+        let builder = GridBuilder::<f64>::new()
+            .add_col("id", HDict::new())
+            .unwrap();
+        assert!(matches!(
+            builder.clone().add_col("id", HDict::new()),
+            Err(HGridErr::DuplicateCol(name)) if name == "id"
+        ));
+        assert!(matches!(
+            builder.clone().add_col("Bad name", HDict::new()),
+            Err(HGridErr::InvalidColName(_))
+        ));
+        assert!(matches!(
+            builder.clone().add_row([1.0, 2.0]),
+            Err(HGridErr::RowLen {
+                expected: 1,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            builder.set_col_meta("val", "unit", "kW"),
+            Err(HGridErr::UnknownCol(_))
+        ));
+    }
+
+    #[test]
+    fn dict_rows_add_cols() {
+        let mut dict = HDict::new();
+        dict.set("site".to_owned(), Ptr::new(MARKER) as HBox<f64>);
+        let grid = GridBuilder::new()
+            .add_col("dis", HDict::new())
+            .unwrap()
+            .add_row(["a"])
+            .unwrap()
+            .add_dict_row(dict)
+            .unwrap()
+            .build();
+
+        assert_eq!(grid.iter_cols().count(), 2);
+        assert!(!grid.get(0).unwrap().has("site"));
+        assert!(grid.get(1).unwrap().has("site"));
+    }
+
+    #[test]
+    fn grid_macro() {
+        let grid: HGrid<f64> = grid!(
+            { hisStart: "2025-01-01", hisEnd: true },
+            [id, val],
+            [HRef::new("a".into(), None), 1.0],
+            [HRef::new("b".into(), None), None::<HBox<f64>>],
+        );
+        let built = GridBuilder::new()
+            .set_meta("hisStart", "2025-01-01")
+            .set_meta("hisEnd", true)
+            .add_col("id", HDict::new())
+            .unwrap()
+            .add_col("val", HDict::new())
+            .unwrap()
+            .add_row([
+                Some(Ptr::new(HRef::new("a".into(), None)) as HBox<f64>),
+                1.0.into_cell(),
+            ])
+            .unwrap()
+            .add_row([
+                Some(Ptr::new(HRef::new("b".into(), None)) as HBox<f64>),
+                None,
+            ])
+            .unwrap()
+            .build();
+        assert_eq!(grid, built);
+
+        let empty: HGrid<f64> = grid!([]);
+        assert!(matches!(empty, HGrid::Empty { meta: None }));
+    }
+}
@@ -1,7 +1,11 @@
 use crate::h_dict::HDict;
+use crate::h_dict::hash_tags;
 use crate::h_val::HBox;
+use crate::io::JsonVersion;
+use crate::io::write::hayson::write_str;
 use crate::{HType, NumTrait};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 use std::collections::HashMap;
 
@@ -25,6 +29,21 @@ impl<'a, T: NumTrait> fmt::Debug for HCol<'a, T> {
 
 pub type Col<'a, T> = HCol<'a, T>;
 
+impl<'a, T: NumTrait + 'a> PartialEq for HCol<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.meta == other.meta
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Eq for HCol<'a, T> {}
+
+impl<'a, T: NumTrait + 'a> Hash for HCol<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        hash_tags(&self.meta, state);
+    }
+}
+
 impl<'a, T: NumTrait> HCol<'a, T> {
     pub fn new(name: String, meta: Option<HashMap<String, HBox<'a, T>>>) -> Self {
         Self {
@@ -76,6 +95,27 @@ impl<'a, T: NumTrait> HCol<'a, T> {
         &self.name
     }
 
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write!(f, "{{\"name\":")?;
+        write_str(f, &self.name)?;
+        match version {
+            JsonVersion::V4 if !self.meta.is_empty() => {
+                write!(f, ",\"meta\":")?;
+                self.meta().to_hayson(f, version)?;
+            }
+            JsonVersion::V4 => (),
+            JsonVersion::V3 => {
+                for (k, v) in self.meta.iter().filter(|(_, v)| v.get_null().is_none()) {
+                    write!(f, ",")?;
+                    write_str(f, k)?;
+                    write!(f, ":")?;
+                    v.to_hayson(f, version)?;
+                }
+            }
+        }
+        write!(f, "}}")
+    }
+
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.name)?;
 
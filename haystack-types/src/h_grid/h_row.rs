@@ -1,24 +1,30 @@
+use super::Vector;
 use crate::HCol;
-use crate::{HType, NumTrait, h_val::HBox};
-use rpds::Vector;
+use crate::h_dict::HDict;
+use crate::h_str::HStr;
+use crate::io::JsonVersion;
+use crate::io::write::ZincWriter;
+use crate::{
+    HType, NumTrait,
+    h_val::{HBox, WeakPtr},
+};
 use std::collections::HashMap;
 use std::fmt::{self, Debug, Display};
-use std::rc::Weak;
 
 #[derive(Clone)]
 pub struct HRow<'a, T: NumTrait + 'a> {
-    col_index: Weak<HashMap<String, usize>>,
+    col_index: WeakPtr<HashMap<String, usize>>,
     pub cols: Vector<HCol<'a, T>>,
-    pub inner: Weak<Vector<Option<HBox<'a, T>>>>,
+    pub inner: WeakPtr<Vector<Option<HBox<'a, T>>>>,
 }
 
 pub type Row<'a, T> = HRow<'a, T>;
 
 impl<'a, T: NumTrait + 'a> HRow<'a, T> {
     pub fn new(
-        col_index: Weak<HashMap<String, usize>>,
+        col_index: WeakPtr<HashMap<String, usize>>,
         cols: Vector<HCol<'a, T>>,
-        inner: Weak<Vector<Option<HBox<'a, T>>>>,
+        inner: WeakPtr<Vector<Option<HBox<'a, T>>>>,
     ) -> Self {
         Self {
             col_index,
@@ -27,7 +33,7 @@ impl<'a, T: NumTrait + 'a> HRow<'a, T> {
         }
     }
 
-    pub fn get(&'a self, key: &str) -> Option<HBox<'a, T>> {
+    pub fn get(&self, key: &str) -> Option<HBox<'a, T>> {
         let col_index = self.col_index.upgrade().unwrap();
         let idx = col_index.get(key);
 
@@ -41,7 +47,7 @@ impl<'a, T: NumTrait + 'a> HRow<'a, T> {
         }
     }
 
-    pub fn has(&'a self, key: &str) -> bool {
+    pub fn has(&self, key: &str) -> bool {
         let col_index = self.col_index.upgrade().unwrap();
 
         match col_index.get(key) {
@@ -56,6 +62,20 @@ impl<'a, T: NumTrait + 'a> HRow<'a, T> {
         }
     }
 
+    pub fn to_dict(&self) -> HDict<'a, T> {
+        let inner = self.inner.upgrade().unwrap();
+        let mut dict = HDict::new();
+
+        for (idx, col) in self.cols.iter().enumerate() {
+            if let Some(Some(v)) = inner.get(idx)
+                && v.haystack_type() != HType::Null
+            {
+                dict.set(col.name.clone(), v.clone());
+            }
+        }
+        dict
+    }
+
     pub fn to_zinc<'b>(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if !self.cols.is_empty() {
             let mut iter = self.cols.iter().enumerate().peekable();
@@ -77,18 +97,43 @@ impl<'a, T: NumTrait + 'a> HRow<'a, T> {
     }
 
     pub fn to_trio<'b>(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let col_index = self.col_index.upgrade().unwrap();
-
-        if !col_index.is_empty() {
-            let mut iter = self.cols.iter().enumerate().peekable();
-            while let Some((idx, _c)) = iter.next() {
-                match self.inner.upgrade().unwrap().get(idx) {
-                    Some(v) => match v {
-                        Some(v) => v.to_trio(f),
-                        _ => Ok(()),
-                    },
-                    None => Ok(()),
-                }?;
+        let inner = self.inner.upgrade().unwrap();
+
+        for (idx, col) in self.cols.iter().enumerate() {
+            let Some(Some(v)) = inner.get(idx) else {
+                continue;
+            };
+
+            match v.haystack_type() {
+                HType::Null => (),
+                HType::Marker => writeln!(f, "{}", col.name)?,
+                HType::Str if v.get_string().unwrap().as_str().contains('\n') => {
+                    writeln!(f, "{}:", col.name)?;
+                    for line in v.get_string().unwrap().as_str().split('\n') {
+                        writeln!(f, "  {}", line)?;
+                    }
+                }
+                // Ref display strings are quoted so the value reads back as a ref
+                HType::Ref => {
+                    let r = v.get_ref().unwrap();
+                    write!(f, "{}: @{}", col.name, r.id)?;
+                    if let Some(dis) = &r.dis {
+                        write!(f, " {}", ZincWriter::new(&HStr(dis.clone())))?;
+                    }
+                    writeln!(f)?;
+                }
+                HType::Grid => {
+                    writeln!(f, "{}: Zinc:", col.name)?;
+                    let zinc = ZincWriter::new(v.as_ref()).to_string();
+                    for line in zinc.lines() {
+                        writeln!(f, "  {}", line)?;
+                    }
+                }
+                _ => {
+                    write!(f, "{}: ", col.name)?;
+                    v.to_trio(f)?;
+                    writeln!(f)?;
+                }
             }
         }
 
@@ -96,21 +141,11 @@ impl<'a, T: NumTrait + 'a> HRow<'a, T> {
     }
 
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{{")?;
-        let mut iter = self.cols.iter().enumerate().peekable();
-
-        while let Some((idx, c)) = iter.next() {
-            if let Some(v) = self.inner.upgrade().unwrap().get(idx) {
-                if let Some(v) = v {
-                    write!(f, "\"{}\":", c.name)?;
-                    v.to_json(f)?;
-                    if iter.peek().is_some() {
-                        write!(f, ",")?;
-                    }
-                }
-            }
-        }
-        write!(f, "}}")
+        self.to_hayson(f, JsonVersion::V3)
+    }
+
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        self.to_dict().to_hayson(f, version)
     }
 }
 
@@ -0,0 +1,404 @@
+use super::builder::{from_parts, is_col_name};
+use super::{Col, HGrid, HGridErr};
+use crate::h_dict::HDict;
+use crate::h_val::{HBox, Ptr};
+use crate::{HType, NumTrait};
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Whether rows without a match on the other side are kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    /// Keeps every left row, leaving the right cells empty when nothing matches
+    Left,
+}
+
+type Cells<'a, T> = Vec<Option<HBox<'a, T>>>;
+type Parts<'a, T> = (HDict<'a, T>, Vec<Col<'a, T>>, Vec<Cells<'a, T>>);
+
+/// Prefixes a tag name in camel case, ie. `equip` and `dis` give `equipDis`
+fn prefixed(prefix: &str, name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}{}", prefix, first.to_uppercase(), chars.as_str()),
+        None => prefix.to_owned(),
+    }
+}
+
+/// Claims a column name, prefixing `name` when it is already taken
+fn unique_name(taken: &mut HashSet<String>, prefix: &str, name: &str) -> Result<String, HGridErr> {
+    let name = match taken.contains(name) {
+        true => prefixed(prefix, name),
+        false => name.to_owned(),
+    };
+    if !is_col_name(&name) {
+        return Err(HGridErr::InvalidColName(name));
+    }
+    if !taken.insert(name.clone()) {
+        return Err(HGridErr::DuplicateCol(name));
+    }
+    Ok(name)
+}
+
+fn renamed<'a, T: NumTrait + 'a>(mut col: Col<'a, T>, name: String) -> Col<'a, T> {
+    col.name = name;
+    col
+}
+
+/// Empty and null cells match nothing
+fn join_key<'a, T: NumTrait + 'a>(cell: &Option<HBox<'a, T>>) -> Option<HBox<'a, T>> {
+    cell.clone()
+        .filter(|val| val.haystack_type() != HType::Null)
+}
+
+impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
+    /// Grid meta, columns and the cells of each row, in column order
+    fn parts(&self) -> Result<Parts<'a, T>, HGridErr> {
+        match self {
+            HGrid::Grid {
+                meta, cols, rows, ..
+            } => Ok((
+                meta.clone(),
+                cols.iter().cloned().collect(),
+                rows.iter()
+                    .map(|row| row.iter().cloned().collect())
+                    .collect(),
+            )),
+            HGrid::Empty { meta } => Ok((
+                HDict::from_map(meta.clone().unwrap_or_default()),
+                vec![],
+                vec![],
+            )),
+            HGrid::Error { dis, .. } => Err(HGridErr::ErrorGrid(dis.clone())),
+        }
+    }
+
+    /// Joins each row to the rows of `other` whose `right_col` matches its `left_col`, ie. `id`
+    /// to `equipRef`. Columns of `other` whose names are taken get `prefix`, so `dis` with
+    /// `equip` becomes `equipDis`, and `right_col` is dropped when named the same as `left_col`.
+    pub fn join(
+        &self,
+        other: &Self,
+        left_col: &str,
+        right_col: &str,
+        kind: JoinKind,
+        prefix: &str,
+    ) -> Result<Self, HGridErr> {
+        let left_idx = self.col_idx(left_col)?;
+        let right_idx = other.col_idx(right_col)?;
+        let (meta, mut cols, left_rows) = self.parts()?;
+        let (_, right_cols, right_rows) = other.parts()?;
+
+        let mut taken: HashSet<_> = cols.iter().map(|col| col.name.clone()).collect();
+        let mut keep = Vec::new();
+        for (idx, col) in right_cols.into_iter().enumerate() {
+            if idx == right_idx && left_col == right_col {
+                continue;
+            }
+            let name = unique_name(&mut taken, prefix, col.name())?;
+            cols.push(renamed(col, name));
+            keep.push(idx);
+        }
+
+        let mut index: HashMap<HBox<'a, T>, Vec<&Cells<'a, T>>> = HashMap::new();
+        for row in right_rows.iter() {
+            if let Some(key) = join_key(&row[right_idx]) {
+                index.entry(key).or_default().push(row);
+            }
+        }
+
+        let mut rows = Vec::new();
+        for left in left_rows {
+            let matches = join_key(&left[left_idx]).and_then(|key| index.get(&key));
+            match matches {
+                Some(matches) => rows.extend(matches.iter().map(|right| {
+                    let mut row = left.clone();
+                    row.extend(keep.iter().map(|idx| right[*idx].clone()));
+                    row
+                })),
+                None if kind == JoinKind::Left => rows.push(left),
+                None => (),
+            }
+        }
+
+        Ok(from_parts(meta, cols, rows))
+    }
+
+    /// Stacks the rows of several grids under the union of their columns, in the order first
+    /// seen. Grid meta comes from the first grid, column meta from the first grid with the column.
+    pub fn concat(grids: &[&Self]) -> Result<Self, HGridErr> {
+        let mut meta = None;
+        let mut cols: Vec<Col<'a, T>> = Vec::new();
+        let mut col_index: HashMap<String, usize> = HashMap::new();
+        let mut rows = Vec::new();
+
+        for grid in grids {
+            let (grid_meta, grid_cols, grid_rows) = grid.parts()?;
+            meta.get_or_insert(grid_meta);
+            let idxs: Vec<_> = grid_cols
+                .into_iter()
+                .map(|col| {
+                    *col_index.entry(col.name.clone()).or_insert_with(|| {
+                        cols.push(col);
+                        cols.len() - 1
+                    })
+                })
+                .collect();
+            for cells in grid_rows {
+                let mut row = vec![None; cols.len()];
+                for (idx, cell) in idxs.iter().zip(cells) {
+                    row[*idx] = cell;
+                }
+                rows.push(row);
+            }
+        }
+
+        Ok(from_parts(meta.unwrap_or_else(HDict::new), cols, rows))
+    }
+
+    /// Merges `hisRead` grids into one row per timestamp across them. The `val` column of the
+    /// nth grid becomes `v{n}` with the grid's `id` in its meta, other clashing columns are
+    /// prefixed the same way. Timestamps are written in the timezone of the first one.
+    pub fn his_merge(grids: &[&Self]) -> Result<Self, HGridErr> {
+        let parts = grids
+            .iter()
+            .map(|grid| Ok((grid.col_idx("ts")?, grid.parts()?)))
+            .collect::<Result<Vec<_>, HGridErr>>()?;
+        let Some((first_ts, (first_meta, first_cols, _))) = parts.first() else {
+            return Ok(HGrid::Empty { meta: None });
+        };
+
+        let mut meta = first_meta.clone().into_map();
+        meta.remove("id");
+        let mut cols = vec![first_cols[*first_ts].clone()];
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (ts_idx, (_, grid_cols, _)) in parts.iter() {
+            for (idx, col) in grid_cols.iter().enumerate() {
+                if idx != *ts_idx {
+                    *counts.entry(col.name()).or_default() += 1;
+                }
+            }
+        }
+        let mut taken: HashSet<_> = (0..parts.len()).map(|n| format!("v{}", n)).collect();
+        taken.insert("ts".to_owned());
+        for name in counts
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(name, _)| name)
+        {
+            taken.insert((*name).to_owned());
+        }
+
+        let mut offsets = Vec::new();
+        for (n, (ts_idx, (grid_meta, grid_cols, _))) in parts.iter().enumerate() {
+            offsets.push(cols.len() - 1);
+            for (idx, col) in grid_cols.iter().enumerate() {
+                if idx == *ts_idx {
+                    continue;
+                }
+                let mut col = col.clone();
+                col.name = match col.name() {
+                    "val" => {
+                        if let Some(id) = grid_meta.get("id") {
+                            col.add_meta(HashMap::from([("id".to_owned(), id.clone())]));
+                        }
+                        format!("v{}", n)
+                    }
+                    name => unique_name(&mut taken, &format!("v{}", n), name)?,
+                };
+                cols.push(col);
+            }
+        }
+
+        let width = cols.len() - 1;
+        let mut tz = None;
+        let mut by_ts = BTreeMap::new();
+        for ((ts_idx, (_, _, grid_rows)), offset) in parts.iter().zip(offsets) {
+            for cells in grid_rows {
+                let dt = cells[*ts_idx]
+                    .as_ref()
+                    .and_then(|ts| ts.get_datetime())
+                    .ok_or_else(|| HGridErr::NotDateTime("ts".to_owned()))?;
+                let tz = *tz.get_or_insert(dt.tz_id());
+                let (_, row) = by_ts
+                    .entry(dt.to_chrono().with_timezone(&Utc))
+                    .or_insert_with(|| (Ptr::new(dt.to_tz(tz)) as HBox<T>, vec![None; width]));
+                let vals = cells.iter().enumerate().filter(|(idx, _)| idx != ts_idx);
+                for (slot, (_, cell)) in row[offset..].iter_mut().zip(vals) {
+                    *slot = cell.clone();
+                }
+            }
+        }
+
+        let rows = by_ts
+            .into_values()
+            .map(|(ts, vals)| std::iter::once(Some(ts)).chain(vals).collect())
+            .collect();
+        Ok(from_parts(HDict::from_map(meta), cols, rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid;
+    use crate::h_datetime::HDateTime;
+    use crate::h_grid::test_util::{names, strs};
+    use crate::h_number::HNumber;
+    use crate::h_ref::HRef;
+    use crate::h_str::HStr;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    fn r(id: &str) -> HRef {
+        HRef::new(id.to_owned(), None)
+    }
+
+    fn ts(hour: u32, tz: Tz) -> HDateTime {
+        HDateTime::from(tz.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap())
+    }
+
+    fn nums(grid: &HGrid<f64>, name: &str) -> Vec<Option<f64>> {
+        grid.iter()
+            .map(|row| row.get(name).map(|val| val.get_number().unwrap().val()))
+            .collect()
+    }
+
+    fn sites() -> HGrid<'static, f64> {
+        grid!(
+            [id, dis],
+            [HRef::new("s1".into(), Some("Site 1".into())), "Site 1"],
+            [r("s2"), "Site 2"],
+            [r("s3"), "Site 3"],
+        )
+    }
+
+    fn equips() -> HGrid<'static, f64> {
+        grid!(
+            [id, dis, siteRef],
+            [r("e1"), "AHU-1", r("s1")],
+            [r("e2"), "AHU-2", r("s1")],
+            [r("e3"), "Boiler", r("s2")],
+            [r("e4"), "Orphan", None::<HBox<f64>>],
+        )
+    }
+
+    #[test]
+    fn inner_and_left_join() {
+        let inner = sites()
+            .join(&equips(), "id", "siteRef", JoinKind::Inner, "equip")
+            .unwrap();
+        assert_eq!(
+            names(&inner),
+            ["id", "dis", "equipId", "equipDis", "siteRef"]
+        );
+        assert_eq!(strs(&inner, "dis"), ["Site 1", "Site 1", "Site 2"]);
+        assert_eq!(strs(&inner, "equipDis"), ["AHU-1", "AHU-2", "Boiler"]);
+
+        let left = sites()
+            .join(&equips(), "id", "siteRef", JoinKind::Left, "equip")
+            .unwrap();
+        assert_eq!(strs(&left, "dis"), ["Site 1", "Site 1", "Site 2", "Site 3"]);
+        assert_eq!(strs(&left, "equipDis"), ["AHU-1", "AHU-2", "Boiler", "-"]);
+    }
+
+    #[test]
+    fn join_on_same_name_drops_key() {
+        let areas: HGrid<f64> = grid!([id, area], [r("s2"), 200.0], [r("s1"), 100.0]);
+        let joined = sites()
+            .join(&areas, "id", "id", JoinKind::Inner, "x")
+            .unwrap();
+        assert_eq!(names(&joined), ["id", "dis", "area"]);
+        assert_eq!(nums(&joined, "area"), [Some(100.0), Some(200.0)]);
+
+        let clash: HGrid<f64> = grid!([id, xDis, dis], [r("s1"), "a", "b"]);
+        assert!(matches!(
+            sites().join(&clash, "id", "id", JoinKind::Inner, "x"),
+            Err(HGridErr::DuplicateCol(name)) if name == "xDis"
+        ));
+        assert!(matches!(
+            sites().join(&areas, "id", "siteRef", JoinKind::Inner, "x"),
+            Err(HGridErr::UnknownCol(_))
+        ));
+    }
+
+    #[test]
+    fn concat_unions_cols() {
+        let a: HGrid<f64> = grid!({ dis: "A" }, [dis, area], ["a", 1.0]);
+        let b: HGrid<f64> = grid!({ dis: "B" }, [city, dis], ["Perth", "b"]);
+        let grid = HGrid::concat(&[&a, &HGrid::Empty { meta: None }, &b]).unwrap();
+        assert_eq!(names(&grid), ["dis", "area", "city"]);
+        assert_eq!(strs(&grid, "dis"), ["a", "b"]);
+        assert_eq!(strs(&grid, "city"), ["-", "Perth"]);
+        assert_eq!(nums(&grid, "area"), [Some(1.0), None]);
+        assert_eq!(
+            grid.meta().get("dis").unwrap().get_string(),
+            Some(&HStr("A".into()))
+        );
+        assert!(matches!(
+            HGrid::<f64>::concat(&[]).unwrap(),
+            HGrid::Empty { meta: None }
+        ));
+    }
+
+    #[test]
+    fn merge_his_grids() {
+        let a: HGrid<f64> = grid!(
+            { id: r("p1"), hisStart: ts(0, Tz::UTC) },
+            [ts, val],
+            [ts(1, Tz::UTC), 1.0],
+            [ts(2, Tz::UTC), 2.0],
+        );
+        // The same instants written in another zone merge into the same rows
+        let b: HGrid<f64> = grid!(
+            { id: r("p2") },
+            [ts, val],
+            [ts(15, Tz::Pacific__Auckland), HNumber::new(20.0, None)],
+            [ts(16, Tz::Pacific__Auckland), HNumber::new(30.0, None)],
+        );
+        let grid = HGrid::his_merge(&[&a, &b]).unwrap();
+        assert_eq!(names(&grid), ["ts", "v0", "v1"]);
+        assert_eq!(nums(&grid, "v0"), [Some(1.0), Some(2.0), None]);
+        assert_eq!(nums(&grid, "v1"), [None, Some(20.0), Some(30.0)]);
+        assert_eq!(
+            grid.iter_cols()
+                .nth(2)
+                .unwrap()
+                .meta()
+                .get("id")
+                .unwrap()
+                .get_ref(),
+            Some(&r("p2"))
+        );
+        assert!(grid.meta().has("hisStart") && !grid.meta().has("id"));
+        let tzs: Vec<_> = grid
+            .iter()
+            .map(|row| row.get("ts").unwrap().get_datetime().unwrap().tz_id())
+            .collect();
+        assert_eq!(tzs, [Tz::UTC; 3]);
+        let grid = HGrid::his_merge(&[&b, &a]).unwrap();
+        assert_eq!(
+            grid.iter()
+                .next()
+                .unwrap()
+                .get("ts")
+                .unwrap()
+                .get_datetime(),
+            Some(&ts(14, Tz::Pacific__Auckland))
+        );
+
+        let bad_ts: HGrid<f64> = grid!([ts, val], ["yesterday", 1.0]);
+        assert!(matches!(
+            HGrid::his_merge(&[&a, &bad_ts]),
+            Err(HGridErr::NotDateTime(name)) if name == "ts"
+        ));
+
+        let no_ts: HGrid<f64> = grid!([val], [1.0]);
+        assert!(matches!(
+            HGrid::his_merge(&[&a, &no_ts]),
+            Err(HGridErr::UnknownCol(name)) if name == "ts"
+        ));
+    }
+}
@@ -1,11 +1,17 @@
 use crate::h_dict::HDict;
+use crate::h_dict::hash_tags;
+use crate::h_marker::HMarker;
 use crate::h_str::HStr;
 use crate::h_val::HBox;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_str;
 use crate::io::write::zinc::ZincWriter;
 use crate::{HType, HVal, NumTrait};
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-use rpds::Vector;
 use std::collections::HashMap;
 
 pub mod h_col;
@@ -14,16 +20,31 @@ pub use h_col::{Col, HCol};
 pub mod h_row;
 pub use h_row::{HRow, Row};
 
-use std::cell::RefCell;
-use std::rc::Rc;
+pub mod builder;
+pub use builder::GridBuilder;
+
+mod join;
+pub use join::JoinKind;
+
+mod ops;
+
+#[cfg(test)]
+mod test_util;
+
+use crate::h_val::Ptr;
+
+#[cfg(not(feature = "sync"))]
+pub type Vector<T> = rpds::Vector<T>;
+#[cfg(feature = "sync")]
+pub type Vector<T> = rpds::VectorSync<T>;
 
 #[derive(Clone)]
 pub enum HGrid<'a, T: NumTrait + 'a> {
     Grid {
-        meta: RefCell<HDict<'a, T>>,
-        col_index: Rc<HashMap<String, usize>>,
+        meta: HDict<'a, T>,
+        col_index: Ptr<HashMap<String, usize>>,
         cols: Vector<HCol<'a, T>>,
-        rows: Vec<Rc<Vector<Option<HBox<'a, T>>>>>,
+        rows: Vec<Ptr<Vector<Option<HBox<'a, T>>>>>,
     },
     Error {
         dis: String,
@@ -58,6 +79,12 @@ pub enum HGridErr {
     IndexErr,
     NotImplemented,
     AddMetaFailed,
+    DuplicateCol(String),
+    InvalidColName(String),
+    UnknownCol(String),
+    ErrorGrid(String),
+    NotDateTime(String),
+    RowLen { expected: usize, found: usize },
 }
 
 impl fmt::Display for HGridErr {
@@ -67,6 +94,20 @@ impl fmt::Display for HGridErr {
             HGridErr::IndexErr => write!(f, "Error: Index Out of Bounds"),
             HGridErr::NotImplemented => write!(f, "Error: Not Implemented"),
             HGridErr::AddMetaFailed => write!(f, "Error: Failed to add grid metadata"),
+            HGridErr::DuplicateCol(name) => write!(f, "Error: Duplicate column {:?}", name),
+            HGridErr::InvalidColName(name) => write!(f, "Error: Invalid column name {:?}", name),
+            HGridErr::UnknownCol(name) => write!(f, "Error: Unknown column {:?}", name),
+            HGridErr::ErrorGrid(dis) => write!(f, "Error: Error grid: {}", dis),
+            HGridErr::NotDateTime(name) => {
+                write!(f, "Error: Expected dateTime in column {:?}", name)
+            }
+            HGridErr::RowLen { expected, found } => {
+                write!(
+                    f,
+                    "Error: Expected {} cells in row, found {}",
+                    expected, found
+                )
+            }
         }
     }
 }
@@ -80,7 +121,7 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
     ) -> HGrid<'a, T> {
         let meta = HashMap::with_capacity(0);
         let mut col_index: HashMap<String, _> = HashMap::new();
-        let mut cols = Vector::new();
+        let mut cols = Vector::new_with_ptr_kind();
 
         if let Some(columns) = g_columns {
             let mut col_iter = columns.iter();
@@ -106,12 +147,12 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
 
                 let row: Vector<Option<HBox<'a, T>>> =
                     cols.iter().map(|c| r.remove(c.name.as_str())).collect();
-                Rc::from(row)
+                Ptr::from(row)
             })
             .collect();
 
-        let meta = RefCell::new(HDict::from_map(meta));
-        let col_index = Rc::new(col_index);
+        let meta = HDict::from_map(meta);
+        let col_index = Ptr::new(col_index);
 
         let grid = HGrid::Grid {
             meta,
@@ -123,13 +164,14 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
         grid
     }
 
+    /// Panics on duplicate column names, `GridBuilder` reports them as errors instead
     pub fn from_row_vec<'b>(
         columns: Vec<(String, Option<HashMap<String, HBox<'b, T>>>)>,
         grid_rows: Vec<Vec<Option<HBox<'b, T>>>>,
     ) -> Grid<'b, T> {
         let meta = HashMap::with_capacity(0);
         let mut col_index: HashMap<String, _> = HashMap::new();
-        let mut cols: Vector<HCol<'_, T>> = Vector::new();
+        let mut cols: Vector<HCol<'_, T>> = Vector::new_with_ptr_kind();
 
         for (name, meta) in columns.into_iter() {
             if !col_index.contains_key(name.as_str()) {
@@ -144,16 +186,16 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
         let rows = grid_rows
             .into_iter()
             .map(|r| {
-                let mut v = Vector::new();
+                let mut v = Vector::new_with_ptr_kind();
 
                 r.into_iter().for_each(|val| v = v.push_back(val));
 
-                Rc::from(v)
+                Ptr::from(v)
             })
             .collect();
 
-        let meta = RefCell::new(HDict::from_map(meta));
-        let col_index = Rc::new(col_index);
+        let meta = HDict::from_map(meta);
+        let col_index = Ptr::new(col_index);
 
         HGrid::Grid {
             meta,
@@ -171,7 +213,7 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
             HGrid::Grid {
                 meta: orig_meta, ..
             } => {
-                orig_meta.borrow_mut().extend(meta);
+                orig_meta.extend(meta);
             }
             HGrid::Error { .. } => {
                 return Err(HGridErr::NotImplemented);
@@ -218,9 +260,9 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
             } => {
                 let r = rows.get(key).ok_or(HGridErr::IndexErr)?;
                 Ok(HRow::new(
-                    Rc::downgrade(col_index),
+                    Ptr::downgrade(col_index),
                     cols.clone(),
-                    Rc::downgrade(r),
+                    Ptr::downgrade(r),
                 ))
             }
             _ => Err(HGridErr::IndexErr),
@@ -261,7 +303,7 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
 
     pub fn meta(&self) -> HDict<'a, T> {
         match self {
-            HGrid::Grid { meta, .. } => meta.borrow().clone(),
+            HGrid::Grid { meta, .. } => meta.clone(),
             HGrid::Error { dis, errTrace } => todo!("Not implemented"),
             HGrid::Empty { meta } => todo!("Not implemented"),
         }
@@ -308,13 +350,7 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
                 ..
             } => rows
                 .iter()
-                .map(|row| {
-                    HRow::new(
-                        Rc::downgrade(col_index),
-                        cols.clone(),
-                        Rc::<Vector<Option<Rc<(dyn HVal<'a, T>)>>>>::downgrade(row),
-                    )
-                })
+                .map(|row| HRow::new(Ptr::downgrade(col_index), cols.clone(), Ptr::downgrade(row)))
                 .collect::<Vec<HRow<'a, T>>>()
                 .into_iter(),
             HGrid::Empty { .. } => panic!("Empty grid"), //Box::new(std::iter::empty()),
@@ -334,9 +370,8 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
         match self {
             HGrid::Grid { meta, rows, .. } => {
                 write!(f, "ver:\"3.0\" ")?;
-                if !meta.borrow().is_empty() {
-                    let meta_borrow = meta.borrow();
-                    let mut iter = meta_borrow.iter().peekable();
+                if !meta.is_empty() {
+                    let mut iter = meta.iter().peekable();
                     while let Some((k, v)) = iter.next() {
                         write!(f, " {}", k.as_str())?;
                         match v.haystack_type() {
@@ -426,55 +461,104 @@ impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
     }
 
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_hayson(f, JsonVersion::V3)
+    }
+
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write!(f, "{{")?;
+        if version == JsonVersion::V4 {
+            write!(f, "\"_kind\":\"grid\",")?;
+        }
+
+        let meta = match self {
+            HGrid::Grid { .. } => self.meta(),
+            HGrid::Error { dis, errTrace } => {
+                let mut meta = HDict::new();
+                meta.set("err".to_owned(), Ptr::new(HMarker));
+                meta.set("dis".to_owned(), Ptr::new(HStr(dis.clone())));
+                if let Some(trace) = errTrace {
+                    meta.set("errTrace".to_owned(), Ptr::new(HStr(trace.clone())));
+                }
+                meta
+            }
+            HGrid::Empty { meta } => HDict::from_map(meta.clone().unwrap_or_default()),
+        };
+        write!(f, "\"meta\":{{\"ver\":\"3.0\"")?;
+        for (k, v) in meta
+            .iter()
+            .filter(|(k, v)| *k != "ver" && v.get_null().is_none())
+        {
+            write!(f, ",")?;
+            write_str(f, k)?;
+            write!(f, ":")?;
+            v.to_hayson(f, version)?;
+        }
+        write!(f, "}},\"cols\":[")?;
+
         match self {
-            HGrid::Grid { meta, cols, .. } => {
-                write!(f, "{{\n")?;
-                write!(f, "  \"meta\": {{\"ver\":\"3.0\"")?;
-                self.meta().iter().try_for_each(|(k, v)| {
-                    write!(f, ", \"{}\":\"", k)?;
-                    v.to_json(f)?;
-                    write!(f, "\"")
-                })?;
-                write!(f, "}},\n")?;
-
-                write!(f, "  \"cols\": [\n")?;
-                let mut cols_iter = self.iter_cols().enumerate();
-                let num_cols = cols.len();
-                cols_iter.try_for_each(|(i, c)| {
-                    let col_meta = c.meta();
-                    write!(f, "{{\"name\":\"{}\"", c.name)?;
-                    let mut col_meta_iter = col_meta.iter().enumerate();
-                    col_meta_iter.try_for_each(|(_j, (k, v))| {
-                        write!(f, ", \"{}\":\"", k)?;
-                        v.to_json(f)?;
-                        write!(f, "\"")?;
-                        Ok(())
-                    })?;
-                    write!(f, "}}")?;
-                    if i + 1 < num_cols {
+            HGrid::Grid { cols, .. } => {
+                let mut cols_iter = cols.iter().peekable();
+                while let Some(c) = cols_iter.next() {
+                    c.to_hayson(f, version)?;
+                    if cols_iter.peek().is_some() {
                         write!(f, ",")?;
                     }
-                    Ok(())
-                })?;
-                write!(f, "],\n")?;
-
-                write!(f, "  \"rows\": [\n")?;
-                let mut row_iter = self.iter().enumerate();
-                let num_rows = self.len();
-                row_iter.try_for_each(|(i, r)| {
-                    r.to_json(f)?;
-                    if i + 1 < num_rows {
-                        write!(f, ",")?;
-                    }
-                    Ok(())
-                })?;
-                write!(f, "],\n")?;
+                }
+            }
+            HGrid::Error { .. } => write!(f, "{{\"name\":\"empty\"}}")?,
+            HGrid::Empty { .. } => (),
+        }
+        write!(f, "],\"rows\":[")?;
 
-                write!(f, "}},\n")?;
+        if let HGrid::Grid { .. } = self {
+            let mut row_iter = self.iter().peekable();
+            while let Some(r) = row_iter.next() {
+                r.to_hayson(f, version)?;
+                if row_iter.peek().is_some() {
+                    write!(f, ",")?;
+                }
             }
-            _ => {}
         }
-        Ok(())
+        write!(f, "]}}")
+    }
+
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        let (meta, cols): (_, Vec<_>) = match self {
+            HGrid::Grid { meta, cols, .. } => (meta.clone(), cols.iter().cloned().collect()),
+            HGrid::Error { dis, errTrace } => {
+                let mut meta = HDict::new();
+                meta.set("err".to_owned(), Ptr::new(HMarker));
+                meta.set("dis".to_owned(), Ptr::new(HStr(dis.clone())));
+                if let Some(trace) = errTrace {
+                    meta.set("errTrace".to_owned(), Ptr::new(HStr(trace.clone())));
+                }
+                (meta, vec![HCol::new("empty".to_owned(), None)])
+            }
+            HGrid::Empty { meta } => (HDict::from_map(meta.clone().unwrap_or_default()), vec![]),
+        };
+
+        enc.u8(ctrl::GRID);
+        enc.u8(b'<');
+        enc.var_int(cols.len() as i64);
+        let rows: Vec<_> = match self {
+            HGrid::Grid { .. } => self.iter().collect(),
+            _ => vec![],
+        };
+        enc.var_int(rows.len() as i64);
+        meta.to_brio(enc);
+        for col in cols.iter() {
+            enc.str(col.name());
+            col.meta().to_brio(enc);
+        }
+        for row in rows.iter() {
+            for col in cols.iter() {
+                match row.get(col.name()) {
+                    Some(val) => val.to_brio(enc),
+                    None => enc.u8(ctrl::NULL),
+                }
+            }
+        }
+        enc.u8(b'>');
     }
 }
 
@@ -566,8 +650,75 @@ impl<'a, T: 'a + NumTrait> HVal<'a, T> for HGrid<'a, T> {
         THIS_TYPE
     }
 
-    fn _eq(&self, other: &dyn HVal<'a, T>) -> bool {
-        false
+    set_trait_eq_method!(get_grid,'a,T);
+    set_trait_hash_method!();
+}
+
+/// Grids are equal when their meta, columns in order and cells match, a missing empty meta equals
+/// an empty one
+impl<'a, T: NumTrait + 'a> PartialEq for HGrid<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                HGrid::Grid {
+                    meta, cols, rows, ..
+                },
+                HGrid::Grid {
+                    meta: other_meta,
+                    cols: other_cols,
+                    rows: other_rows,
+                    ..
+                },
+            ) => meta == other_meta && cols == other_cols && rows == other_rows,
+            (
+                HGrid::Error { dis, errTrace },
+                HGrid::Error {
+                    dis: other_dis,
+                    errTrace: other_trace,
+                },
+            ) => dis == other_dis && errTrace == other_trace,
+            (HGrid::Empty { meta }, HGrid::Empty { meta: other_meta }) => {
+                match (meta, other_meta) {
+                    (Some(meta), Some(other_meta)) => meta == other_meta,
+                    (Some(meta), None) | (None, Some(meta)) => meta.is_empty(),
+                    (None, None) => true,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Eq for HGrid<'a, T> {}
+
+/// Grids have no natural order, they only compare when equal
+impl<'a, T: NumTrait + 'a> PartialOrd for HGrid<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self == other).then_some(Ordering::Equal)
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Hash for HGrid<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            HGrid::Grid {
+                meta, cols, rows, ..
+            } => {
+                meta.hash(state);
+                state.write_usize(cols.len());
+                cols.iter().for_each(|col| col.hash(state));
+                rows.hash(state);
+            }
+            HGrid::Error { dis, errTrace } => {
+                dis.hash(state);
+                errTrace.hash(state);
+            }
+            HGrid::Empty { meta } => match meta {
+                Some(meta) => hash_tags(meta, state),
+                None => hash_tags::<T, H>(&HashMap::new(), state),
+            },
+        }
     }
 }
 
@@ -611,6 +762,59 @@ mod tests {
         }
         println!("GRID\n{}", buf);
     }
+
+    #[test]
+    fn grid_deep_eq_and_hash() {
+        use crate::io::parse::zinc::parse_grid;
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |grid: &HGrid<f64>| {
+            let mut hasher = DefaultHasher::new();
+            grid.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let zinc = "ver:\"3.0\" view:\"table\"\nid dis:\"Id\",val\n@a,[1, {x}]\n@b,\n";
+        let grid = parse_grid::<f64>(zinc).unwrap();
+        let same = parse_grid::<f64>(zinc).unwrap();
+        assert!(grid == same);
+        assert_eq!(hash(&grid), hash(&same));
+        assert!(grid._eq(&same));
+
+        let col_meta = parse_grid::<f64>(&zinc.replace("\"Id\"", "\"Ref\"")).unwrap();
+        let cell = parse_grid::<f64>(&zinc.replace("{x}", "{y}")).unwrap();
+        let meta = parse_grid::<f64>(&zinc.replace("table", "chart")).unwrap();
+        assert!(grid != col_meta);
+        assert!(grid != cell);
+        assert!(grid != meta);
+
+        let empty: HGrid<f64> = HGrid::Empty { meta: None };
+        assert!(
+            empty
+                == HGrid::Empty {
+                    meta: Some(HashMap::new())
+                }
+        );
+        assert!(empty != grid);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn grid_crosses_threads() {
+        fn assert_send_sync<S: Send + Sync>() {}
+        assert_send_sync::<HGrid<'static, f64>>();
+        assert_send_sync::<HDict<'static, f64>>();
+        assert_send_sync::<HBox<'static, f64>>();
+
+        let zinc = "ver:\"3.0\"\nid,val\n@a,1kW\n@b,2kW\n";
+        let grid =
+            std::thread::spawn(move || crate::io::parse::zinc::parse_grid::<f64>(zinc).unwrap())
+                .join()
+                .unwrap();
+        assert_eq!(grid.len(), 2);
+        let val = grid.get(1).unwrap().get("val").unwrap();
+        assert_eq!(val.get_number().unwrap().val(), 2.0);
+    }
 }
 
 // TODO: Implement serialisation tests for HGrid
@@ -0,0 +1,443 @@
+use super::builder::is_col_name;
+use super::{Col, HGrid, HGridErr, HRow, Vector};
+use crate::HVal;
+use crate::NumTrait;
+use crate::h_dict::HDict;
+use crate::h_number::{HNumber, float_cmp};
+use crate::h_val::{HBox, Ptr};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Where each column of a new grid takes its cells from, an index into the old columns or a
+/// computed value
+enum Source<'f, 'a, T: NumTrait + 'a> {
+    Col(usize),
+    Computed(&'f mut dyn FnMut(&HRow<'a, T>) -> Option<HBox<'a, T>>),
+}
+
+/// Groups numbers whose units convert into each other by their quantity, and others by unit
+fn unit_key<T: NumTrait>(num: &HNumber<T>) -> Option<(&'static str, &str)> {
+    let unit = num.unit().as_ref()?;
+    Some(match unit.def() {
+        Some(def) if def.quantity() != "currency" => (def.quantity(), ""),
+        Some(def) => (def.quantity(), def.symbol()),
+        None => ("", unit.as_str()),
+    })
+}
+
+/// Haystack order made total for sorting. Numbers in units that convert into each other sort
+/// by their converted value, so 1500W follows 1kW. Numbers in other units, which Haystack
+/// leaves unordered, sort by quantity, then unit and value, and dicts and grids that differ
+/// sort as equal.
+fn cmp_vals<'a, T: NumTrait + 'a>(
+    a: &(dyn HVal<'a, T> + 'a),
+    b: &(dyn HVal<'a, T> + 'a),
+) -> Ordering {
+    if let Some(ord) = a.partial_cmp(b) {
+        return ord;
+    }
+    if let (Some(a), Some(b)) = (a.get_number(), b.get_number()) {
+        let unit = |n: &HNumber<T>| n.unit().as_ref().map(|unit| unit.as_str().to_owned());
+        return unit_key(a)
+            .cmp(&unit_key(b))
+            .then_with(|| {
+                let b_val = a
+                    .unit()
+                    .as_ref()
+                    .and_then(|unit| b.convert_to(unit).ok())
+                    .map_or(b.val(), |b| b.val());
+                float_cmp(a.val(), b_val)
+            })
+            .then_with(|| unit(a).cmp(&unit(b)));
+    }
+    if let (Some(a), Some(b)) = (a.get_list(), b.get_list()) {
+        return a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| cmp_vals(a.as_ref(), b.as_ref()))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len()));
+    }
+    Ordering::Equal
+}
+
+/// Sort order of cells, with missing cells first
+fn cmp_cells<'a, T: NumTrait + 'a>(a: Option<HBox<'a, T>>, b: Option<HBox<'a, T>>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp_vals(a.as_ref(), b.as_ref()),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Operations returning new grids, grid and column meta carry over unchanged
+impl<'a, T: NumTrait + 'a> HGrid<'a, T> {
+    pub(super) fn col_idx(&self, name: &str) -> Result<usize, HGridErr> {
+        match self {
+            HGrid::Grid { col_index, .. } => col_index.get(name).copied(),
+            _ => None,
+        }
+        .ok_or_else(|| HGridErr::UnknownCol(name.to_owned()))
+    }
+
+    fn check_new_col(&self, name: &str) -> Result<(), HGridErr> {
+        if !is_col_name(name) {
+            return Err(HGridErr::InvalidColName(name.to_owned()));
+        }
+        match self.has(name) {
+            true => Err(HGridErr::DuplicateCol(name.to_owned())),
+            false => Ok(()),
+        }
+    }
+
+    /// The same columns with the rows `f` keeps, in the order it returns them
+    fn with_rows(
+        &self,
+        f: impl FnOnce(Vec<HRow<'a, T>>) -> Vec<HRow<'a, T>>,
+    ) -> Result<Self, HGridErr> {
+        match self {
+            HGrid::Grid {
+                meta,
+                col_index,
+                cols,
+                ..
+            } => {
+                let rows = f(self.iter().collect())
+                    .into_iter()
+                    .filter_map(|row| row.inner.upgrade())
+                    .collect();
+                Ok(HGrid::Grid {
+                    meta: meta.clone(),
+                    col_index: col_index.clone(),
+                    cols: cols.clone(),
+                    rows,
+                })
+            }
+            HGrid::Empty { .. } => Ok(self.clone()),
+            HGrid::Error { dis, .. } => Err(HGridErr::ErrorGrid(dis.clone())),
+        }
+    }
+
+    fn with_cols(&self, new_cols: Vec<(Col<'a, T>, Source<'_, 'a, T>)>) -> Result<Self, HGridErr> {
+        let (meta, rows) = match self {
+            HGrid::Grid { meta, .. } => (meta.clone(), self.iter().collect()),
+            HGrid::Empty { meta } => (HDict::from_map(meta.clone().unwrap_or_default()), vec![]),
+            HGrid::Error { dis, .. } => return Err(HGridErr::ErrorGrid(dis.clone())),
+        };
+        if new_cols.is_empty() {
+            let meta = (!meta.is_empty()).then(|| meta.into_map());
+            return Ok(HGrid::Empty { meta });
+        }
+
+        let (cols, mut sources): (Vector<_>, Vec<_>) = new_cols.into_iter().unzip();
+        let rows = rows
+            .iter()
+            .map(|row: &HRow<'a, T>| {
+                let cells = row.inner.upgrade();
+                let row_vec: Vector<_> = sources
+                    .iter_mut()
+                    .map(|source| match source {
+                        Source::Col(idx) => cells.as_ref()?.get(*idx)?.clone(),
+                        Source::Computed(f) => f(row),
+                    })
+                    .collect();
+                Ptr::new(row_vec)
+            })
+            .collect();
+        let col_index: HashMap<_, _> = cols
+            .iter()
+            .enumerate()
+            .map(|(idx, col)| (col.name.clone(), idx))
+            .collect();
+
+        Ok(HGrid::Grid {
+            meta,
+            col_index: Ptr::new(col_index),
+            cols,
+            rows,
+        })
+    }
+
+    fn cols_with_source(&self) -> Vec<(Col<'a, T>, Source<'_, 'a, T>)> {
+        self.iter_cols()
+            .enumerate()
+            .map(|(idx, col)| (col, Source::Col(idx)))
+            .collect()
+    }
+
+    /// Keeps only the named columns, in the order given
+    pub fn select(&self, names: &[&str]) -> Result<Self, HGridErr> {
+        let cols: Vec<_> = self.iter_cols().collect();
+        let new_cols = names
+            .iter()
+            .map(|name| {
+                let idx = self.col_idx(name)?;
+                Ok((cols[idx].clone(), Source::Col(idx)))
+            })
+            .collect::<Result<_, HGridErr>>()?;
+        self.with_cols(new_cols)
+    }
+
+    pub fn remove_cols(&self, names: &[&str]) -> Result<Self, HGridErr> {
+        for name in names {
+            self.col_idx(name)?;
+        }
+        let mut new_cols = self.cols_with_source();
+        new_cols.retain(|(col, _)| !names.contains(&col.name()));
+        self.with_cols(new_cols)
+    }
+
+    pub fn rename_col(&self, from: &str, to: &str) -> Result<Self, HGridErr> {
+        let idx = self.col_idx(from)?;
+        if from == to {
+            return Ok(self.clone());
+        }
+        self.check_new_col(to)?;
+        let mut new_cols = self.cols_with_source();
+        new_cols[idx].0.name = to.to_owned();
+        self.with_cols(new_cols)
+    }
+
+    /// Appends a column computed from each row
+    pub fn add_col(
+        &self,
+        name: &str,
+        meta: HDict<'a, T>,
+        mut f: impl FnMut(&HRow<'a, T>) -> Option<HBox<'a, T>>,
+    ) -> Result<Self, HGridErr> {
+        self.check_new_col(name)?;
+        let meta = (!meta.is_empty()).then(|| meta.into_map());
+        let mut new_cols = self.cols_with_source();
+        new_cols.push((Col::new(name.to_owned(), meta), Source::Computed(&mut f)));
+        self.with_cols(new_cols)
+    }
+
+    pub fn filter(&self, mut f: impl FnMut(&HRow<'a, T>) -> bool) -> Result<Self, HGridErr> {
+        self.with_rows(|rows| rows.into_iter().filter(|row| f(row)).collect())
+    }
+
+    /// Stable sort with a comparison of whole rows
+    pub fn sort_by(
+        &self,
+        mut cmp: impl FnMut(&HRow<'a, T>, &HRow<'a, T>) -> Ordering,
+    ) -> Result<Self, HGridErr> {
+        self.with_rows(|mut rows| {
+            rows.sort_by(|a, b| cmp(a, b));
+            rows
+        })
+    }
+
+    /// Sorts ascending by each column in turn, in Haystack order with empty cells first
+    pub fn sort_by_cols(&self, names: &[&str]) -> Result<Self, HGridErr> {
+        for name in names {
+            self.col_idx(name)?;
+        }
+        self.sort_by(|a, b| {
+            names
+                .iter()
+                .map(|name| cmp_cells(a.get(name), b.get(name)))
+                .find(|ord| ord.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+
+    pub fn take(&self, n: usize) -> Result<Self, HGridErr> {
+        self.with_rows(|rows| rows.into_iter().take(n).collect())
+    }
+
+    pub fn skip(&self, n: usize) -> Result<Self, HGridErr> {
+        self.with_rows(|rows| rows.into_iter().skip(n).collect())
+    }
+
+    /// Drops rows equal to an earlier row
+    pub fn distinct(&self) -> Result<Self, HGridErr> {
+        self.with_rows(|rows| {
+            let mut seen = HashSet::new();
+            rows.into_iter()
+                .filter(|row| row.inner.upgrade().is_some_and(|cells| seen.insert(cells)))
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid;
+    use crate::h_grid::GridBuilder;
+    use crate::h_grid::test_util::{names, strs};
+    use crate::h_number::HUnit;
+    use crate::h_str::HStr;
+
+    fn sites() -> HGrid<'static, f64> {
+        grid!(
+            { dis: "Sites" },
+            [dis, area, city],
+            ["B", 200.0, "Sydney"],
+            ["A", 100.0, "Perth"],
+            ["C", None::<HBox<f64>>, "Sydney"],
+            ["A", 100.0, "Perth"],
+        )
+    }
+
+    fn col<'a>(grid: &HGrid<'a, f64>, name: &str) -> Vec<Option<HBox<'a, f64>>> {
+        grid.iter().map(|row| row.get(name)).collect()
+    }
+
+    #[test]
+    fn select_and_remove() {
+        let grid = sites()
+            .add_col_meta(
+                "city",
+                HashMap::from([("doc".to_owned(), Ptr::new(HStr("x".into())) as HBox<f64>)]),
+            )
+            .unwrap();
+        let selected = grid.select(&["city", "dis"]).unwrap();
+        assert_eq!(names(&selected), ["city", "dis"]);
+        assert_eq!(strs(&selected, "dis"), ["B", "A", "C", "A"]);
+        assert!(selected.iter_cols().next().unwrap().has("doc"));
+        assert!(selected.meta().has("dis"));
+
+        let removed = grid.remove_cols(&["area"]).unwrap();
+        assert_eq!(names(&removed), ["dis", "city"]);
+        assert_eq!(strs(&removed, "city"), strs(&grid, "city"));
+
+        assert!(matches!(
+            grid.select(&["nope"]),
+            Err(HGridErr::UnknownCol(name)) if name == "nope"
+        ));
+        assert!(matches!(
+            grid.remove_cols(&["dis", "area", "city"]).unwrap(),
+            HGrid::Empty { meta: Some(_) }
+        ));
+    }
+
+    #[test]
+    fn rename_and_add() {
+        let grid = sites();
+        let renamed = grid.rename_col("dis", "name").unwrap();
+        assert_eq!(names(&renamed), ["name", "area", "city"]);
+        assert_eq!(strs(&renamed, "name"), strs(&grid, "dis"));
+        assert!(matches!(
+            grid.rename_col("dis", "city"),
+            Err(HGridErr::DuplicateCol(_))
+        ));
+        assert!(matches!(
+            grid.rename_col("dis", "Bad"),
+            Err(HGridErr::InvalidColName(_))
+        ));
+
+        let doubled = grid
+            .add_col("double", HDict::new(), |row| {
+                let area = row.get("area")?;
+                let area = area.get_number()?;
+                Some(Ptr::new(HNumber::new(area.val() * 2.0, None)) as HBox<f64>)
+            })
+            .unwrap();
+        assert_eq!(names(&doubled), ["dis", "area", "city", "double"]);
+        let doubled: Vec<_> = col(&doubled, "double")
+            .into_iter()
+            .map(|cell| cell.map(|val| val.get_number().unwrap().val()))
+            .collect();
+        assert_eq!(doubled, [Some(400.0), Some(200.0), None, Some(200.0)]);
+    }
+
+    #[test]
+    fn filter_sort_take_skip() {
+        let grid = sites();
+        let sydney = grid
+            .filter(|row| {
+                row.get("city")
+                    .is_some_and(|c| c.get_string().unwrap().0 == "Sydney")
+            })
+            .unwrap();
+        assert_eq!(strs(&sydney, "dis"), ["B", "C"]);
+
+        let sorted = grid.sort_by_cols(&["area", "dis"]).unwrap();
+        assert_eq!(strs(&sorted, "dis"), ["C", "A", "A", "B"]);
+        let sorted = grid.sort_by_cols(&["city", "dis"]).unwrap();
+        assert_eq!(strs(&sorted, "dis"), ["A", "A", "B", "C"]);
+        let reversed = grid
+            .sort_by(|a, b| cmp_cells(b.get("dis"), a.get("dis")))
+            .unwrap();
+        assert_eq!(strs(&reversed, "dis"), ["C", "B", "A", "A"]);
+
+        assert_eq!(
+            strs(&grid.skip(1).unwrap().take(2).unwrap(), "dis"),
+            ["A", "C"]
+        );
+        assert_eq!(grid.take(10).unwrap().len(), 4);
+        assert!(grid.skip(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sort_mixed_units() {
+        let num = |val: f64, unit: Option<&str>| {
+            HNumber::new(val, unit.map(|unit| HUnit::new(unit.into())))
+        };
+        let mut builder = GridBuilder::new().add_col("val", HDict::new()).unwrap();
+        for cell in [
+            num(2.0, Some("kW")),
+            num(20.0, Some("°C")),
+            num(5.0, None),
+            num(1500.0, Some("W")),
+            num(50.0, Some("°F")),
+            num(1.0, Some("kW")),
+            num(900.0, Some("W")),
+            num(3.0, None),
+        ] {
+            builder = builder.add_row([cell]).unwrap();
+        }
+        let grid: HGrid<f64> = builder.build();
+        let sorted: Vec<_> = col(&grid.sort_by_cols(&["val"]).unwrap(), "val")
+            .into_iter()
+            .map(|cell| cell.unwrap().get_number().unwrap().clone())
+            .collect();
+        assert_eq!(
+            sorted,
+            [
+                num(3.0, None),
+                num(5.0, None),
+                num(900.0, Some("W")),
+                num(1.0, Some("kW")),
+                num(1500.0, Some("W")),
+                num(2.0, Some("kW")),
+                num(50.0, Some("°F")),
+                num(20.0, Some("°C")),
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct_rows() {
+        let grid = sites();
+        let distinct = grid.distinct().unwrap();
+        assert_eq!(strs(&distinct, "dis"), ["B", "A", "C"]);
+        assert_eq!(
+            distinct
+                .select(&["city"])
+                .unwrap()
+                .distinct()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn error_grids() {
+        let err: HGrid<f64> = HGrid::Error {
+            dis: "boom".into(),
+            errTrace: None,
+        };
+        assert!(matches!(
+            err.take(1),
+            Err(HGridErr::ErrorGrid(dis)) if dis == "boom"
+        ));
+        let empty: HGrid<f64> = HGrid::Empty { meta: None };
+        assert_eq!(empty.filter(|_| true).unwrap(), empty);
+        assert_eq!(
+            names(&empty.add_col("a", HDict::new(), |_| None).unwrap()),
+            ["a"]
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! Helpers shared by the grid tests
+
+use super::HGrid;
+
+pub(super) fn names(grid: &HGrid<f64>) -> Vec<String> {
+    grid.iter_cols().map(|col| col.name).collect()
+}
+
+pub(super) fn strs(grid: &HGrid<f64>, name: &str) -> Vec<String> {
+    grid.iter()
+        .map(|row| match row.get(name) {
+            Some(val) => val.get_string().unwrap().0.clone(),
+            None => "-".to_owned(),
+        })
+        .collect()
+}
@@ -1,6 +1,10 @@
 use crate::h_val::HBox;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
 use crate::{HType, HVal, NumTrait};
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Index;
 
 #[derive(Clone)]
@@ -44,6 +48,10 @@ impl<'a, T: NumTrait> HList<'a, T> {
         self.inner.push(value);
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &HBox<'a, T>> {
+        self.inner.iter()
+    }
+
     pub fn to_zinc<'b>(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[")?;
         let inner = &self.inner;
@@ -61,18 +69,31 @@ impl<'a, T: NumTrait> HList<'a, T> {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
         write!(f, "[")?;
         let mut elements = self.inner.iter().peekable();
         while let Some(v) = elements.next() {
-            write!(f, "\"")?;
-            v.to_json(f)?;
-            write!(f, "\"")?;
+            v.to_hayson(f, version)?;
             if elements.peek().is_some() {
                 write!(f, ",")?;
             }
         }
         write!(f, "]")
     }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        if self.inner.is_empty() {
+            return enc.u8(ctrl::LIST_EMPTY);
+        }
+        enc.u8(ctrl::LIST);
+        enc.u8(b'[');
+        enc.var_int(self.inner.len() as i64);
+        for v in self.inner.iter() {
+            v.to_brio(enc);
+        }
+        enc.u8(b']');
+    }
 }
 
 impl<'a, T: NumTrait> HVal<'a, T> for HList<'a, T> {
@@ -80,8 +101,34 @@ impl<'a, T: NumTrait> HVal<'a, T> for HList<'a, T> {
         THIS_TYPE
     }
 
-    fn _eq(&self, _other: &dyn HVal<'a, T>) -> bool {
-        false
+    set_trait_eq_method!(get_list,'a,T);
+    set_trait_hash_method!();
+}
+
+impl<'a, T: NumTrait + 'a> PartialEq for HList<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Eq for HList<'a, T> {}
+
+/// Compares element by element, a shorter list sorts before a longer one it prefixes
+impl<'a, T: NumTrait + 'a> PartialOrd for HList<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        for (a, b) in self.inner.iter().zip(other.inner.iter()) {
+            match a.as_ref().partial_cmp(b.as_ref())? {
+                Ordering::Equal => continue,
+                ord => return Some(ord),
+            }
+        }
+        Some(self.inner.len().cmp(&other.inner.len()))
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Hash for HList<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
     }
 }
 
@@ -146,8 +193,9 @@ mod tests {
             HNumber::new(1f64, None).to_hbox(),
             HNumber::new(2f64, None).to_hbox(),
         ]);
-        assert!(!hlist1._eq(&hlist2));
-        assert!(!hlist1._eq(&hlist2));
+        assert!(hlist1._eq(&hlist2));
+        assert!(hlist2._eq(&hlist1));
         assert!(!hlist1._eq(&hlist3));
+        assert!(hlist3._eq(&hlist3.clone()));
     }
 }
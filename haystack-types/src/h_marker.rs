@@ -1,14 +1,16 @@
 use crate::common::Txt;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use std::fmt;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct HMarker;
 
 pub const MARKER: HMarker = HMarker {};
 
 const ZINC: Txt = Txt::Const("M");
-const JSON: Txt = Txt::Const("m:");
 
 const THIS_TYPE: HType = HType::Marker;
 
@@ -20,7 +22,13 @@ impl HMarker {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", JSON)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write_scalar(f, version, "marker", &[])
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::MARKER);
     }
 }
 
@@ -30,6 +38,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HMarker {
     }
 
     set_trait_eq_method!(get_marker,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
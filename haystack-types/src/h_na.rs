@@ -1,14 +1,16 @@
 use crate::common::Txt;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use std::fmt;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct HNA;
 
 pub const NA: HNA = HNA {};
 
 const ZINC: Txt = Txt::Const("NA");
-const JSON: Txt = Txt::Const("z:");
 
 const THIS_TYPE: HType = HType::NA;
 
@@ -20,7 +22,13 @@ impl HNA {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", JSON)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write_scalar(f, version, "na", &[])
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::NA);
     }
 }
 
@@ -30,6 +38,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HNA {
     }
 
     set_trait_eq_method!(get_na,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
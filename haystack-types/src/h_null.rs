@@ -1,8 +1,10 @@
 use crate::common::Txt;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
 use crate::{HType, HVal, NumTrait};
 use std::fmt;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct HNull;
 
 pub const NULL: HNull = HNull {};
@@ -20,8 +22,14 @@ impl HNull {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, _version: JsonVersion) -> fmt::Result {
         write!(f, "{}", JSON)
     }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::NULL);
+    }
 }
 
 impl<'a, T: NumTrait + 'a> HVal<'a, T> for HNull {
@@ -30,6 +38,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HNull {
     }
 
     set_trait_eq_method!(get_null,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -1,10 +1,19 @@
+use crate::err::UnitErr;
+use crate::h_val::MaybeSync;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_str;
+use crate::units::{self, UnitDef};
 use crate::{HType, HVal};
-use num::Float;
+use num::{Float, NumCast};
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 use std::str::FromStr;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct HUnit(String);
 
 impl HUnit {
@@ -15,6 +24,29 @@ impl HUnit {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// The unit's entry in the units database, `None` for unknown units
+    pub fn def(&self) -> Option<&'static UnitDef> {
+        units::find(&self.0)
+    }
+
+    /// Checks the unit is in the units database
+    pub fn validate(&self) -> Result<&'static UnitDef, UnitErr> {
+        self.def().ok_or_else(|| UnitErr::Unknown(self.0.clone()))
+    }
+}
+
+/// Parses a unit, failing unless it is in the units database. The Zinc, JSON and other
+/// readers keep units as written, so validating them is opt-in through `str::parse` or
+/// `HUnit::validate`.
+impl FromStr for HUnit {
+    type Err = UnitErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unit = HUnit(s.to_owned());
+        unit.validate()?;
+        Ok(unit)
+    }
 }
 
 impl From<String> for HUnit {
@@ -23,19 +55,76 @@ impl From<String> for HUnit {
     }
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct HNumber<T: Display> {
     val: T,
     unit: Option<HUnit>,
 }
 
 pub type Number<T> = HNumber<T>;
-pub trait NumTrait: Float + Display + Debug + FromStr {}
-impl<T> NumTrait for T where T: Float + Display + Debug + FromStr {}
+pub trait NumTrait: Float + Display + Debug + FromStr + MaybeSync {}
+impl<T> NumTrait for T where T: Float + Display + Debug + FromStr + MaybeSync {}
 //impl<'a,T> NumTrait for T where T: 'a + Float + Display + FromStr {}
 
 const THIS_TYPE: HType = HType::Number;
 
+/// Casts between numeric types, NaN where the value doesn't fit
+pub(crate) fn cast<N: NumCast, T: Float>(val: N) -> T {
+    <T as NumCast>::from(val).unwrap_or_else(T::nan)
+}
+
+/// Float equality where NaN equals NaN, as in Haystack, so numbers can be `Eq`
+pub(crate) fn float_eq<T: Float>(a: T, b: T) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+/// Hashes consistently with `float_eq`, so every NaN and both zeros hash alike
+pub(crate) fn hash_float<T: Float, H: Hasher>(val: T, state: &mut H) {
+    let val = if val.is_nan() {
+        T::nan()
+    } else if val.is_zero() {
+        T::zero()
+    } else {
+        val
+    };
+    val.integer_decode().hash(state);
+}
+
+/// Total float order agreeing with `float_eq`, NaN sorts after every number
+pub(crate) fn float_cmp<T: Float>(a: T, b: T) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+impl<T: Float + Display> PartialEq for HNumber<T> {
+    fn eq(&self, other: &Self) -> bool {
+        float_eq(self.val, other.val) && self.unit == other.unit
+    }
+}
+
+impl<T: Float + Display> Eq for HNumber<T> {}
+
+/// Only numbers in the same unit are comparable
+impl<T: Float + Display> PartialOrd for HNumber<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.unit == other.unit {
+            true => Some(float_cmp(self.val, other.val)),
+            false => None,
+        }
+    }
+}
+
+impl<T: Float + Display> Hash for HNumber<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_float(self.val, state);
+        self.unit.hash(state);
+    }
+}
+
 impl<T: Float + Display> Number<T> {
     pub fn new(num: T, unit: Option<HUnit>) -> Self {
         HNumber { val: num, unit }
@@ -48,6 +137,16 @@ impl<T: Float + Display> Number<T> {
     pub fn unit(&self) -> &Option<HUnit> {
         &self.unit
     }
+
+    /// Converts to a unit of the same quantity, both units must be in the units database
+    pub fn convert_to(&self, unit: &HUnit) -> Result<Self, UnitErr> {
+        let from = self.unit.as_ref().ok_or(UnitErr::Unitless)?;
+        if from == unit {
+            return Ok(self.clone());
+        }
+        let val = from.validate()?.convert(self.val, unit.validate()?)?;
+        Ok(HNumber::new(val, Some(unit.clone())))
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.unit {
             Some(unit) => write!(f, "{}{}", self.val, unit),
@@ -58,10 +157,192 @@ impl<T: Float + Display> Number<T> {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.unit {
-            Some(unit) => write!(f, "n:{} {}", self.val, unit),
-            None => write!(f, "n:{}", self.val),
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        let val = if self.val.is_nan() {
+            "NaN".to_owned()
+        } else if self.val.is_infinite() {
+            if self.val > T::zero() { "INF" } else { "-INF" }.to_owned()
+        } else {
+            self.val.to_string()
+        };
+
+        match version {
+            JsonVersion::V4 => {
+                write!(f, "{{\"_kind\":\"number\",\"val\":")?;
+                if self.val.is_finite() {
+                    write!(f, "{}", val)?;
+                } else {
+                    write_str(f, &val)?;
+                }
+                if let Some(unit) = &self.unit {
+                    write!(f, ",\"unit\":")?;
+                    write_str(f, unit.as_str())?;
+                }
+                write!(f, "}}")
+            }
+            JsonVersion::V3 => match &self.unit {
+                Some(unit) => write_str(f, &format!("n:{} {}", val, unit.as_str())),
+                None => write_str(f, &format!("n:{}", val)),
+            },
+        }
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        let val: f64 = cast(self.val);
+        if val.fract() == 0.0 && (i16::MIN as f64..=i16::MAX as f64).contains(&val) {
+            enc.u8(ctrl::NUMBER_I2);
+            enc.i16(val as i16);
+        } else if val.fract() == 0.0 && (i32::MIN as f64..=i32::MAX as f64).contains(&val) {
+            enc.u8(ctrl::NUMBER_I4);
+            enc.i32(val as i32);
+        } else {
+            enc.u8(ctrl::NUMBER_F8);
+            enc.f64(val);
+        }
+        enc.str(self.unit.as_ref().map_or("", HUnit::as_str));
+    }
+}
+
+/// Adds or subtracts, converting `rhs` into the unit of `lhs` when they differ
+fn sum<T: Float + Display>(
+    lhs: &HNumber<T>,
+    rhs: &HNumber<T>,
+    op: impl Fn(T, T) -> T,
+) -> Result<HNumber<T>, UnitErr> {
+    match (&lhs.unit, &rhs.unit) {
+        (Some(unit), Some(rhs_unit)) if unit != rhs_unit => {
+            let rhs = rhs.convert_to(unit)?;
+            Ok(HNumber::new(op(lhs.val, rhs.val), lhs.unit.clone()))
+        }
+        (unit, rhs_unit) => Ok(HNumber::new(
+            op(lhs.val, rhs.val),
+            unit.as_ref().or(rhs_unit.as_ref()).cloned(),
+        )),
+    }
+}
+
+/// Unit of a product or quotient, with the factor to scale the value by. Dimensionless
+/// units fold into the value, others derive the unit from their combined dimension.
+fn derive_unit<T: Float>(
+    lhs: &HUnit,
+    rhs: &HUnit,
+    is_div: bool,
+) -> Result<(T, Option<HUnit>), UnitErr> {
+    if is_div && lhs == rhs {
+        return Ok((T::one(), None));
+    }
+    let (lhs_def, rhs_def) = (lhs.validate()?, rhs.validate()?);
+    let no_unit = || {
+        let op = if is_div { "/" } else { "*" };
+        UnitErr::NoDerivedUnit(format!("{}{}{}", lhs, op, rhs))
+    };
+    let scalar = |def: &UnitDef| def.quantity() == "dimensionless";
+    let has_dim = |def: &UnitDef| !def.dim().is_dimensionless() && def.offset() == 0.0;
+
+    if scalar(rhs_def) {
+        let scale = match is_div {
+            true => 1.0 / rhs_def.scale(),
+            false => rhs_def.scale(),
+        };
+        return Ok((cast(scale), Some(lhs.clone())));
+    }
+    if scalar(lhs_def) && !is_div {
+        return Ok((cast(lhs_def.scale()), Some(rhs.clone())));
+    }
+    if !has_dim(lhs_def) || !has_dim(rhs_def) {
+        return Err(no_unit());
+    }
+
+    let (dim, scale) = match is_div {
+        true => (
+            lhs_def.dim() / rhs_def.dim(),
+            lhs_def.scale() / rhs_def.scale(),
+        ),
+        false => (
+            lhs_def.dim() * rhs_def.dim(),
+            lhs_def.scale() * rhs_def.scale(),
+        ),
+    };
+    if dim.is_dimensionless() {
+        return Ok((cast(scale), None));
+    }
+    // Apparent or reactive power stays apparent or reactive, ie. kVA*h is kVAh and not kWh
+    let qualifier = match (lhs_def.qualifier(), rhs_def.qualifier()) {
+        (Some(lhs), Some(rhs)) if lhs != rhs => return Err(no_unit()),
+        (lhs, rhs) => lhs.or(rhs),
+    };
+    match units::find_by_dim(dim, scale, qualifier) {
+        Some(def) => Ok((T::one(), Some(HUnit::new(def.symbol().to_owned())))),
+        None => units::find_by_dim(dim, 1.0, qualifier)
+            .map(|def| (cast(scale), Some(HUnit::new(def.symbol().to_owned()))))
+            .ok_or_else(no_unit),
+    }
+}
+
+fn product<T: Float + Display>(
+    lhs: &HNumber<T>,
+    rhs: &HNumber<T>,
+    is_div: bool,
+) -> Result<HNumber<T>, UnitErr> {
+    let val = match is_div {
+        true => lhs.val / rhs.val,
+        false => lhs.val * rhs.val,
+    };
+    match (&lhs.unit, &rhs.unit) {
+        (Some(unit), Some(rhs_unit)) => {
+            let (scale, unit) = derive_unit::<T>(unit, rhs_unit, is_div)?;
+            Ok(HNumber::new(val * scale, unit))
+        }
+        (Some(unit), None) => Ok(HNumber::new(val, Some(unit.clone()))),
+        (None, Some(rhs_unit)) if is_div => Err(UnitErr::NoDerivedUnit(format!("1/{}", rhs_unit))),
+        (unit, rhs_unit) => Ok(HNumber::new(
+            val,
+            unit.as_ref().or(rhs_unit.as_ref()).cloned(),
+        )),
+    }
+}
+
+/// Implements an operator for owned and borrowed numbers, failing on incompatible units
+macro_rules! impl_num_op {
+    ( $op:ident, $method:ident, |$lhs:ident, $rhs:ident| $body:expr ) => {
+        impl<T: Float + Display> $op for &HNumber<T> {
+            type Output = Result<HNumber<T>, UnitErr>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                let ($lhs, $rhs) = (self, rhs);
+                $body
+            }
+        }
+
+        impl<T: Float + Display> $op for HNumber<T> {
+            type Output = Result<HNumber<T>, UnitErr>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                (&self).$method(&rhs)
+            }
         }
+    };
+}
+
+impl_num_op!(Add, add, |lhs, rhs| sum(lhs, rhs, |a, b| a + b));
+impl_num_op!(Sub, sub, |lhs, rhs| sum(lhs, rhs, |a, b| a - b));
+impl_num_op!(Mul, mul, |lhs, rhs| product(lhs, rhs, false));
+impl_num_op!(Div, div, |lhs, rhs| product(lhs, rhs, true));
+
+impl<T: Float + Display> Neg for &HNumber<T> {
+    type Output = HNumber<T>;
+
+    fn neg(self) -> Self::Output {
+        HNumber::new(-self.val, self.unit.clone())
+    }
+}
+
+impl<T: Float + Display> Neg for HNumber<T> {
+    type Output = HNumber<T>;
+
+    fn neg(self) -> Self::Output {
+        HNumber::new(-self.val, self.unit)
     }
 }
 
@@ -71,6 +352,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HNumber<T> {
     }
 
     set_trait_eq_method!(get_number,'a,T);
+    set_trait_hash_method!();
 }
 
 impl Display for HUnit {
@@ -115,4 +397,128 @@ mod tests {
         let number = HNumber::new(42.0, None);
         assert_eq!(number.haystack_type(), HType::Number);
     }
+
+    #[test]
+    fn test_eq_and_hash() {
+        use std::collections::HashSet;
+
+        let nan: HNumber<f64> = HNumber::new(f64::NAN, None);
+        assert_eq!(nan, HNumber::new(f64::NAN, None));
+        assert_eq!(HNumber::new(0.0, None), HNumber::<f64>::new(-0.0, None));
+        assert_ne!(
+            HNumber::new(1.0, Some(HUnit::new("kW".into()))),
+            HNumber::<f64>::new(1.0, None)
+        );
+
+        let set: HashSet<HNumber<f64>> = [
+            nan.clone(),
+            nan,
+            HNumber::new(0.0, None),
+            HNumber::new(-0.0, None),
+            HNumber::new(1.0, Some(HUnit::new("kW".into()))),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 3);
+    }
+
+    fn num(val: f64, unit: &str) -> HNumber<f64> {
+        HNumber::new(val, Some(HUnit::new(unit.into())))
+    }
+
+    fn assert_num(res: Result<HNumber<f64>, UnitErr>, val: f64, unit: Option<&str>) {
+        let res = res.unwrap();
+        assert!((res.val() - val).abs() < 1e-9, "{} != {}", res.val(), val);
+        assert_eq!(res.unit().as_ref().map(HUnit::as_str), unit);
+    }
+
+    #[test]
+    fn test_add_sub() {
+        assert_num(num(2.0, "kW") + num(500.0, "W"), 2.5, Some("kW"));
+        assert_num(num(10.0, "°C") + num(50.0, "°F"), 20.0, Some("°C"));
+        assert_num(&num(3.0, "m") - &num(1.0, "m"), 2.0, Some("m"));
+        assert_num(num(3.0, "m") + HNumber::new(1.0, None), 4.0, Some("m"));
+        assert_num(HNumber::new(1.0, None) - num(3.0, "m"), -2.0, Some("m"));
+        assert_eq!(
+            num(1.0, "kW") + num(1.0, "m"),
+            Err(UnitErr::Incompatible("m".into(), "kW".into()))
+        );
+        assert_eq!(
+            num(1.0, "kWh") + num(1.0, "kVAh"),
+            Err(UnitErr::Incompatible("kVAh".into(), "kWh".into()))
+        );
+    }
+
+    #[test]
+    fn test_mul_div() {
+        assert_num(num(2.0, "kW") * num(3.0, "h"), 6.0, Some("kWh"));
+        assert_num(num(6.0, "kWh") / num(3.0, "h"), 2.0, Some("kW"));
+        assert_num(num(100.0, "m") / num(20.0, "s"), 5.0, Some("m/s"));
+        assert_num(num(2.0, "kW") / num(500.0, "W"), 4.0, None);
+        assert_num(num(6.0, "$") / num(3.0, "$"), 2.0, None);
+        assert_num(num(10.0, "kW") * num(50.0, "%"), 5.0, Some("kW"));
+        assert_num(num(2.0, "kW") * HNumber::new(3.0, None), 6.0, Some("kW"));
+        assert_num(num(2.0, "kW") * num(30.0, "min"), 3.6e6, Some("J"));
+        assert_num(num(2.0, "kVA") * num(3.0, "h"), 6.0, Some("kVAh"));
+        assert_num(num(2.0, "kvar") * num(3.0, "h"), 6.0, Some("kvarh"));
+        assert_num(num(6.0, "kVAh") / num(3.0, "h"), 2.0, Some("kVA"));
+        assert_num(num(2.0, "kVA") / num(4.0, "V"), 0.5, Some("kA"));
+        assert_eq!(
+            num(1.0, "kVA") * num(1.0, "kvar"),
+            Err(UnitErr::NoDerivedUnit("kVA*kvar".into()))
+        );
+        assert_eq!(
+            num(1.0, "kW") * num(1.0, "m"),
+            Err(UnitErr::NoDerivedUnit("kW*m".into()))
+        );
+        assert_eq!(
+            num(1.0, "°C") * num(1.0, "m"),
+            Err(UnitErr::NoDerivedUnit("°C*m".into()))
+        );
+        assert_eq!(
+            HNumber::new(1.0, None) / num(1.0, "s"),
+            Err(UnitErr::NoDerivedUnit("1/s".into()))
+        );
+    }
+
+    #[test]
+    fn test_neg() {
+        assert_eq!(-num(2.0, "kW"), num(-2.0, "kW"));
+        assert_eq!(-&HNumber::<f64>::new(2.0, None), HNumber::new(-2.0, None));
+    }
+
+    #[test]
+    fn test_unit_validate() {
+        assert_eq!("kWh".parse::<HUnit>(), Ok(HUnit::new("kWh".into())));
+        assert_eq!(
+            "kWhr".parse::<HUnit>(),
+            Err(UnitErr::Unknown("kWhr".into()))
+        );
+        assert_eq!(HUnit::new("°F".into()).def().unwrap().name(), "fahrenheit");
+    }
+
+    #[test]
+    fn test_convert_to() {
+        let f: HNumber<f64> = HNumber::new(72.0, Some(HUnit::new("°F".into())));
+        let c = f.convert_to(&HUnit::new("°C".into())).unwrap();
+        assert!((c.val() - 22.222222222222).abs() < 1e-9);
+        assert_eq!(c.unit(), &Some(HUnit::new("°C".into())));
+
+        let kw = HNumber::new(1.5f64, Some(HUnit::new("kW".into())));
+        let w = kw.convert_to(&HUnit::new("W".into())).unwrap();
+        assert!((w.val() - 1500.0).abs() < 1e-9);
+
+        assert_eq!(
+            kw.convert_to(&HUnit::new("m".into())),
+            Err(UnitErr::Incompatible("kW".into(), "m".into()))
+        );
+        assert_eq!(
+            kw.convert_to(&HUnit::new("foo".into())),
+            Err(UnitErr::Unknown("foo".into()))
+        );
+        assert_eq!(
+            HNumber::new(1.0f64, None).convert_to(&HUnit::new("W".into())),
+            Err(UnitErr::Unitless)
+        );
+    }
 }
@@ -1,8 +1,15 @@
-use crate::common::{escape_str_no_escape_unicode as escape_str, zinc_escape_str};
+use crate::common::zinc_escape_str;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl, ref_handle};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
+use std::cmp::Ordering;
 use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Clone, PartialEq)]
+/// A reference to an entity. Refs are equal, hash and order by id alone, the display name
+/// only describes the entity.
+#[derive(Debug, Clone)]
 pub struct HRef {
     pub id: String,
     pub dis: Option<String>,
@@ -31,15 +38,54 @@ impl HRef {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "r:{}", self.id)?;
-        match &self.dis {
-            Some(dis) => {
-                f.write_char(' ')?;
-                dis.chars().try_for_each(|c| escape_str(c, f))?;
-                Ok(())
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write_scalar(
+            f,
+            version,
+            "ref",
+            &[("val", Some(&self.id)), ("dis", self.dis.as_deref())],
+        )
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        match ref_handle(&self.id) {
+            Some(handle) => {
+                enc.u8(ctrl::REF_I8);
+                enc.i64(handle);
+            }
+            None => {
+                enc.u8(ctrl::REF_STR);
+                enc.chars(&self.id);
             }
-            None => Ok(()),
         }
+        enc.chars(self.dis.as_deref().unwrap_or(""));
+    }
+}
+
+impl PartialEq for HRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for HRef {}
+
+impl Hash for HRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PartialOrd for HRef {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HRef {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
     }
 }
 
@@ -49,6 +95,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HRef {
     }
 
     set_trait_eq_method!(get_ref,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -72,4 +119,20 @@ mod tests {
         let href_hval = HVal::<f64>::as_hval(&href);
         assert_eq!(href_hval.haystack_type(), HType::Ref);
     }
+
+    #[test]
+    fn test_eq_by_id() {
+        use std::collections::HashSet;
+        let plain = HRef::new("site".into(), None);
+        let named = HRef::new("site".into(), Some("Main Site".into()));
+        assert_eq!(plain, named);
+        assert_ne!(plain, HRef::new("other".into(), Some("Main Site".into())));
+        assert_eq!(plain.cmp(&named), Ordering::Equal);
+        let set: HashSet<_> = [plain, named].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        let a: &dyn HVal<f64> = &HRef::new("site".into(), None);
+        let b: &dyn HVal<f64> = &HRef::new("site".into(), Some("Main Site".into()));
+        assert!(a == b);
+    }
 }
@@ -1,8 +1,11 @@
 use crate::common::zinc_escape_str;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_str;
 use crate::{HType, HVal, NumTrait};
 use std::fmt::{self, Write};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct HStr(pub String);
 
 pub type Str = HStr;
@@ -46,11 +49,20 @@ impl HStr {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(_) = self.0.find(":") {
-            write!(f, "s:")?;
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        // Haystack 3 reserves a colon in the second position for type prefixes
+        match version {
+            JsonVersion::V3 if self.0.as_bytes().get(1) == Some(&b':') => {
+                write_str(f, &format!("s:{}", self.0))
+            }
+            _ => write_str(f, &self.0),
         }
-        write!(f, "{}", self.0)?;
-        Ok(())
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::STR);
+        enc.str(&self.0);
     }
 }
 
@@ -60,6 +72,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HStr {
     }
 
     set_trait_eq_method!(get_string,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
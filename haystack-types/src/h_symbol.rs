@@ -1,7 +1,10 @@
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use std::fmt;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct HSymbol {
     val: String,
 }
@@ -14,6 +17,9 @@ impl HSymbol {
     pub fn new(val: String) -> HSymbol {
         HSymbol { val }
     }
+    pub fn as_str(&self) -> &str {
+        &self.val
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "^{}", self.val)
     }
@@ -21,7 +27,14 @@ impl HSymbol {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "y:{}", self.val)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write_scalar(f, version, "symbol", &[("val", Some(&self.val))])
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::SYMBOL);
+        enc.str(&self.val);
     }
 }
 
@@ -31,6 +44,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HSymbol {
     }
 
     set_trait_eq_method!(get_symbol, 'a, T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
@@ -1,10 +1,14 @@
+use crate::io::JsonVersion;
+use crate::io::write::ZincWriter;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use std::fmt::{self, Display};
 
 use chrono::Timelike;
 use chrono::naive::NaiveTime;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct HTime {
     inner: NaiveTime,
 }
@@ -35,6 +39,9 @@ impl HTime {
                 .ok_or(HTimeErr::InvalidDate)?,
         })
     }
+    pub fn val(&self) -> NaiveTime {
+        self.inner
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -52,8 +59,17 @@ impl HTime {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "h:")?;
-        self.to_zinc(f)
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        let val = ZincWriter::new(self).to_string();
+        write_scalar(f, version, "time", &[("val", Some(&val))])
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::TIME);
+        let millis =
+            self.inner.num_seconds_from_midnight() * 1000 + self.inner.nanosecond() / 1_000_000;
+        enc.i32(millis as i32);
     }
 }
 
@@ -63,6 +79,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HTime {
     }
 
     set_trait_eq_method!(get_time,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
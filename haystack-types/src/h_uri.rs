@@ -1,9 +1,12 @@
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use std::fmt::{self, Write};
 
 use url::{ParseError as UrlParseError, Url};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct HUri(Url);
 
 pub type Uri = HUri;
@@ -25,8 +28,14 @@ impl HUri {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "u:{}", self.0)?;
-        Ok(())
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write_scalar(f, version, "uri", &[("val", Some(self.0.as_str()))])
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::URI);
+        enc.str(self.0.as_str());
     }
     pub fn to_owned_string(&self) -> String {
         self.0.to_string()
@@ -39,6 +48,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HUri {
     }
 
     set_trait_eq_method!(get_uri,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
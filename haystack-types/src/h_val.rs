@@ -1,15 +1,19 @@
 use crate::common::ZincReader;
 use crate::io::write::ZincWriter;
+use crate::io::write::brio::BrioWritable;
+use crate::io::write::hayson::HaysonWritable;
 use crate::io::write::json::JsonWritable;
 use crate::io::write::trio::TrioWritable;
 use crate::io::write::zinc::ZincWritable;
 use crate::{HCast, NumTrait, io};
+use std::cmp::Ordering;
 use std::fmt::{self, Debug, Display};
-use std::rc::Rc;
+use std::hash::{Hash, Hasher};
 
 use nom::IResult;
 
-#[derive(Debug, PartialEq)]
+/// Kinds in Haystack sort order, values of different kinds order by kind
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum HType {
     Null,
     Marker,
@@ -31,7 +35,27 @@ pub enum HType {
     Grid,
 }
 
-pub type HBox<'a, T> = Rc<dyn HVal<'a, T> + 'a>;
+pub type HBox<'a, T> = Ptr<dyn HVal<'a, T> + 'a>;
+
+/// Shared pointer behind values and grid rows, an `Arc` with the `sync` feature
+#[cfg(not(feature = "sync"))]
+pub type Ptr<T> = std::rc::Rc<T>;
+#[cfg(not(feature = "sync"))]
+pub type WeakPtr<T> = std::rc::Weak<T>;
+#[cfg(feature = "sync")]
+pub type Ptr<T> = std::sync::Arc<T>;
+#[cfg(feature = "sync")]
+pub type WeakPtr<T> = std::sync::Weak<T>;
+
+/// `Send + Sync` with the `sync` feature so values can move between threads, otherwise no bound
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
 
 impl Display for HType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -50,8 +74,22 @@ macro_rules! set_trait_eq_method {
     };
 }
 
+macro_rules! set_trait_hash_method {
+    () => {
+        fn _hash(&self, mut state: &mut dyn std::hash::Hasher) {
+            std::hash::Hash::hash(self, &mut state)
+        }
+    };
+}
+
 pub trait HVal<'a, T: NumTrait + 'a>:
-    HCast<'a, T> + ZincWritable + TrioWritable + JsonWritable
+    HCast<'a, T>
+    + ZincWritable
+    + TrioWritable
+    + JsonWritable
+    + HaysonWritable
+    + BrioWritable
+    + MaybeSync
 {
     fn haystack_type(&self) -> HType;
 
@@ -66,7 +104,7 @@ pub trait HVal<'a, T: NumTrait + 'a>:
     where
         Self: Sized + 'static,
     {
-        Rc::new(self)
+        Ptr::new(self)
     }
 
     fn to_owned(&self) -> Self
@@ -77,6 +115,8 @@ pub trait HVal<'a, T: NumTrait + 'a>:
     }
 
     fn _eq(&self, other: &dyn HVal<'a, T>) -> bool;
+
+    fn _hash(&self, state: &mut dyn Hasher);
 }
 
 impl<'a, T: NumTrait + 'a> Display for dyn HVal<'a, T> {
@@ -91,9 +131,8 @@ impl<'a, T: NumTrait + 'a> Debug for dyn HVal<'a, T> {
     }
 }
 
-impl<'a, T: NumTrait + 'a> PartialEq for dyn HVal<'a, T> {
-    fn eq(&self, other: &dyn HVal<'a, T>) -> bool {
-        // TODO: Implement equality testing for HVal
+impl<'a, T: NumTrait + 'a> PartialEq for dyn HVal<'a, T> + 'a {
+    fn eq(&self, other: &(dyn HVal<'a, T> + 'a)) -> bool {
         if self.haystack_type() == other.haystack_type() {
             return self._eq(other);
         };
@@ -101,6 +140,44 @@ impl<'a, T: NumTrait + 'a> PartialEq for dyn HVal<'a, T> {
     }
 }
 
+impl<'a, T: NumTrait + 'a> Eq for dyn HVal<'a, T> + 'a {}
+
+/// Haystack sort order, null first then by kind and the natural order within a kind. Numbers in
+/// different units, dicts and grids only compare when equal
+impl<'a, T: NumTrait + 'a> PartialOrd for dyn HVal<'a, T> + 'a {
+    fn partial_cmp(&self, other: &(dyn HVal<'a, T> + 'a)) -> Option<Ordering> {
+        let kind = self.haystack_type();
+        if kind != other.haystack_type() {
+            return kind.partial_cmp(&other.haystack_type());
+        }
+
+        match kind {
+            HType::Null | HType::Marker | HType::Remove | HType::NA => Some(Ordering::Equal),
+            HType::Bool => self.get_bool()?.partial_cmp(other.get_bool()?),
+            HType::Number => self.get_number()?.partial_cmp(other.get_number()?),
+            HType::Str => self.get_string()?.partial_cmp(other.get_string()?),
+            HType::Uri => self.get_uri()?.partial_cmp(other.get_uri()?),
+            HType::Ref => self.get_ref()?.partial_cmp(other.get_ref()?),
+            HType::Symbol => self.get_symbol()?.partial_cmp(other.get_symbol()?),
+            HType::Date => self.get_date()?.partial_cmp(other.get_date()?),
+            HType::Time => self.get_time()?.partial_cmp(other.get_time()?),
+            HType::DateTime => self.get_datetime()?.partial_cmp(other.get_datetime()?),
+            HType::Coord => self.get_coord()?.partial_cmp(other.get_coord()?),
+            HType::XStr => self.get_xstr()?.partial_cmp(other.get_xstr()?),
+            HType::List => self.get_list()?.partial_cmp(other.get_list()?),
+            HType::Dict => self.get_dict()?.partial_cmp(other.get_dict()?),
+            HType::Grid => self.get_grid()?.partial_cmp(other.get_grid()?),
+        }
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Hash for dyn HVal<'a, T> + 'a {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.haystack_type().hash(state);
+        self._hash(state);
+    }
+}
+
 impl<'a, T: NumTrait + 'a> ZincReader<'a, T> for dyn HVal<'a, T> {
     fn parse<'b>(buf: &'b str) -> IResult<&'b str, HBox<'a, T>>
     where
@@ -110,3 +187,48 @@ impl<'a, T: NumTrait + 'a> ZincReader<'a, T> for dyn HVal<'a, T> {
         io::parse::zinc::literal::<T>(&mut dt_cell)(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_number::HNumber;
+    use crate::io::ParseHint;
+    use crate::io::parse::zinc::{literal, parse_list};
+    use std::fmt::Write;
+
+    fn val(zinc: &str) -> HBox<'static, f64> {
+        literal::<f64>(&mut ParseHint::default())(zinc).unwrap().1
+    }
+
+    #[test]
+    fn sort_mixed_kinds() {
+        let list = parse_list::<f64>("[\"b\", 2, N, 2024-01-01, M, T, 1, \"a\", @x, F]").unwrap();
+        let mut vals: Vec<HBox<f64>> = list.iter().cloned().collect();
+        vals.sort_by(|a, b| a.as_ref().partial_cmp(b.as_ref()).unwrap());
+
+        let mut buf = String::new();
+        for v in vals.iter() {
+            write!(buf, "{} ", ZincWriter::new(v.as_ref())).unwrap();
+        }
+        assert_eq!(buf, "N M F T 1 2 \"a\" \"b\" @x 2024-01-01 ");
+    }
+
+    #[test]
+    fn cmp_within_kind() {
+        assert!(*val("1kW") < *val("2kW"));
+        assert_eq!(val("1kW").as_ref().partial_cmp(val("1°C").as_ref()), None);
+        let nan: HBox<f64> = Ptr::new(HNumber::new(f64::NAN, None));
+        let inf: HBox<f64> = Ptr::new(HNumber::new(f64::INFINITY, None));
+        assert!(*nan > *inf);
+
+        let sydney = val("2024-01-01T10:00:00+11:00 Sydney");
+        let utc = val("2024-01-01T00:00:00Z UTC");
+        assert!(*sydney < *utc);
+        assert!(sydney.get_datetime().unwrap() < utc.get_datetime().unwrap());
+
+        assert!(*val("[1, 2]") < *val("[1, 3]"));
+        assert!(*val("[1]") < *val("[1, 0]"));
+        assert_eq!(val("{a}").as_ref().partial_cmp(val("{b}").as_ref()), None);
+        assert!(val("{a}").as_ref() <= val("{a}").as_ref());
+    }
+}
@@ -1,9 +1,11 @@
-use crate::common::escape_str_no_escape_unicode;
 use crate::h_str::HStr;
+use crate::io::JsonVersion;
+use crate::io::write::brio::{BrioEncoder, ctrl};
+use crate::io::write::hayson::write_scalar;
 use crate::{HType, HVal, NumTrait};
 use std::fmt;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct HXStr {
     xtype: String,
     xval: HStr,
@@ -20,6 +22,12 @@ impl HXStr {
             xval: HStr::new(xval),
         }
     }
+    pub fn xtype(&self) -> &str {
+        &self.xtype
+    }
+    pub fn xval(&self) -> &str {
+        self.xval.as_str()
+    }
     pub fn to_zinc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}(", self.xtype)?;
         self.xval.to_zinc(f)?;
@@ -29,10 +37,23 @@ impl HXStr {
         self.to_zinc(f)
     }
     pub fn to_json(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "x:{}:", self.xtype)?;
-        self.xval
-            .chars()
-            .try_for_each(|c| escape_str_no_escape_unicode(c, f))
+        self.to_hayson(f, JsonVersion::V3)
+    }
+    pub fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+        write_scalar(
+            f,
+            version,
+            "xstr",
+            &[
+                ("type", Some(&self.xtype)),
+                ("val", Some(self.xval.as_str())),
+            ],
+        )
+    }
+    pub fn to_brio(&self, enc: &mut BrioEncoder) {
+        enc.u8(ctrl::XSTR);
+        enc.str(&self.xtype);
+        enc.str(self.xval.as_str());
     }
 }
 
@@ -49,6 +70,7 @@ impl<'a, T: NumTrait + 'a> HVal<'a, T> for HXStr {
     }
 
     set_trait_eq_method!(get_xstr,'a,T);
+    set_trait_hash_method!();
 }
 
 #[cfg(test)]
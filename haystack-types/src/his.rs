@@ -0,0 +1,784 @@
+//! Typed history series read from and written to `hisRead`/`hisWrite` grids
+
+use crate::NumTrait;
+use crate::date_span::start_of_day;
+use crate::err::UnitErr;
+use crate::h_datetime::HDateTime;
+use crate::h_dict::HDict;
+use crate::h_grid::{GridBuilder, HGrid, HGridErr};
+use crate::h_number::{HNumber, HUnit, cast};
+use crate::h_ref::HRef;
+use crate::h_str::HStr;
+use crate::h_val::{HBox, Ptr};
+use chrono::{Datelike, Days, Months, TimeDelta, Utc};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HisErr {
+    UnitErr(UnitErr),
+    InvalidInterval(TimeDelta),
+}
+
+impl Display for HisErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HisErr::UnitErr(e) => e.fmt(f),
+            HisErr::InvalidInterval(len) => {
+                write!(f, "Error: Interval length must be positive, found {}", len)
+            }
+        }
+    }
+}
+
+impl Error for HisErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HisErr::UnitErr(e) => Some(e),
+            HisErr::InvalidInterval(_) => None,
+        }
+    }
+}
+
+impl From<UnitErr> for HisErr {
+    fn from(e: UnitErr) -> Self {
+        HisErr::UnitErr(e)
+    }
+}
+
+/// How the values in each bucket combine into one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rollup {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    /// The number of values, without a unit
+    Count,
+    First,
+    Last,
+}
+
+/// Bucket sizes, aligned to the local clock and calendar of the series' timezone
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    /// Buckets of a fixed length counted from local midnight, ie. 15 minutes start on the
+    /// quarter hour. Lengths should divide a day evenly, and must be at least a millisecond.
+    Fixed(TimeDelta),
+    Day,
+    /// Sunday to Saturday, like `DateSpan::week`
+    Week,
+    Month,
+    Year,
+}
+
+impl Interval {
+    fn check(&self) -> Result<(), HisErr> {
+        match self {
+            Interval::Fixed(len) if len.num_milliseconds() <= 0 => {
+                Err(HisErr::InvalidInterval(*len))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Start of the bucket holding `ts`, in `tz`
+    fn bucket(&self, ts: &HDateTime, tz: Tz) -> HDateTime {
+        let local = ts.to_tz(tz);
+        let date = local.val().date();
+        let date = match self {
+            Interval::Fixed(len) => {
+                let step = len.num_milliseconds();
+                let offset = local.offset().local_minus_utc() as i64 * 1000;
+                let local_ms = local.to_chrono().timestamp_millis() + offset;
+                let start = local_ms - local_ms.rem_euclid(step) - offset;
+                return chrono::DateTime::<Utc>::from_timestamp_millis(start)
+                    .map(|dt| HDateTime::from(dt.with_timezone(&tz)))
+                    .unwrap_or(local);
+            }
+            Interval::Day => date,
+            Interval::Week => date - Days::new(date.weekday().num_days_from_sunday() as u64),
+            Interval::Month => date.with_day(1).unwrap_or(date),
+            Interval::Year => date.with_ordinal(1).unwrap_or(date),
+        };
+        start_of_day(date, tz)
+    }
+
+    /// Start of the bucket after the one starting at `start`
+    fn next(&self, start: &HDateTime, tz: Tz) -> HDateTime {
+        let date = start.to_tz(tz).val().date();
+        let date = match self {
+            Interval::Fixed(len) => {
+                let step = TimeDelta::milliseconds(len.num_milliseconds());
+                return HDateTime::from((start.to_chrono() + step).with_timezone(&tz));
+            }
+            Interval::Day => date + Days::new(1),
+            Interval::Week => date + Days::new(7),
+            Interval::Month => date + Months::new(1),
+            Interval::Year => date + Months::new(12),
+        };
+        start_of_day(date, tz)
+    }
+}
+
+/// How an aligned series fills timestamps it has no value at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fill {
+    /// The last value before, for change of value points
+    Previous,
+    /// Interpolated between the values either side, for analog points
+    Linear,
+    Null,
+    Zero,
+}
+
+/// The timestamps series are aligned on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// Every timestamp of any of the series
+    Union,
+    /// The start of each interval from the first timestamp to the last
+    Every(Interval),
+}
+
+/// Series sampled on one time axis, with a value or an empty cell per series at each timestamp
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlignedHis<T: NumTrait> {
+    ts: Vec<HDateTime>,
+    tz: Tz,
+    cols: Vec<AlignedCol<T>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct AlignedCol<T: NumTrait> {
+    id: Option<HRef>,
+    unit: Option<HUnit>,
+    vals: Vec<Option<HNumber<T>>>,
+}
+
+impl<T: NumTrait> AlignedHis<T> {
+    pub fn ts(&self) -> &[HDateTime] {
+        &self.ts
+    }
+
+    pub fn len(&self) -> usize {
+        self.ts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ts.is_empty()
+    }
+
+    /// The number of series
+    pub fn width(&self) -> usize {
+        self.cols.len()
+    }
+
+    pub fn id(&self, series: usize) -> Option<&HRef> {
+        self.cols.get(series)?.id.as_ref()
+    }
+
+    pub fn unit(&self, series: usize) -> Option<&HUnit> {
+        self.cols.get(series)?.unit.as_ref()
+    }
+
+    /// The values of a series, one per timestamp
+    pub fn vals(&self, series: usize) -> Option<&[Option<HNumber<T>>]> {
+        self.cols.get(series).map(|col| col.vals.as_slice())
+    }
+
+    /// The values of every series at a timestamp
+    pub fn row(&self, idx: usize) -> Option<Vec<Option<&HNumber<T>>>> {
+        self.ts.get(idx)?;
+        Some(self.cols.iter().map(|col| col.vals[idx].as_ref()).collect())
+    }
+
+    /// A batch grid with `ts` and a `v0..vn` column per series, like `HisSeries::to_batch_grid`
+    pub fn to_grid<'a>(&self) -> HGrid<'a, T>
+    where
+        T: 'a,
+    {
+        let mut ts_meta = HDict::new();
+        ts_meta.set(
+            "tz".to_owned(),
+            Ptr::new(HStr(crate::tz::name(self.tz).to_owned())),
+        );
+        let mut builder = GridBuilder::new()
+            .add_col("ts", ts_meta)
+            .expect("ts is a valid column name");
+        for (n, AlignedCol { id, unit, .. }) in self.cols.iter().enumerate() {
+            let mut meta = HDict::new();
+            if let Some(id) = id {
+                meta.set("id".to_owned(), Ptr::new(id.clone()));
+            }
+            if let Some(unit) = unit {
+                meta.set("unit".to_owned(), Ptr::new(HStr(unit.to_string())));
+            }
+            builder = builder
+                .add_col(&format!("v{}", n), meta)
+                .expect("v0..vn are valid column names");
+        }
+        for (idx, ts) in self.ts.iter().enumerate() {
+            let row = std::iter::once(Some(Ptr::new(ts.clone()) as HBox<'a, T>)).chain(
+                self.cols.iter().map(|col| {
+                    col.vals[idx]
+                        .clone()
+                        .map(|val| Ptr::new(val) as HBox<'a, T>)
+                }),
+            );
+            builder = builder.add_row(row).expect("rows match the columns");
+        }
+        builder.build()
+    }
+}
+
+/// The history of one point, with its timestamped values in time order
+#[derive(Clone, Debug, PartialEq)]
+pub struct HisSeries<T: NumTrait> {
+    id: Option<HRef>,
+    unit: Option<HUnit>,
+    tz: Tz,
+    items: Vec<(HDateTime, HNumber<T>)>,
+}
+
+/// `val`, or `v0..vn` in batch reads
+fn is_val_col(name: &str) -> bool {
+    name == "val"
+        || name
+            .strip_prefix('v')
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// A tag from the column meta, falling back to the grid meta
+fn meta_tag<'a, T: NumTrait + 'a>(
+    col: &HDict<'a, T>,
+    grid: &HDict<'a, T>,
+    name: &str,
+) -> Option<HBox<'a, T>> {
+    col.get(name).or_else(|| grid.get(name)).cloned()
+}
+
+impl<T: NumTrait> HisSeries<T> {
+    pub fn new(id: Option<HRef>, unit: Option<HUnit>, tz: Tz) -> Self {
+        Self {
+            id,
+            unit,
+            tz,
+            items: Vec::new(),
+        }
+    }
+
+    /// Reads the `val` column of a `hisRead` grid, or each `v0..vn` column of a batch read. The
+    /// `id`, `unit` and `tz` of each series come from the column meta, then the grid meta, then
+    /// the first value. Empty cells and values that are not numbers are skipped.
+    pub fn from_grid<'a>(grid: &HGrid<'a, T>) -> Result<Vec<Self>, HGridErr>
+    where
+        T: 'a,
+    {
+        if let HGrid::Empty { .. } = grid {
+            return Ok(vec![]);
+        }
+        if !grid.has("ts") {
+            return Err(HGridErr::UnknownCol("ts".to_owned()));
+        }
+        let grid_meta = grid.meta();
+        let ts_meta = grid
+            .iter_cols()
+            .find(|col| col.name() == "ts")
+            .map(|col| col.meta());
+        let ts_tz = ts_meta.as_ref().and_then(|meta| {
+            meta.get("tz")?
+                .get_string()
+                .and_then(|tz| crate::tz::find(&tz.0))
+        });
+
+        let mut series = Vec::new();
+        for col in grid.iter_cols().filter(|col| is_val_col(col.name())) {
+            let meta = col.meta();
+            let mut items: Vec<_> = grid
+                .iter()
+                .filter_map(|row| {
+                    let ts = row.get("ts")?.get_datetime()?.clone();
+                    let val = row.get(col.name())?.get_number()?.clone();
+                    Some((ts, val))
+                })
+                .collect();
+            items.sort_by(|a, b| a.0.cmp(&b.0));
+
+            let id = meta_tag(&meta, &grid_meta, "id").and_then(|id| id.get_ref().cloned());
+            let unit = meta_tag(&meta, &grid_meta, "unit")
+                .and_then(|unit| Some(HUnit::new(unit.get_string()?.0.clone())))
+                .or_else(|| items.iter().find_map(|(_, val)| val.unit().clone()));
+            let tz = meta_tag(&meta, &grid_meta, "tz")
+                .and_then(|tz| crate::tz::find(&tz.get_string()?.0))
+                .or(ts_tz)
+                .or_else(|| items.first().map(|(ts, _)| ts.tz_id()))
+                .unwrap_or(Tz::UTC);
+
+            series.push(Self {
+                id,
+                unit,
+                tz,
+                items,
+            });
+        }
+        Ok(series)
+    }
+
+    pub fn id(&self) -> Option<&HRef> {
+        self.id.as_ref()
+    }
+
+    pub fn unit(&self) -> Option<&HUnit> {
+        self.unit.as_ref()
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.tz
+    }
+
+    pub fn items(&self) -> &[(HDateTime, HNumber<T>)] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Adds a value, keeping the series in time order
+    pub fn push(&mut self, ts: HDateTime, val: HNumber<T>) {
+        let idx = self.items.partition_point(|(other, _)| *other <= ts);
+        self.items.insert(idx, (ts, val));
+    }
+
+    /// A value in the series' unit, converting values in other units
+    fn val_in_unit(&self, val: &HNumber<T>) -> Result<T, UnitErr> {
+        match (&self.unit, val.unit()) {
+            (Some(unit), Some(val_unit)) if unit != val_unit => Ok(val.convert_to(unit)?.val()),
+            _ => Ok(val.val()),
+        }
+    }
+
+    /// Combines the values in each bucket into one, timestamped with the start of the bucket.
+    /// Buckets without values are left out.
+    pub fn rollup(&self, interval: Interval, rollup: Rollup) -> Result<Self, HisErr> {
+        interval.check()?;
+        let mut buckets: BTreeMap<_, (HDateTime, Vec<T>)> = BTreeMap::new();
+        for (ts, val) in self.items.iter() {
+            let start = interval.bucket(ts, self.tz);
+            let val = self.val_in_unit(val)?;
+            buckets
+                .entry(start.to_chrono().with_timezone(&Utc))
+                .or_insert_with(|| (start, Vec::new()))
+                .1
+                .push(val);
+        }
+
+        let unit = match rollup {
+            Rollup::Count => None,
+            _ => self.unit.clone(),
+        };
+        let items = buckets
+            .into_values()
+            .map(|(start, vals)| {
+                let count = cast(vals.len());
+                let sum = || vals.iter().fold(T::zero(), |acc, val| acc + *val);
+                let val = match rollup {
+                    Rollup::Sum => sum(),
+                    Rollup::Avg => sum() / count,
+                    Rollup::Min => vals.iter().fold(T::infinity(), |acc, val| acc.min(*val)),
+                    Rollup::Max => vals
+                        .iter()
+                        .fold(T::neg_infinity(), |acc, val| acc.max(*val)),
+                    Rollup::Count => count,
+                    Rollup::First => vals[0],
+                    Rollup::Last => vals[vals.len() - 1],
+                };
+                (start, HNumber::new(val, unit.clone()))
+            })
+            .collect();
+
+        Ok(Self {
+            id: self.id.clone(),
+            unit,
+            tz: self.tz,
+            items,
+        })
+    }
+
+    /// Aligns the series onto one time axis in the timezone of the first, filling the
+    /// timestamps each series has no value at as given
+    pub fn align(series: &[(&Self, Fill)], axis: Axis) -> Result<AlignedHis<T>, HisErr> {
+        if let Axis::Every(interval) = axis {
+            interval.check()?;
+        }
+        let tz = series.first().map_or(Tz::UTC, |(series, _)| series.tz);
+        let mut instants = BTreeMap::new();
+        for (series, _) in series {
+            for (ts, _) in series.items.iter() {
+                instants
+                    .entry(ts.to_chrono().with_timezone(&Utc))
+                    .or_insert_with(|| ts.to_tz(tz));
+            }
+        }
+
+        let ts = match (axis, instants.first_key_value(), instants.last_key_value()) {
+            (Axis::Every(interval), Some((_, first)), Some((last, _))) => {
+                let mut ts = vec![interval.bucket(first, tz)];
+                loop {
+                    let next = interval.next(&ts[ts.len() - 1], tz);
+                    if next.to_chrono() > *last {
+                        break;
+                    }
+                    ts.push(next);
+                }
+                ts
+            }
+            _ => instants.into_values().collect(),
+        };
+
+        let cols = series
+            .iter()
+            .map(|(series, fill)| {
+                let vals = series.sample(&ts, *fill)?;
+                Ok(AlignedCol {
+                    id: series.id.clone(),
+                    unit: series.unit.clone(),
+                    vals,
+                })
+            })
+            .collect::<Result<_, UnitErr>>()?;
+        Ok(AlignedHis { ts, tz, cols })
+    }
+
+    /// The value at each timestamp of `axis`, filled where the series has none
+    fn sample(&self, axis: &[HDateTime], fill: Fill) -> Result<Vec<Option<HNumber<T>>>, UnitErr> {
+        let items = self
+            .items
+            .iter()
+            .map(|(ts, val)| Ok((ts.to_chrono(), self.val_in_unit(val)?)))
+            .collect::<Result<Vec<_>, UnitErr>>()?;
+        let float = |delta: TimeDelta| cast::<_, T>(delta.num_milliseconds());
+
+        let vals = axis
+            .iter()
+            .map(|ts| {
+                let ts = ts.to_chrono();
+                let idx = items.partition_point(|(other, _)| *other <= ts);
+                let prev = idx.checked_sub(1).map(|idx| &items[idx]);
+                let val = match (prev, fill) {
+                    (Some((prev_ts, val)), _) if *prev_ts == ts => Some(*val),
+                    (prev, Fill::Previous) => prev.map(|(_, val)| *val),
+                    (Some((prev_ts, prev)), Fill::Linear) => {
+                        items.get(idx).map(|(next_ts, next)| {
+                            let frac = float(ts - *prev_ts) / float(*next_ts - *prev_ts);
+                            *prev + (*next - *prev) * frac
+                        })
+                    }
+                    (None, Fill::Linear) | (_, Fill::Null) => None,
+                    (_, Fill::Zero) => Some(T::zero()),
+                };
+                val.map(|val| HNumber::new(val, self.unit.clone()))
+            })
+            .collect();
+        Ok(vals)
+    }
+
+    /// Gaps between consecutive values further apart than the expected sample `rate`, as the
+    /// timestamps either side
+    pub fn gaps(&self, rate: TimeDelta) -> Vec<(HDateTime, HDateTime)> {
+        match (self.items.first(), self.items.last()) {
+            (Some((first, _)), Some((last, _))) => self.gaps_within(first, last, rate),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Like `gaps`, also counting from `start` to the first value and from the last value to
+    /// the exclusive `end`
+    pub fn gaps_within(
+        &self,
+        start: &HDateTime,
+        end: &HDateTime,
+        rate: TimeDelta,
+    ) -> Vec<(HDateTime, HDateTime)> {
+        let inside = self
+            .items
+            .iter()
+            .map(|(ts, _)| ts)
+            .filter(|ts| *ts >= start && *ts < end);
+        let points: Vec<_> = std::iter::once(start)
+            .chain(inside)
+            .chain(std::iter::once(end))
+            .collect();
+        points
+            .windows(2)
+            .filter(|pair| pair[1].to_chrono() - pair[0].to_chrono() > rate)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect()
+    }
+
+    /// A `ts`/`val` grid for `hisWrite`, with the `id` in the grid meta
+    pub fn to_grid<'a>(&self) -> HGrid<'a, T>
+    where
+        T: 'a,
+    {
+        let mut ts_meta = HDict::new();
+        ts_meta.set(
+            "tz".to_owned(),
+            Ptr::new(HStr(crate::tz::name(self.tz).to_owned())),
+        );
+        let mut val_meta = HDict::new();
+        if let Some(unit) = &self.unit {
+            val_meta.set("unit".to_owned(), Ptr::new(HStr(unit.to_string())));
+        }
+
+        let mut builder = GridBuilder::new()
+            .add_col("ts", ts_meta)
+            .and_then(|builder| builder.add_col("val", val_meta))
+            .expect("ts and val are valid column names");
+        if let Some(id) = &self.id {
+            builder = builder.set_meta("id", id.clone());
+        }
+        for (ts, val) in self.items.iter() {
+            let row: [HBox<'a, T>; 2] = [Ptr::new(ts.clone()), Ptr::new(val.clone())];
+            builder = builder.add_row(row).expect("rows match the columns");
+        }
+        builder.build()
+    }
+
+    /// A batch `hisWrite` grid with a `v0..vn` column per series, see `HGrid::his_merge`
+    pub fn to_batch_grid<'a>(series: &[Self]) -> HGrid<'a, T>
+    where
+        T: 'a,
+    {
+        let grids: Vec<_> = series.iter().map(Self::to_grid).collect();
+        HGrid::his_merge(&grids.iter().collect::<Vec<_>>()).expect("series grids have a ts column")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn ts(day: u32, hour: u32, min: u32) -> HDateTime {
+        HDateTime::from(
+            Tz::Australia__Sydney
+                .with_ymd_and_hms(2025, 1, day, hour, min, 0)
+                .unwrap(),
+        )
+    }
+
+    fn kw(val: f64) -> HNumber<f64> {
+        HNumber::new(val, Some(HUnit::new("kW".into())))
+    }
+
+    fn series() -> HisSeries<f64> {
+        let grid: HGrid<f64> = grid!(
+            { id: HRef::new("p1".into(), None) },
+            [ts, val],
+            [ts(1, 0, 10), kw(1.0)],
+            [ts(1, 0, 5), kw(2.0)],
+            [ts(1, 0, 20), HNumber::new(3000.0, Some(HUnit::new("W".into())))],
+            [ts(1, 1, 0), kw(4.0)],
+            [ts(2, 9, 0), None::<HBox<f64>>],
+            [ts(3, 9, 0), kw(5.0)],
+        );
+        HisSeries::from_grid(&grid).unwrap().remove(0)
+    }
+
+    fn vals(series: &HisSeries<f64>) -> Vec<f64> {
+        series.items().iter().map(|(_, val)| val.val()).collect()
+    }
+
+    #[test]
+    fn read_his_grid() {
+        let series = series();
+        assert_eq!(series.id(), Some(&HRef::new("p1".into(), None)));
+        assert_eq!(series.unit(), Some(&HUnit::new("kW".into())));
+        assert_eq!(series.tz(), Tz::Australia__Sydney);
+        assert_eq!(vals(&series), [2.0, 1.0, 3000.0, 4.0, 5.0]);
+
+        let no_ts: HGrid<f64> = grid!([val], [1.0]);
+        assert!(matches!(
+            HisSeries::from_grid(&no_ts),
+            Err(HGridErr::UnknownCol(_))
+        ));
+    }
+
+    #[test]
+    fn read_batch_grid() {
+        let grid: HGrid<f64> = grid!([ts, v0, v1], [ts(1, 0, 0), 1.0, 2.0])
+            .add_col_meta(
+                "v1",
+                HashMap::from([
+                    (
+                        "id".to_owned(),
+                        Ptr::new(HRef::new("p2".into(), None)) as HBox<f64>,
+                    ),
+                    ("tz".to_owned(), Ptr::new(HStr("UTC".into()))),
+                ]),
+            )
+            .unwrap();
+        let series = HisSeries::from_grid(&grid).unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].id(), None);
+        assert_eq!(series[0].tz(), Tz::Australia__Sydney);
+        assert_eq!(series[1].id(), Some(&HRef::new("p2".into(), None)));
+        assert_eq!(series[1].tz(), Tz::UTC);
+    }
+
+    #[test]
+    fn rollup_fixed_intervals() {
+        let series = series();
+        let hourly = series
+            .rollup(Interval::Fixed(TimeDelta::hours(1)), Rollup::Sum)
+            .unwrap();
+        assert_eq!(vals(&hourly), [6.0, 4.0, 5.0]);
+        assert_eq!(hourly.items()[0].0, ts(1, 0, 0));
+        assert_eq!(hourly.unit(), Some(&HUnit::new("kW".into())));
+
+        let quarters = series
+            .rollup(Interval::Fixed(TimeDelta::minutes(15)), Rollup::Avg)
+            .unwrap();
+        assert_eq!(vals(&quarters), [1.5, 3.0, 4.0, 5.0]);
+        assert_eq!(quarters.items()[1].0, ts(1, 0, 15));
+
+        let counts = series
+            .rollup(Interval::Fixed(TimeDelta::hours(1)), Rollup::Count)
+            .unwrap();
+        assert_eq!(vals(&counts), [3.0, 1.0, 1.0]);
+        assert_eq!(counts.unit(), None);
+
+        for len in [
+            TimeDelta::zero(),
+            TimeDelta::minutes(-15),
+            TimeDelta::microseconds(500),
+        ] {
+            assert_eq!(
+                series.rollup(Interval::Fixed(len), Rollup::Sum),
+                Err(HisErr::InvalidInterval(len))
+            );
+        }
+    }
+
+    #[test]
+    fn rollup_calendar_buckets() {
+        let series = series();
+        let rollup = |interval, rollup| vals(&series.rollup(interval, rollup).unwrap());
+        assert_eq!(rollup(Interval::Day, Rollup::Max), [4.0, 5.0]);
+        assert_eq!(rollup(Interval::Day, Rollup::Min), [1.0, 5.0]);
+        assert_eq!(rollup(Interval::Month, Rollup::First), [2.0]);
+        assert_eq!(rollup(Interval::Year, Rollup::Last), [5.0]);
+
+        // 2025-01-01 is a Wednesday, so the week starts on Sunday 2024-12-29
+        let weeks = series.rollup(Interval::Week, Rollup::Count).unwrap();
+        assert_eq!(weeks.items()[0].0.val().to_string(), "2024-12-29 00:00:00");
+        assert_eq!(weeks.items()[0].0.tz_id(), Tz::Australia__Sydney);
+    }
+
+    #[test]
+    fn write_grids() {
+        let series = series();
+        let grid = series.to_grid();
+        assert!(grid.meta().has("id"));
+        assert_eq!(HisSeries::from_grid(&grid).unwrap()[0], series);
+
+        let mut other = HisSeries::new(Some(HRef::new("p2".into(), None)), None, Tz::UTC);
+        other.push(ts(1, 1, 0), HNumber::new(7.0, None));
+        other.push(ts(1, 0, 0), HNumber::new(6.0, None));
+        assert_eq!(vals(&other), [6.0, 7.0]);
+
+        let batch = HisSeries::to_batch_grid(&[series, other]);
+        let read = HisSeries::from_grid(&batch).unwrap();
+        assert_eq!(read[1].id(), Some(&HRef::new("p2".into(), None)));
+        assert_eq!(vals(&read[1]), [6.0, 7.0]);
+        assert_eq!(read[0].len(), 5);
+    }
+
+    fn pushed(unit: &str, items: &[(u32, f64)]) -> HisSeries<f64> {
+        let unit = HUnit::new(unit.into());
+        let mut series = HisSeries::new(None, Some(unit.clone()), Tz::Australia__Sydney);
+        for (min, val) in items {
+            series.push(ts(1, 0, *min), HNumber::new(*val, Some(unit.clone())));
+        }
+        series
+    }
+
+    fn aligned(aligned: &AlignedHis<f64>, series: usize) -> Vec<Option<f64>> {
+        let vals = aligned.vals(series).unwrap();
+        vals.iter()
+            .map(|val| val.as_ref().map(HNumber::val))
+            .collect()
+    }
+
+    #[test]
+    fn align_with_fills() {
+        let temp = pushed("°C", &[(0, 10.0), (30, 40.0)]);
+        let cov = pushed("%", &[(40, 2.0), (10, 1.0)]);
+        let fills = [Fill::Previous, Fill::Null, Fill::Zero];
+        let series: Vec<_> = std::iter::once((&temp, Fill::Linear))
+            .chain(fills.iter().map(|fill| (&cov, *fill)))
+            .collect();
+
+        let union = HisSeries::align(&series, Axis::Union).unwrap();
+        assert_eq!(
+            union.ts(),
+            [ts(1, 0, 0), ts(1, 0, 10), ts(1, 0, 30), ts(1, 0, 40)]
+        );
+        assert_eq!(
+            aligned(&union, 0),
+            [Some(10.0), Some(20.0), Some(40.0), None]
+        );
+        assert_eq!(aligned(&union, 1), [None, Some(1.0), Some(1.0), Some(2.0)]);
+        assert_eq!(aligned(&union, 2), [None, Some(1.0), None, Some(2.0)]);
+        assert_eq!(
+            aligned(&union, 3),
+            [Some(0.0), Some(1.0), Some(0.0), Some(2.0)]
+        );
+        assert_eq!(union.unit(0), Some(&HUnit::new("°C".into())));
+
+        let quarters = Axis::Every(Interval::Fixed(TimeDelta::minutes(15)));
+        let regular = HisSeries::align(&series[..2], quarters).unwrap();
+        assert_eq!(regular.ts(), [ts(1, 0, 0), ts(1, 0, 15), ts(1, 0, 30)]);
+        assert_eq!(aligned(&regular, 0), [Some(10.0), Some(25.0), Some(40.0)]);
+        assert_eq!(regular.row(1).unwrap()[1].map(HNumber::val), Some(1.0));
+        assert!(matches!(
+            HisSeries::align(&series, Axis::Every(Interval::Fixed(TimeDelta::zero()))),
+            Err(HisErr::InvalidInterval(_))
+        ));
+
+        let grid = regular.to_grid();
+        assert_eq!(grid.len(), 3);
+        let read = HisSeries::from_grid(&grid).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].unit(), Some(&HUnit::new("%".into())));
+    }
+
+    #[test]
+    fn find_gaps() {
+        let series = series();
+        assert_eq!(
+            series.gaps(TimeDelta::hours(1)),
+            [(ts(1, 1, 0), ts(3, 9, 0))]
+        );
+        assert_eq!(
+            series.gaps_within(&ts(1, 0, 0), &ts(4, 0, 0), TimeDelta::hours(1)),
+            [(ts(1, 1, 0), ts(3, 9, 0)), (ts(3, 9, 0), ts(4, 0, 0))]
+        );
+        assert!(series.gaps(TimeDelta::days(3)).is_empty());
+        let empty = HisSeries::<f64>::new(None, None, Tz::UTC);
+        assert_eq!(
+            empty.gaps_within(&ts(1, 0, 0), &ts(2, 0, 0), TimeDelta::hours(1)),
+            [(ts(1, 0, 0), ts(2, 0, 0))]
+        );
+    }
+}
@@ -8,13 +8,14 @@ use nom::number::complete::double;
 use nom::sequence::{preceded, terminated};
 use nom::{IResult, Parser};
 
-use core::str::FromStr;
 use std::collections::HashMap;
-use std::rc::Rc;
 use std::sync::OnceLock;
 
 pub mod write;
 
+#[cfg(feature = "serde")]
+mod serde;
+
 use crate::{
     h_bool::HBool,
     h_coord::HCoord,
@@ -33,11 +34,21 @@ use crate::{
     h_symbol::HSymbol,
     h_time::HTime,
     h_uri::HUri,
-    h_val::HBox,
+    h_val::{HBox, Ptr},
     h_xstr::HXStr,
 };
 
 use crate::common::*;
+use crate::err::ZincParseErr;
+use crate::value::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JsonVersion {
+    /// Haystack 3 encoding where scalars are type-prefixed strings, ie. `"n:42 kW"`
+    V3,
+    /// Haystack 4 (Hayson) encoding where scalars are objects tagged with `_kind`
+    V4,
+}
 
 pub struct ParseHint {
     tz: OnceLock<chrono_tz::Tz>,
@@ -61,17 +72,10 @@ fn get_timezone(tz_name: &str, dt_cell: &mut ParseHint) -> Result<chrono_tz::Tz,
     let _tz = dt_cell.tz().get_or_init(|| chrono_tz::Tz::UTC);
     match dt_cell.tz().get_mut() {
         Some(tz) => {
-            if tz.name() == tz_name || tz.name().split('/').last() == Some(tz_name) {
+            if tz.name() == tz_name || crate::tz::name(*tz) == tz_name {
                 return Ok(tz.clone());
             }
-            let tz_clone = match chrono_tz::Tz::from_str(tz_name) {
-                Ok(tz_clone) => tz_clone,
-                Err(_) => chrono_tz::TZ_VARIANTS
-                    .iter()
-                    .find(|&&t| t.name() == tz_name || t.name().split('/').last() == Some(tz_name))
-                    .ok_or_else(|| "Invalid timezone name")?
-                    .clone(),
-            };
+            let tz_clone = crate::tz::find(tz_name).ok_or("Invalid timezone name")?;
 
             *tz = tz_clone.clone();
             return Ok(tz_clone);
@@ -88,10 +92,16 @@ pub mod parse {
 
     macro_rules! into_box {
         ( $fn: expr, $num_type: ty, $lt: lifetime ) => {
-            map($fn, |hval| Rc::new(hval) as HBox<$lt, $num_type>)
+            map($fn, |hval| Ptr::new(hval) as HBox<$lt, $num_type>)
         };
     }
 
+    pub mod brio;
+    pub mod csv;
+    pub mod json;
+    pub mod trio;
+    pub mod zinc_stream;
+
     pub mod zinc {
         use chrono::FixedOffset;
         use chrono_tz::Tz;
@@ -122,7 +132,7 @@ pub mod parse {
                     // TODO: Implement tests for collection types
                     into_box!(dict::<T>,T,'out),
                     into_box!(list::<T>,T,'out),
-                    into_box!(delimited(tag("<<"),grid::<T>,tag(">>")),T,'out),
+                    into_box!(delimited(tag("<<"),grid::<T>,preceded(opt(tag("\n")),tag(">>"))),T,'out),
                 ))
                 .parse(input)
             }
@@ -427,7 +437,10 @@ pub mod parse {
                 let mut map: HashMap<String, HBox<'out, T>> = HashMap::new();
 
                 res.into_iter().for_each(|(k, v)| {
-                    map.insert(k.to_owned(), v.unwrap_or(Rc::new(HMarker) as HBox<'out, T>));
+                    map.insert(
+                        k.to_owned(),
+                        v.unwrap_or(Ptr::new(HMarker) as HBox<'out, T>),
+                    );
                 });
 
                 Ok((input, map))
@@ -579,9 +592,8 @@ pub mod parse {
                 take_while1(|c| c == '\n'),
             ))
             .parse(input);
-            let is_empty = is_empty_res.is_ok();
-            if is_empty {
-                return Ok((input, HGrid::Empty { meta }));
+            if let Ok((rest, _)) = is_empty_res {
+                return Ok((rest, HGrid::Empty { meta }));
             }
 
             // Cols
@@ -608,12 +620,152 @@ pub mod parse {
             Ok((input, grid))
         }
 
+        fn nom_err<'i>(
+            input: &'i str,
+            err: nom::Err<Error<&'i str>>,
+            expected: &str,
+        ) -> ZincParseErr {
+            match err {
+                nom::Err::Error(e) | nom::Err::Failure(e) => {
+                    ZincParseErr::new(input, e.input, expected)
+                }
+                nom::Err::Incomplete(_) => {
+                    ZincParseErr::new(input, &input[input.len()..], expected)
+                }
+            }
+        }
+
+        /// Finds the failing cell of a row that didn't parse, `width` is the expected cell count
+        pub(crate) fn row_err<'out, T: NumTrait + 'out>(
+            input: &str,
+            row: &str,
+            width: usize,
+        ) -> ZincParseErr {
+            let mut parse_hint = ParseHint::default();
+            let mut pos = row;
+            let mut cells = 0;
+
+            loop {
+                let rest = match opt(literal::<T>(&mut parse_hint)).parse(pos) {
+                    Ok((rest, _)) => rest,
+                    Err(_) => pos,
+                };
+                cells += 1;
+
+                match rest.chars().next() {
+                    Some(',') => pos = &rest[1..],
+                    None | Some('\n') | Some('\r') => {
+                        return ZincParseErr::new(
+                            input,
+                            row,
+                            format!("a row of {} cells but found {}", width, cells),
+                        );
+                    }
+                    Some(_) => {
+                        return ZincParseErr::new(
+                            input,
+                            rest,
+                            "a value, ',' or the end of the row",
+                        );
+                    }
+                }
+            }
+        }
+
+        /// Parses a complete grid, reporting the line and column where it failed
+        pub fn parse_grid<'out, T: NumTrait + 'out>(
+            input: &str,
+        ) -> Result<HGrid<'out, T>, ZincParseErr> {
+            let header_err = |pos: &str| {
+                let line = input[..input.len() - pos.len()].matches('\n').count();
+                match line {
+                    0 => "the grid version and meta, ie. ver:\"3.0\"",
+                    _ => "comma separated column names",
+                }
+            };
+
+            let width = || {
+                let (_, cols) = cols::<T>(input.lines().nth(1)?).ok()?;
+                Some(cols.len())
+            };
+
+            match grid::<T>(input) {
+                Ok((rest, grid)) if rest.trim().is_empty() => Ok(grid),
+                // Rows stop at the first that fails, which follows the remaining newline
+                Ok((rest, _)) => {
+                    let row = rest.strip_prefix('\n').unwrap_or(rest);
+                    Err(row_err::<T>(input, row, width().unwrap_or_default()))
+                }
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                    let line = input[..input.len() - e.input.len()].matches('\n').count();
+                    match line {
+                        0 | 1 => Err(ZincParseErr::new(input, e.input, header_err(e.input))),
+                        _ => {
+                            let start = input[..input.len() - e.input.len()]
+                                .rfind('\n')
+                                .map(|idx| idx + 1)
+                                .unwrap_or(0);
+                            Err(row_err::<T>(
+                                input,
+                                &input[start..],
+                                width().unwrap_or_default(),
+                            ))
+                        }
+                    }
+                }
+                Err(e) => Err(nom_err(input, e, "a complete grid")),
+            }
+        }
+
+        pub fn parse_dict<'out, T: NumTrait + 'out>(
+            input: &str,
+        ) -> Result<HDict<'out, T>, ZincParseErr> {
+            let (rest, dict) = dict::<T>(input).map_err(|e| {
+                nom_err(
+                    input,
+                    e,
+                    "a dict of name:value pairs, ie. {site dis:\"Site\"}",
+                )
+            })?;
+            match rest.trim().is_empty() {
+                true => Ok(dict),
+                false => Err(ZincParseErr::new(input, rest, "the end of input")),
+            }
+        }
+
+        pub fn parse_list<'out, T: NumTrait + 'out>(
+            input: &str,
+        ) -> Result<HList<'out, T>, ZincParseErr> {
+            let (rest, list) = list::<T>(input)
+                .map_err(|e| nom_err(input, e, "a list of comma separated values, ie. [1, 2]"))?;
+            match rest.trim().is_empty() {
+                true => Ok(list),
+                false => Err(ZincParseErr::new(input, rest, "the end of input")),
+            }
+        }
+
+        /// Parses a single value, or a whole grid when the input starts with its version
+        pub fn parse_value<T: NumTrait>(input: &str) -> Result<Value<T>, ZincParseErr> {
+            if input.trim_start().starts_with("ver:") {
+                return parse_grid::<T>(input).map(|grid| Value::from(&grid));
+            }
+
+            let mut parse_hint = ParseHint::default();
+            let (rest, val) = literal::<T>(&mut parse_hint)(input)
+                .map_err(|e| nom_err(input, e, "a zinc value"))?;
+            match rest.trim().is_empty() {
+                true => Ok(Value::from(val)),
+                false => Err(ZincParseErr::new(input, rest, "the end of input")),
+            }
+        }
+
         #[cfg(test)]
         mod tests {
             use super::*;
             use crate::h_datetime::IntoTimezone;
             use crate::io::write::ZincWriter;
             use std::fmt::Write;
+            use std::str::FromStr;
 
             #[test]
             fn parse_unicode_char() {
@@ -631,7 +783,7 @@ pub mod parse {
 
                     let v = e.get("dis").unwrap();
                     write!(buf, "{}", ZincWriter::new(v.as_ref())).unwrap();
-                    let rhs = Rc::new(HStr("Fri 31-Jul-2020".to_owned())) as HBox<f64>;
+                    let rhs = Ptr::new(HStr("Fri 31-Jul-2020".to_owned())) as HBox<f64>;
                     assert_eq!(v, &rhs)
                 } else {
                     panic!("Failed to parse separated list")
@@ -984,6 +1136,44 @@ pub mod parse {
                 assert_eq!(grid.rows()[1][1].get_bool(), Some(&HBool(false)));
             }
             */
+
+            #[test]
+            fn parse_grid_errors() {
+                let grid = parse_grid::<f64>("ver:\"3.0\"\na,b\n1,2\n3,4\n").unwrap();
+                assert_eq!(grid.len(), 2);
+
+                let err = parse_grid::<f64>("version:3\na\n1\n").unwrap_err();
+                assert_eq!((err.line, err.col), (1, 1));
+                assert!(err.expected.contains("grid version"));
+
+                let err = parse_grid::<f64>("ver:\"3.0\"\nA,b\n1,2\n").unwrap_err();
+                assert_eq!((err.line, err.col), (2, 1));
+                assert!(err.expected.contains("column names"));
+
+                let err = parse_grid::<f64>("ver:\"3.0\"\na,b\n1,2\n3,\"open\n").unwrap_err();
+                assert_eq!((err.line, err.col), (4, 3));
+                assert_eq!(err.snippet, "\"open");
+
+                let err = parse_grid::<f64>("ver:\"3.0\"\na,b\n1,2,3\n").unwrap_err();
+                assert_eq!((err.line, err.col), (3, 1));
+                assert_eq!(err.expected, "a row of 2 cells but found 3");
+            }
+
+            #[test]
+            fn parse_dict_list_errors() {
+                assert!(parse_dict::<f64>("{site dis:\"A\"}").is_ok());
+                let err = parse_dict::<f64>("{site} extra").err().unwrap();
+                assert_eq!((err.line, err.col, err.snippet.as_str()), (1, 7, " extra"));
+                assert!(parse_dict::<f64>("site").is_err());
+
+                assert_eq!(parse_list::<f64>("[1,2]").unwrap().len(), 2);
+                let err = parse_list::<f64>("(1,2)").err().unwrap();
+                assert_eq!((err.line, err.col), (1, 1));
+                assert!(
+                    err.to_string()
+                        .starts_with("Error: Invalid zinc at line 1, column 1")
+                );
+            }
         }
     }
 
@@ -1115,8 +1305,7 @@ pub mod parse {
             code: ErrorKind::Digit,
         })))?;
 
-        let (input, _) = tag(".")(input)?;
-        let (input, nano) = map(opt(digit1), |s| {
+        let (input, nano) = map(opt(preceded(tag("."), digit1)), |s| {
             if let Some(s) = s {
                 u32::from_str_radix(s, 10)
             } else {
@@ -1178,6 +1367,10 @@ pub mod parse {
                 time("07:23:02.773").unwrap(),
                 ("", HTime::new(07, 23, 02, 773).unwrap())
             );
+            assert_eq!(
+                time("08:00:00").unwrap(),
+                ("", HTime::new(8, 0, 0, 0).unwrap())
+            );
         }
     }
 }
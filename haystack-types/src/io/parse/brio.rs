@@ -0,0 +1,485 @@
+use super::*;
+use chrono::{TimeDelta, TimeZone};
+use nom::number::complete::{be_f64, be_i16, be_i32, be_i64, be_u8, be_u16, be_u32};
+
+use crate::HType;
+use crate::h_grid::GridBuilder;
+use crate::h_number::cast;
+use crate::io::write::brio::{BrioConsts, ctrl, epoch};
+
+fn invalid(input: &[u8]) -> nom::Err<Error<&[u8]>> {
+    nom::Err::Error(Error {
+        input,
+        code: ErrorKind::Verify,
+    })
+}
+
+fn byte(expected: u8) -> impl Fn(&[u8]) -> IResult<&[u8], u8> {
+    move |input| verify(be_u8, |b| *b == expected).parse(input)
+}
+
+fn var_int(input: &[u8]) -> IResult<&[u8], i64> {
+    let (rest, first) = be_u8(input)?;
+    match first {
+        0xff => Ok((rest, -1)),
+        _ if first & 0x80 == 0 => Ok((rest, first as i64)),
+        _ if first & 0xc0 == 0x80 => map(be_u16, |v| (v & 0x3fff) as i64).parse(input),
+        _ if first & 0xe0 == 0xc0 => map(be_u32, |v| (v & 0x1fff_ffff) as i64).parse(input),
+        _ => be_i64(rest),
+    }
+}
+
+/// An inline string, its length counts chars rather than bytes
+fn chars(input: &[u8]) -> IResult<&[u8], String> {
+    let (rest, count) = var_int(input)?;
+    let mut len = 0;
+    for _ in 0..count.max(0) {
+        len += match rest.get(len) {
+            Some(0x00..0x80) => 1,
+            Some(0xf0..) => 4,
+            Some(0xe0..) => 3,
+            Some(_) => 2,
+            None => return Err(invalid(input)),
+        };
+    }
+    match rest.get(..len).map(std::str::from_utf8) {
+        Some(Ok(s)) => Ok((&rest[len..], s.to_owned())),
+        _ => Err(invalid(input)),
+    }
+}
+
+struct Reader<'c> {
+    consts: Option<&'c BrioConsts>,
+}
+
+impl Reader<'_> {
+    fn str<'i>(&self, input: &'i [u8]) -> IResult<&'i [u8], String> {
+        let (rest, code) = var_int(input)?;
+        if code < 0 {
+            return chars(rest);
+        }
+        match self.consts.and_then(|consts| consts.get(code as usize)) {
+            Some(s) => Ok((rest, s.to_owned())),
+            None => Err(invalid(input)),
+        }
+    }
+
+    fn number<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+        val: f64,
+    ) -> IResult<&'i [u8], HBox<'out, T>> {
+        let (rest, unit) = self.str(input)?;
+        let unit = (!unit.is_empty()).then(|| HUnit::new(unit));
+        Ok((rest, Ptr::new(HNumber::new(cast(val), unit))))
+    }
+
+    fn datetime<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+        since: TimeDelta,
+    ) -> IResult<&'i [u8], HBox<'out, T>> {
+        let (rest, tz) = self.str(input)?;
+        let tz = crate::tz::find(&tz).ok_or_else(|| invalid(input))?;
+        let utc = epoch()
+            .checked_add_signed(since)
+            .ok_or_else(|| invalid(input))?;
+        Ok((rest, Ptr::new(HDateTime::from(tz.from_utc_datetime(&utc)))))
+    }
+
+    fn dict<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+    ) -> IResult<&'i [u8], HDict<'out, T>> {
+        let (mut rest, len) = preceded(byte(b'{'), var_int).parse(input)?;
+        let mut dict = HDict::new();
+        for _ in 0..len {
+            let (next, name) = self.str(rest)?;
+            let (next, val) = self.val(next)?;
+            dict.set(name, val);
+            rest = next;
+        }
+        let (rest, _) = byte(b'}')(rest)?;
+        Ok((rest, dict))
+    }
+
+    fn list<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+    ) -> IResult<&'i [u8], HList<'out, T>> {
+        let (mut rest, len) = preceded(byte(b'['), var_int).parse(input)?;
+        let mut list = HList::new();
+        for _ in 0..len {
+            let (next, val) = self.val(rest)?;
+            list.push(val);
+            rest = next;
+        }
+        let (rest, _) = byte(b']')(rest)?;
+        Ok((rest, list))
+    }
+
+    /// A nested dict, written as a full value so it may also be empty
+    fn meta<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+    ) -> IResult<&'i [u8], HDict<'out, T>> {
+        let (rest, val) = self.val::<T>(input)?;
+        match val.get_dict() {
+            Some(dict) => Ok((rest, dict.clone())),
+            None => Err(invalid(input)),
+        }
+    }
+
+    fn grid<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+    ) -> IResult<&'i [u8], HGrid<'out, T>> {
+        let (rest, (num_cols, num_rows)) = preceded(byte(b'<'), (var_int, var_int)).parse(input)?;
+        if num_cols < 0 || num_rows < 0 || (num_cols == 0 && num_rows > 0) {
+            return Err(invalid(input));
+        }
+        let (mut rest, meta) = self.meta::<T>(rest)?;
+
+        let mut builder = GridBuilder::new().extend_meta(meta.clone());
+        for _ in 0..num_cols {
+            let (next, name) = self.str(rest)?;
+            let (next, col_meta) = self.meta(next)?;
+            builder = builder
+                .add_col(&name, col_meta)
+                .map_err(|_| invalid(rest))?;
+            rest = next;
+        }
+        // Every cell takes at least a byte, so counts past the input are corrupt
+        if num_cols > 0 && num_rows > rest.len() as i64 / num_cols {
+            return Err(invalid(input));
+        }
+        for _ in 0..num_rows {
+            let mut row = Vec::new();
+            for _ in 0..num_cols {
+                let (next, val) = self.val::<T>(rest)?;
+                row.push((val.haystack_type() != HType::Null).then_some(val));
+                rest = next;
+            }
+            builder = builder.add_row(row).map_err(|_| invalid(rest))?;
+        }
+        let (rest, _) = byte(b'>')(rest)?;
+
+        if meta.has("err") {
+            let text = |name| Some(meta.get(name)?.get_string()?.clone_into_string());
+            let grid = HGrid::Error {
+                dis: text("dis").unwrap_or_default(),
+                errTrace: text("errTrace"),
+            };
+            return Ok((rest, grid));
+        }
+        Ok((rest, builder.build()))
+    }
+
+    fn val<'i, 'out, T: NumTrait + 'out>(
+        &self,
+        input: &'i [u8],
+    ) -> IResult<&'i [u8], HBox<'out, T>> {
+        let (rest, code) = be_u8(input)?;
+        match code {
+            ctrl::NULL => Ok((rest, Ptr::new(HNull))),
+            ctrl::MARKER => Ok((rest, Ptr::new(HMarker))),
+            ctrl::NA => Ok((rest, Ptr::new(HNA))),
+            ctrl::REMOVE => Ok((rest, Ptr::new(HRemove))),
+            ctrl::FALSE => Ok((rest, Ptr::new(HBool(false)))),
+            ctrl::TRUE => Ok((rest, Ptr::new(HBool(true)))),
+            ctrl::NUMBER_I2 => {
+                let (rest, val) = be_i16(rest)?;
+                self.number(rest, val as f64)
+            }
+            ctrl::NUMBER_I4 => {
+                let (rest, val) = be_i32(rest)?;
+                self.number(rest, val as f64)
+            }
+            ctrl::NUMBER_F8 => {
+                let (rest, val) = be_f64(rest)?;
+                self.number(rest, val)
+            }
+            ctrl::STR => map(|i| self.str(i), |s| Ptr::new(HStr(s)) as HBox<T>).parse(rest),
+            ctrl::REF_STR | ctrl::REF_I8 => {
+                let (rest, id) = match code {
+                    ctrl::REF_STR => chars(rest)?,
+                    _ => map(be_i64, |handle| {
+                        format!("{:08x}-{:08x}", (handle >> 32) as u32, handle as u32)
+                    })
+                    .parse(rest)?,
+                };
+                let (rest, dis) = chars(rest)?;
+                let dis = (!dis.is_empty()).then_some(dis);
+                Ok((rest, Ptr::new(HRef::new(id, dis))))
+            }
+            ctrl::URI => {
+                let (rest, uri) = self.str(rest)?;
+                let uri = HUri::new(&uri).map_err(|_| invalid(input))?;
+                Ok((rest, Ptr::new(uri)))
+            }
+            ctrl::DATE => {
+                let (rest, (year, month, day)) = (be_i16, be_u8, be_u8).parse(rest)?;
+                let date = HDate::new(year as i32, month as u32, day as u32)
+                    .map_err(|_| invalid(input))?;
+                Ok((rest, Ptr::new(date)))
+            }
+            ctrl::TIME => {
+                let (rest, millis) = be_i32(rest)?;
+                let secs = (millis / 1000) as u32;
+                let nano = (millis % 1000) as u32 * 1_000_000;
+                let time = HTime::new(secs / 3600, secs / 60 % 60, secs % 60, nano)
+                    .map_err(|_| invalid(input))?;
+                Ok((rest, Ptr::new(time)))
+            }
+            ctrl::DATETIME_I4 => {
+                let (rest, secs) = be_i32(rest)?;
+                self.datetime(rest, TimeDelta::seconds(secs as i64))
+            }
+            ctrl::DATETIME_I8 => {
+                let (rest, nanos) = be_i64(rest)?;
+                self.datetime(rest, TimeDelta::nanoseconds(nanos))
+            }
+            ctrl::COORD => {
+                let (rest, (lat, long)) = (be_i32, be_i32).parse(rest)?;
+                let unpack = |val: i32, from: f64| cast(val as f64 / 1_000_000.0 - from);
+                let coord = HCoord::new(unpack(lat, 90.0), unpack(long, 180.0));
+                Ok((rest, Ptr::new(coord)))
+            }
+            ctrl::XSTR => {
+                let (rest, xtype) = self.str(rest)?;
+                let (rest, xval) = self.str(rest)?;
+                Ok((rest, Ptr::new(HXStr::new(xtype, xval))))
+            }
+            ctrl::SYMBOL => {
+                map(|i| self.str(i), |s| Ptr::new(HSymbol::new(s)) as HBox<T>).parse(rest)
+            }
+            ctrl::DICT_EMPTY => Ok((rest, Ptr::new(HDict::<T>::new()))),
+            ctrl::DICT => map(|i| self.dict(i), |d| Ptr::new(d) as HBox<T>).parse(rest),
+            ctrl::LIST_EMPTY => Ok((rest, Ptr::new(HList::<T>::new()))),
+            ctrl::LIST => map(|i| self.list(i), |l| Ptr::new(l) as HBox<T>).parse(rest),
+            ctrl::GRID => map(|i| self.grid(i), |g| Ptr::new(g) as HBox<T>).parse(rest),
+            // Bufs have no Haystack kind to read into
+            ctrl::BUF => Err(invalid(input)),
+            _ => Err(invalid(input)),
+        }
+    }
+}
+
+/// Reads one value with every string inline
+pub fn literal<'out, T: NumTrait + 'out>(input: &[u8]) -> IResult<&[u8], HBox<'out, T>> {
+    literal_with(input, None)
+}
+
+/// Reads one value, looking up string codes in `consts`
+pub fn literal_with<'a, 'out, T: NumTrait + 'out>(
+    input: &'a [u8],
+    consts: Option<&BrioConsts>,
+) -> IResult<&'a [u8], HBox<'out, T>> {
+    Reader { consts }.val(input)
+}
+
+pub fn dict<'out, T: NumTrait + 'out>(input: &[u8]) -> IResult<&[u8], HDict<'out, T>> {
+    let (rest, val) = literal::<T>(input)?;
+    match val.get_dict() {
+        Some(dict) => Ok((rest, dict.clone())),
+        None => Err(invalid(input)),
+    }
+}
+
+pub fn grid<'out, T: NumTrait + 'out>(input: &[u8]) -> IResult<&[u8], HGrid<'out, T>> {
+    grid_with(input, None)
+}
+
+pub fn grid_with<'a, 'out, T: NumTrait + 'out>(
+    input: &'a [u8],
+    consts: Option<&BrioConsts>,
+) -> IResult<&'a [u8], HGrid<'out, T>> {
+    let (rest, _) = byte(ctrl::GRID)(input)?;
+    Reader { consts }.grid(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ParseHint;
+    use crate::io::parse::zinc;
+    use crate::io::write::brio::BrioEncoder;
+    use crate::io::write::{BrioWriter, ZincWriter};
+    use std::fmt::Write;
+
+    fn to_zinc(val: &HBox<f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(val.as_ref())).unwrap();
+        buf
+    }
+
+    fn round_trip(input: &str) -> (HBox<'_, f64>, HBox<'_, f64>) {
+        let (_, val) = zinc::literal::<f64>(&mut ParseHint::default())(input).unwrap();
+        let bytes = BrioWriter::new(val.as_ref()).to_bytes();
+        let (rest, back) = literal::<f64>(&bytes).unwrap();
+        assert!(rest.is_empty(), "trailing bytes for {input}");
+        assert!(*back == *val, "{input}");
+        (val, back)
+    }
+
+    #[test]
+    fn round_trip_scalars() {
+        for input in [
+            "N",
+            "M",
+            "NA",
+            "R",
+            "T",
+            "F",
+            "12",
+            "-40000",
+            "3000000000",
+            "72.5°F",
+            "\"Hello\"",
+            "\"Ĉu vi parolas? ☃\"",
+            "`http://project-haystack.org/`",
+            "2024-02-29",
+            "08:12:05",
+            "2024-03-10T02:30:00Z UTC",
+            "1999-12-31T23:59:59Z UTC",
+            "2024-01-10T02:30:00.123-05:00 New_York",
+            "C(37.5,-77.25)",
+            "^elec-meter",
+            "@site \"Main Site\"",
+            "@1deb31b8-7508b187",
+            "@1deb31b8-7508b187 \"Handle\"",
+        ] {
+            let (val, back) = round_trip(input);
+            assert_eq!(to_zinc(&back), to_zinc(&val), "{input}");
+        }
+    }
+
+    #[test]
+    fn round_trip_xstr() {
+        let val: HBox<f64> = Ptr::new(HXStr::new("Bin".to_owned(), "text/plain".to_owned()));
+        let bytes = BrioWriter::new(val.as_ref()).to_bytes();
+        assert_eq!(bytes[0], ctrl::XSTR);
+        let (_, back) = literal::<f64>(&bytes).unwrap();
+        assert_eq!(to_zinc(&back), to_zinc(&val));
+    }
+
+    #[test]
+    fn round_trip_collections() {
+        for input in [
+            "{}",
+            "[]",
+            "{dis:\"Site\" site area:1200ft² tags:[1, \"a\", {nested}]}",
+            "[N, M, [], {}, @a]",
+        ] {
+            round_trip(input);
+        }
+    }
+
+    #[test]
+    fn round_trip_grids() {
+        for input in [
+            "ver:\"3.0\" view:\"chart\"\nid,dis dis:\"Display\",area\n@a \"Alpha\",\"Alpha\",1200ft²\n@b,\"Beta\",\n",
+            "ver:\"3.0\"\nempty\n",
+            "ver:\"3.0\"\nval\n<<\nver:\"3.0\"\nx,y\n1,2\n>>\n",
+        ] {
+            let (_, grid) = zinc::grid::<f64>(input).unwrap();
+            let bytes = BrioWriter::new(&grid).to_bytes();
+            assert_eq!(bytes[0], ctrl::GRID);
+            let (rest, back) = super::grid::<f64>(&bytes).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(back, grid, "{input}");
+        }
+    }
+
+    #[test]
+    fn error_grid_is_read_back_as_error() {
+        let grid: HGrid<f64> = HGrid::Error {
+            dis: "Oops".to_owned(),
+            errTrace: Some("trace".to_owned()),
+        };
+        let bytes = BrioWriter::new(&grid).to_bytes();
+        match super::grid::<f64>(&bytes).unwrap().1 {
+            HGrid::Error { dis, errTrace } => {
+                assert_eq!(dis, "Oops");
+                assert_eq!(errTrace.as_deref(), Some("trace"));
+            }
+            _ => panic!("Expected error grid"),
+        }
+    }
+
+    #[test]
+    fn consts_replace_inline_strings() {
+        let consts = BrioConsts::new(["dis", "site", "Site A"]);
+        let (_, val) =
+            zinc::literal::<f64>(&mut ParseHint::default())("{dis:\"Site A\" site}").unwrap();
+        let inline = BrioWriter::new(val.as_ref()).to_bytes();
+        let coded = BrioWriter::new(val.as_ref())
+            .with_consts(&consts)
+            .to_bytes();
+        assert!(coded.len() < inline.len());
+
+        let (_, back) = literal_with::<f64>(&coded, Some(&consts)).unwrap();
+        assert!(*back == *val);
+        assert!(literal::<f64>(&coded).is_err());
+    }
+
+    #[test]
+    fn corrupt_grid_counts_fail() {
+        // No columns but 0x1fffffff rows
+        let bytes = [0x18, 0x3c, 0x00, 0xc0, 0xff, 0xff, 0xff, 0x14, 0x3e];
+        assert!(literal::<f64>(&bytes).is_err());
+        // A negative column count
+        assert!(literal::<f64>(&[0x18, 0x3c, 0xff, 0x00, 0x14, 0x3e]).is_err());
+        // More rows than bytes left
+        let bytes = [
+            0x18, 0x3c, 0x01, 0x7f, 0x14, 0xff, 0x01, b'a', 0x14, 0x00, 0x3e,
+        ];
+        assert!(literal::<f64>(&bytes).is_err());
+        let bytes = [
+            0x18, 0x3c, 0x01, 0x01, 0x14, 0xff, 0x01, b'a', 0x14, 0x00, 0x3e,
+        ];
+        assert!(literal::<f64>(&bytes).is_ok());
+        assert!(literal::<f64>(&[0x18, 0x3c, 0x00, 0x00, 0x14, 0x3e]).is_ok());
+    }
+
+    #[test]
+    fn var_int_boundaries() {
+        for val in [
+            -1,
+            0,
+            0x7f,
+            0x80,
+            0x3fff,
+            0x4000,
+            0x1fff_ffff,
+            0x2000_0000,
+            i64::MAX,
+        ] {
+            let mut enc = BrioEncoder::new(None);
+            enc.var_int(val);
+            let bytes = enc.into_bytes();
+            assert_eq!(var_int(&bytes), Ok((&[][..], val)), "{val}");
+        }
+    }
+
+    #[test]
+    fn handle_refs_are_packed() {
+        let (_, val) =
+            zinc::literal::<f64>(&mut ParseHint::default())("@1deb31b8-7508b187").unwrap();
+        let bytes = BrioWriter::new(val.as_ref()).to_bytes();
+        assert_eq!(bytes[0], ctrl::REF_I8);
+        assert_eq!(&bytes[1..9], &0x1deb31b8_7508b187_i64.to_be_bytes());
+
+        let (_, val) = zinc::literal::<f64>(&mut ParseHint::default())("@site").unwrap();
+        assert_eq!(BrioWriter::new(val.as_ref()).to_bytes()[0], ctrl::REF_STR);
+    }
+
+    #[test]
+    fn truncated_input_fails() {
+        let (_, val) = zinc::literal::<f64>(&mut ParseHint::default())("{dis:\"Site\"}").unwrap();
+        let bytes = BrioWriter::new(val.as_ref()).to_bytes();
+        for len in 0..bytes.len() {
+            assert!(literal::<f64>(&bytes[..len]).is_err(), "{len}");
+        }
+    }
+}
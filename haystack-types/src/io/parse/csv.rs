@@ -0,0 +1,533 @@
+use super::*;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use chrono_tz::Tz;
+use nom::character::complete::{char as nom_char, line_ending, space0};
+use nom::combinator::all_consuming;
+use nom::multi::{many0, separated_list0};
+use nom::sequence::delimited;
+use std::borrow::Cow;
+
+use crate::h_marker::MARKER;
+use crate::h_val::HVal;
+
+/// How the cells of a column are read, `Auto` infers the type of each cell
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum CsvType {
+    #[default]
+    Auto,
+    Str,
+    /// Any non-empty cell other than `false`, `no` or `0` is a marker
+    Marker,
+    Bool,
+    /// The unit applies to cells that don't carry their own
+    Number(Option<HUnit>),
+    Ref,
+    Uri,
+    Date(Option<String>),
+    Time(Option<String>),
+    /// Cells without a zone are local to `tz`, UTC when it isn't given
+    DateTime {
+        tz: Option<Tz>,
+        fmt: Option<String>,
+    },
+}
+
+/// Column type spec, written as `name:Type` or `name:Type(args)`
+///
+/// ie. `ts:DateTime(tz=Sydney, fmt=%d/%m/%Y %H:%M)`, `val:Number(kW)` or `enabled:Bool`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColSpec {
+    pub name: String,
+    pub kind: CsvType,
+}
+
+fn invalid(input: &str) -> nom::Err<Error<&str>> {
+    nom::Err::Error(Error {
+        input,
+        code: ErrorKind::Verify,
+    })
+}
+
+fn spec_args(input: &str) -> IResult<&str, Vec<(Option<&str>, &str)>> {
+    let key = terminated(
+        take_while1(|c: char| c.is_ascii_alphanumeric()),
+        nom_char('='),
+    );
+    let arg = (
+        opt(key),
+        map(take_while1(|c: char| c != ',' && c != ')'), str::trim),
+    );
+    delimited(
+        nom_char('('),
+        separated_list0(nom_char(','), preceded(space0, arg)),
+        nom_char(')'),
+    )
+    .parse(input)
+}
+
+pub fn col_spec(input: &str) -> IResult<&str, ColSpec> {
+    let start = input;
+    let (input, name) = take_while1(|c: char| c != ':').parse(input)?;
+    let (input, kind) = preceded(
+        nom_char(':'),
+        take_while1(|c: char| c.is_ascii_alphabetic()),
+    )
+    .parse(input)?;
+    let (input, args) = opt(spec_args).parse(input)?;
+    let args = args.unwrap_or_default();
+
+    let arg = |key: &str| {
+        args.iter()
+            .find(|(k, _)| *k == Some(key) || (k.is_none() && key == "_"))
+            .map(|(_, v)| v.to_string())
+    };
+
+    let kind = match kind {
+        "Auto" => CsvType::Auto,
+        "Str" => CsvType::Str,
+        "Marker" => CsvType::Marker,
+        "Bool" => CsvType::Bool,
+        "Number" => CsvType::Number(arg("unit").or(arg("_")).map(HUnit::new)),
+        "Ref" => CsvType::Ref,
+        "Uri" => CsvType::Uri,
+        "Date" => CsvType::Date(arg("fmt").or(arg("_"))),
+        "Time" => CsvType::Time(arg("fmt").or(arg("_"))),
+        "DateTime" => {
+            let tz = match arg("tz") {
+                Some(tz) => {
+                    Some(get_timezone(&tz, &mut ParseHint::default()).map_err(|_| invalid(start))?)
+                }
+                None => None,
+            };
+            CsvType::DateTime {
+                tz,
+                fmt: arg("fmt").or(arg("_")),
+            }
+        }
+        _ => return Err(invalid(start)),
+    };
+
+    Ok((
+        input,
+        ColSpec {
+            name: name.trim().to_owned(),
+            kind,
+        },
+    ))
+}
+
+/// A field as it appears in the input, quotes included
+fn field(input: &str) -> IResult<&str, &str> {
+    alt((
+        recognize(delimited(
+            nom_char('"'),
+            many0(alt((tag("\"\""), take_while1(|c| c != '"')))),
+            nom_char('"'),
+        )),
+        take_while(|c| c != ',' && c != '\n' && c != '\r'),
+    ))
+    .parse(input)
+}
+
+fn record(input: &str) -> IResult<&str, Vec<&str>> {
+    separated_list1(nom_char(','), field).parse(input)
+}
+
+fn unquote(raw: &str) -> Cow<'_, str> {
+    match raw.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(inner) => Cow::Owned(inner.replace("\"\"", "\"")),
+        None => Cow::Borrowed(raw),
+    }
+}
+
+/// Converts a header into a valid tag name, ie. `Current Value (kW)` becomes `currentValueKW`
+pub fn tag_name(header: &str) -> String {
+    if all_consuming(id).parse(header).is_ok() {
+        return header.to_owned();
+    }
+
+    let mut name = String::with_capacity(header.len());
+    for (idx, word) in header
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .enumerate()
+    {
+        let mut chars = word.chars();
+        let first = chars.next().unwrap();
+        if idx == 0 {
+            name.push(first.to_ascii_lowercase());
+        } else {
+            name.push(first.to_ascii_uppercase());
+        }
+        name.push_str(chars.as_str());
+    }
+
+    match name.chars().next() {
+        None => "blank".to_owned(),
+        Some(c) if c.is_ascii_digit() => format!("v{}", name),
+        Some(_) => name,
+    }
+}
+
+fn datetime_cell(
+    text: &str,
+    tz: Option<Tz>,
+    fmt: Option<&str>,
+    parse_hint: &mut ParseHint,
+) -> Option<HDateTime> {
+    let tz = tz.unwrap_or(Tz::UTC);
+
+    if let Some(fmt) = fmt {
+        let naive = NaiveDateTime::parse_from_str(text, fmt).ok()?;
+        return HDateTime::from_local(naive, tz).ok();
+    }
+
+    if let Ok((_, dt)) = all_consuming(zinc::datetime(parse_hint)).parse(text) {
+        return Some(dt);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(HDateTime::from(dt.with_timezone(&tz)));
+    }
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+    .and_then(|naive| HDateTime::from_local(naive, tz).ok())
+}
+
+fn ref_cell(text: &str) -> Option<HRef> {
+    let text = text.strip_prefix('@').unwrap_or(text);
+    let (id, dis) = match text.split_once(' ') {
+        Some((id, dis)) => {
+            let dis = dis.trim();
+            let dis = dis
+                .strip_prefix('"')
+                .and_then(|d| d.strip_suffix('"'))
+                .unwrap_or(dis);
+            (id, Some(dis.to_owned()))
+        }
+        None => (text, None),
+    };
+    let input = format!("@{}", id);
+    let (_, r) = all_consuming(zinc::reference).parse(&input).ok()?;
+    Some(HRef::new(r.id, dis))
+}
+
+fn bool_cell(text: &str) -> Option<bool> {
+    match text.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn infer<'out, T: NumTrait + 'out>(text: &str, parse_hint: &mut ParseHint) -> HBox<'out, T> {
+    if text == "\u{2713}" {
+        return MARKER.to_hbox();
+    }
+    if text == "true" || text == "false" {
+        return HBool(text == "true").to_hbox();
+    }
+    if text.starts_with('@')
+        && let Some(r) = ref_cell(text)
+    {
+        return r.to_hbox();
+    }
+    if let Some(dt) = datetime_cell(text, None, None, parse_hint) {
+        return dt.to_hbox();
+    }
+    if let Ok((_, date)) = all_consuming(date).parse(text) {
+        return date.to_hbox();
+    }
+    if let Ok((_, time)) = all_consuming(time).parse(text) {
+        return time.to_hbox();
+    }
+    if let Ok((_, num)) = all_consuming(number::<T>).parse(text) {
+        return Ptr::new(num);
+    }
+    HStr(text.to_owned()).to_hbox()
+}
+
+/// Reads a cell as `kind`, `None` when the cell doesn't hold that type
+fn cell<'out, T: NumTrait + 'out>(
+    raw: &str,
+    kind: &CsvType,
+    parse_hint: &mut ParseHint,
+) -> Option<Option<HBox<'out, T>>> {
+    let unquoted = unquote(raw);
+    let text = unquoted.trim();
+    if text.is_empty() {
+        return Some(None);
+    }
+
+    let val = match kind {
+        CsvType::Auto => infer::<T>(text, parse_hint),
+        CsvType::Str => HStr(unquoted.into_owned()).to_hbox(),
+        CsvType::Marker => match bool_cell(text) {
+            Some(false) => return Some(None),
+            _ => MARKER.to_hbox(),
+        },
+        CsvType::Bool => HBool(bool_cell(text)?).to_hbox(),
+        CsvType::Number(unit) => {
+            let (_, num) = all_consuming(number::<T>).parse(text).ok()?;
+            match (num.unit(), unit) {
+                (None, Some(unit)) => Ptr::new(HNumber::new(num.val(), Some(unit.clone()))),
+                _ => Ptr::new(num),
+            }
+        }
+        CsvType::Ref => ref_cell(text)?.to_hbox(),
+        CsvType::Uri => HUri::new(text).ok()?.to_hbox(),
+        CsvType::Date(fmt) => match fmt {
+            Some(fmt) => {
+                let date = NaiveDate::parse_from_str(text, fmt).ok()?;
+                HDate::new(date.year(), date.month(), date.day())
+                    .ok()?
+                    .to_hbox()
+            }
+            None => all_consuming(date).parse(text).ok()?.1.to_hbox(),
+        },
+        CsvType::Time(fmt) => {
+            let time = match fmt {
+                Some(fmt) => NaiveTime::parse_from_str(text, fmt).ok()?,
+                None => ["%H:%M:%S%.f", "%H:%M"]
+                    .iter()
+                    .find_map(|fmt| NaiveTime::parse_from_str(text, fmt).ok())?,
+            };
+            HTime::new(time.hour(), time.minute(), time.second(), time.nanosecond())
+                .ok()?
+                .to_hbox()
+        }
+        CsvType::DateTime { tz, fmt } => {
+            datetime_cell(text, *tz, fmt.as_deref(), parse_hint)?.to_hbox()
+        }
+    };
+    Some(Some(val))
+}
+
+/// Parses CSV with a header row into a grid, inferring the type of every cell
+pub fn grid<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HGrid<'out, T>> {
+    grid_with::<T>(&[])(input)
+}
+
+/// Parses CSV with a header row, reading the columns named in `specs` as the given type
+///
+/// Specs match either the header or the tag name derived from it, other columns are inferred
+pub fn grid_with<'a, 'out, T: NumTrait + 'out>(
+    specs: &'a [ColSpec],
+) -> impl FnMut(&str) -> IResult<&str, HGrid<'out, T>> + 'a {
+    move |input: &str| {
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+        let (rest, records) = separated_list1(line_ending, record).parse(input)?;
+        let (rest, _) = many0(line_ending).parse(rest)?;
+
+        // Blank lines parse as a record with one empty field
+        let mut records = records
+            .into_iter()
+            .filter(|r| !(r.len() == 1 && r[0].trim().is_empty()));
+
+        let Some(header) = records.next() else {
+            return Ok((rest, HGrid::Empty { meta: None }));
+        };
+
+        let mut cols = Vec::new();
+        let mut kinds = Vec::with_capacity(header.len());
+        for raw in header.iter() {
+            let header = unquote(raw);
+            let header = header.trim();
+            let name = tag_name(header);
+            if cols.iter().any(|(n, _)| n == &name) {
+                return Err(invalid(raw));
+            }
+
+            let kind = specs
+                .iter()
+                .find(|s| s.name == name || s.name == header)
+                .map(|s| s.kind.clone())
+                .unwrap_or_default();
+            cols.push((name, None));
+            kinds.push(kind);
+        }
+
+        let mut parse_hint = ParseHint::default();
+        let mut rows = Vec::new();
+        for record in records {
+            if record.len() > kinds.len() {
+                return Err(invalid(record[kinds.len()]));
+            }
+
+            let mut row = Vec::with_capacity(kinds.len());
+            for (idx, kind) in kinds.iter().enumerate() {
+                match record.get(idx) {
+                    Some(raw) => {
+                        row.push(cell::<T>(raw, kind, &mut parse_hint).ok_or_else(|| invalid(raw))?)
+                    }
+                    None => row.push(None),
+                }
+            }
+            rows.push(row);
+        }
+
+        Ok((rest, HGrid::from_row_vec(cols, rows)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::write::ZincWriter;
+    use std::fmt::Write;
+
+    fn zinc(val: &HBox<f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(val.as_ref())).unwrap();
+        buf
+    }
+
+    fn spec(input: &str) -> ColSpec {
+        let (rest, spec) = col_spec(input).unwrap();
+        assert_eq!(rest, "");
+        spec
+    }
+
+    #[test]
+    fn parse_col_spec() {
+        assert_eq!(
+            spec("val:Number(kW)"),
+            ColSpec {
+                name: "val".into(),
+                kind: CsvType::Number(Some(HUnit::new("kW".into())))
+            }
+        );
+        assert_eq!(
+            spec("ts:DateTime(tz=Sydney)").kind,
+            CsvType::DateTime {
+                tz: Some(Tz::Australia__Sydney),
+                fmt: None
+            }
+        );
+        assert_eq!(
+            spec("Start Date:Date(fmt=%d/%m/%Y)"),
+            ColSpec {
+                name: "Start Date".into(),
+                kind: CsvType::Date(Some("%d/%m/%Y".into()))
+            }
+        );
+        assert_eq!(spec("enabled:Bool").kind, CsvType::Bool);
+        assert!(col_spec("ts:Instant").is_err());
+        assert!(col_spec("ts:DateTime(tz=Nowhere)").is_err());
+    }
+
+    #[test]
+    fn parse_inferred() {
+        let input = "id,Display Name,point,curVal,installed,ts,enabled,note\r\n\
+                     @p1 \"Point 1\",\"Zone, Temp\",\u{2713},21.5°C,2024-01-31,2024-01-01T10:00:00+11:00 Sydney,true,\"Say \"\"hi\"\"\"\r\n\
+                     @p2,Zone Humidity,,45%,,2024-01-01T00:00:00Z,false,\r\n";
+        let (rest, grid) = grid::<f64>(input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(grid.len(), 2);
+
+        let names: Vec<_> = grid.iter_cols().map(|c| c.name.clone()).collect();
+        assert_eq!(
+            names,
+            [
+                "id",
+                "displayName",
+                "point",
+                "curVal",
+                "installed",
+                "ts",
+                "enabled",
+                "note"
+            ]
+        );
+
+        let row = grid.get(0).unwrap();
+        assert_eq!(
+            row.get("id").unwrap().get_ref(),
+            Some(&HRef::new("p1".into(), Some("Point 1".into())))
+        );
+        assert_eq!(zinc(&row.get("displayName").unwrap()), "\"Zone, Temp\"");
+        assert_eq!(zinc(&row.get("point").unwrap()), "M");
+        assert_eq!(zinc(&row.get("curVal").unwrap()), "21.5°C");
+        assert!(row.get("installed").unwrap().get_date().is_some());
+        assert!(row.get("ts").unwrap().get_datetime().is_some());
+        assert_eq!(row.get("enabled").unwrap().get_bool(), Some(&HBool(true)));
+        assert_eq!(zinc(&row.get("note").unwrap()), "\"Say \\\"hi\\\"\"");
+
+        let row = grid.get(1).unwrap();
+        assert!(!row.has("point"));
+        assert!(!row.has("note"));
+        assert_eq!(zinc(&row.get("curVal").unwrap()), "45%");
+        assert_eq!(
+            row.get("ts").unwrap().get_datetime().unwrap().tz_id(),
+            Tz::UTC
+        );
+    }
+
+    #[test]
+    fn parse_with_specs() {
+        let specs = [
+            spec("Timestamp:DateTime(tz=Sydney, fmt=%d/%m/%Y %H:%M)"),
+            spec("val:Number(kW)"),
+            spec("code:Str"),
+            spec("occupied:Marker"),
+            spec("equip:Ref"),
+        ];
+        let input = "Timestamp,val,code,occupied,equip\n\
+                     01/07/2024 09:30,12.5,007,yes,ahu1\n\
+                     01/07/2024 09:45,13000W,010,no,@ahu2\n";
+        let (_, grid) = grid_with::<f64>(&specs)(input).unwrap();
+
+        let row = grid.get(0).unwrap();
+        let ts = row.get("timestamp").unwrap();
+        let ts = ts.get_datetime().unwrap();
+        assert_eq!(ts.tz_id(), Tz::Australia__Sydney);
+        assert_eq!((ts.day(), ts.month(), ts.hour()), (1, 7, 9));
+        assert_eq!(ts.offset().local_minus_utc(), 10 * 3600);
+        assert_eq!(zinc(&row.get("val").unwrap()), "12.5kW");
+        assert_eq!(zinc(&row.get("code").unwrap()), "\"007\"");
+        assert!(row.has("occupied"));
+        assert_eq!(
+            row.get("equip").unwrap().get_ref(),
+            Some(&HRef::new("ahu1".into(), None))
+        );
+
+        let row = grid.get(1).unwrap();
+        assert_eq!(zinc(&row.get("val").unwrap()), "13000W");
+        assert!(!row.has("occupied"));
+    }
+
+    #[test]
+    fn parse_short_rows_and_blank_lines() {
+        let (_, grid) = grid::<f64>("a,b,c\n\n1,2\n").unwrap();
+        assert_eq!(grid.len(), 1);
+        assert!(!grid.get(0).unwrap().has("c"));
+
+        let (_, empty) = super::grid::<f64>("").unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let input = "a,b\n1,2,3\n";
+        match grid::<f64>(input) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.input, "3"),
+            _ => panic!("Expected an error for the extra cell"),
+        }
+        assert!(grid::<f64>("a,a\n1,2\n").is_err());
+
+        let specs = [spec("a:Number")];
+        assert!(grid_with::<f64>(&specs)("a\nabc\n").is_err());
+    }
+
+    #[test]
+    fn header_tag_names() {
+        assert_eq!(tag_name("curVal"), "curVal");
+        assert_eq!(tag_name("Current Value (kW)"), "currentValueKW");
+        assert_eq!(tag_name("2nd floor"), "v2ndFloor");
+        assert_eq!(tag_name("--"), "blank");
+    }
+}
@@ -0,0 +1,707 @@
+use super::*;
+use nom::bytes::complete::take_while_m_n;
+use nom::character::complete::{multispace0, one_of};
+use nom::combinator::all_consuming;
+use nom::multi::separated_list0;
+use nom::sequence::delimited;
+
+use std::borrow::Cow;
+
+use crate::h_datetime::HDateTime;
+use crate::h_marker::MARKER;
+use crate::h_na::NA;
+use crate::h_null::NULL;
+use crate::h_remove::REMOVE;
+use crate::value::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json<'i> {
+    Null,
+    Bool(bool),
+    Num(Cow<'i, str>),
+    Str(String),
+    Array(Vec<Json<'i>>),
+    Object(Vec<(String, Json<'i>)>),
+}
+
+impl<'i> Json<'i> {
+    fn get(&self, key: &str) -> Option<&Json<'i>> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(Json::Str(s)) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    fn has_kind(&self) -> bool {
+        match self {
+            Json::Object(entries) => entries.iter().any(|(k, v)| k == "_kind" || v.has_kind()),
+            Json::Array(items) => items.iter().any(Json::has_kind),
+            _ => false,
+        }
+    }
+}
+
+impl JsonVersion {
+    fn detect(json: &Json) -> JsonVersion {
+        if json.has_kind() {
+            JsonVersion::V4
+        } else {
+            JsonVersion::V3
+        }
+    }
+}
+
+fn ws<'i, O, F>(inner: F) -> impl Parser<&'i str, Output = O, Error = Error<&'i str>>
+where
+    F: Parser<&'i str, Output = O, Error = Error<&'i str>>,
+{
+    delimited(multispace0, inner, multispace0)
+}
+
+fn hex_u16(input: &str) -> IResult<&str, u16> {
+    map_res(
+        preceded(
+            tag("\\u"),
+            take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+        ),
+        |s| u16::from_str_radix(s, 16),
+    )
+    .parse(input)
+}
+
+fn unicode_escape(input: &str) -> IResult<&str, char> {
+    let (rest, hi) = hex_u16(input)?;
+    let (rest, code) = if (0xD800..0xDC00).contains(&hi) {
+        let (rest, lo) = verify(hex_u16, |lo| (0xDC00..0xE000).contains(lo)).parse(rest)?;
+        (
+            rest,
+            0x10000 + (((hi as u32) - 0xD800) << 10) + ((lo as u32) - 0xDC00),
+        )
+    } else {
+        (rest, hi as u32)
+    };
+
+    match char::from_u32(code) {
+        Some(c) => Ok((rest, c)),
+        None => Err(nom::Err::Error(Error {
+            input,
+            code: ErrorKind::Char,
+        })),
+    }
+}
+
+enum Chunk<'i> {
+    Char(char),
+    Str(&'i str),
+}
+
+fn json_str(input: &str) -> IResult<&str, String> {
+    let (input, _) = tag("\"")(input)?;
+    let mut buf = String::new();
+    let mut input = input;
+
+    loop {
+        if let Ok((rest, _)) = tag::<_, _, Error<&str>>("\"")(input) {
+            return Ok((rest, buf));
+        }
+
+        let (rest, chunk) = alt((
+            map(unicode_escape, Chunk::Char),
+            map(preceded(tag("\\"), one_of("\"\\/bfnrt")), |c| {
+                Chunk::Char(match c {
+                    'b' => '\x08',
+                    'f' => '\x0C',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    c => c,
+                })
+            }),
+            map(
+                take_while1(|c: char| c != '"' && c != '\\' && c >= ' '),
+                Chunk::Str,
+            ),
+        ))
+        .parse(input)?;
+
+        match chunk {
+            Chunk::Char(c) => buf.push(c),
+            Chunk::Str(s) => buf.push_str(s),
+        }
+        input = rest;
+    }
+}
+
+fn json_num(input: &str) -> IResult<&str, &str> {
+    recognize((
+        opt(tag("-")),
+        digit1,
+        opt((tag("."), digit1)),
+        opt((one_of("eE"), opt(one_of("+-")), digit1)),
+    ))
+    .parse(input)
+}
+
+fn json_val(input: &str) -> IResult<&str, Json<'_>> {
+    alt((
+        value(Json::Null, tag("null")),
+        value(Json::Bool(true), tag("true")),
+        value(Json::Bool(false), tag("false")),
+        map(json_num, |n| Json::Num(Cow::Borrowed(n))),
+        map(json_str, Json::Str),
+        map(
+            delimited(
+                ws(tag("[")),
+                separated_list0(ws(tag(",")), json_val),
+                ws(tag("]")),
+            ),
+            Json::Array,
+        ),
+        map(
+            delimited(
+                ws(tag("{")),
+                separated_list0(ws(tag(",")), (terminated(json_str, ws(tag(":"))), json_val)),
+                ws(tag("}")),
+            ),
+            Json::Object,
+        ),
+    ))
+    .parse(input)
+}
+
+pub(crate) fn document(input: &str) -> IResult<&str, Json<'_>> {
+    ws(json_val).parse(input)
+}
+
+fn invalid(input: &str) -> nom::Err<Error<&str>> {
+    nom::Err::Error(Error {
+        input,
+        code: ErrorKind::Verify,
+    })
+}
+
+/// Converts parsed JSON into Haystack values, sharing timezone lookups across the document
+pub(crate) struct Decoder {
+    version: JsonVersion,
+    parse_hint: ParseHint,
+}
+
+impl Decoder {
+    pub(crate) fn new(version: JsonVersion) -> Self {
+        Decoder {
+            version,
+            parse_hint: ParseHint::default(),
+        }
+    }
+
+    pub(crate) fn val<'out, T: NumTrait + 'out>(&mut self, json: &Json) -> Option<HBox<'out, T>> {
+        let val = match json {
+            Json::Null => Ptr::new(NULL) as HBox<'out, T>,
+            Json::Bool(b) => Ptr::new(HBool(*b)),
+            Json::Num(n) => Ptr::new(HNumber::new(n.parse::<T>().ok()?, None)),
+            Json::Str(s) => match self.version {
+                JsonVersion::V3 => self.v3_str(s)?,
+                JsonVersion::V4 => Ptr::new(HStr(s.clone())),
+            },
+            Json::Array(items) => Ptr::new(self.list::<T>(items)?),
+            Json::Object(_) => match (self.version, json.get_str("_kind")) {
+                (JsonVersion::V4, Some(kind)) => self.kind(kind, json)?,
+                (JsonVersion::V3, _)
+                    if json.get("cols").is_some() && json.get("rows").is_some() =>
+                {
+                    Ptr::new(self.grid::<T>(json)?)
+                }
+                _ => Ptr::new(HDict::from_map(self.tags::<T>(json)?)),
+            },
+        };
+        Some(val)
+    }
+
+    fn v3_str<'out, T: NumTrait + 'out>(&mut self, s: &str) -> Option<HBox<'out, T>> {
+        let (prefix, rest) = match s.as_bytes() {
+            [p, b':', ..] if p.is_ascii() => (*p as char, &s[2..]),
+            _ => return Some(Ptr::new(HStr(s.to_owned()))),
+        };
+
+        let val = match prefix {
+            'm' => Ptr::new(MARKER) as HBox<'out, T>,
+            '-' => Ptr::new(REMOVE),
+            'z' => Ptr::new(NA),
+            's' => Ptr::new(HStr(rest.to_owned())),
+            'n' => {
+                let (num, unit) = match rest.split_once(' ') {
+                    Some((num, unit)) => (num, Some(unit)),
+                    None => (rest, None),
+                };
+                Ptr::new(number_with_unit::<T>(num, unit)?)
+            }
+            'r' => {
+                let (id, dis) = match rest.split_once(' ') {
+                    Some((id, dis)) => (id, Some(dis.to_owned())),
+                    None => (rest, None),
+                };
+                Ptr::new(HRef::new(id.to_owned(), dis))
+            }
+            'y' => Ptr::new(HSymbol::new(rest.to_owned())),
+            'u' => Ptr::new(HUri::new(rest).ok()?),
+            'd' => Ptr::new(all_consuming(date).parse(rest).ok()?.1),
+            'h' => Ptr::new(all_consuming(time).parse(rest).ok()?.1),
+            't' => Ptr::new(self.datetime(rest)?),
+            'c' => {
+                let (lat, lng) = rest.split_once(',')?;
+                Ptr::new(HCoord::new(lat.parse::<T>().ok()?, lng.parse::<T>().ok()?))
+            }
+            'x' => {
+                let (xtype, xval) = rest.split_once(':')?;
+                Ptr::new(HXStr::new(xtype.to_owned(), xval.to_owned()))
+            }
+            _ => Ptr::new(HStr(s.to_owned())),
+        };
+        Some(val)
+    }
+
+    fn kind<'out, T: NumTrait + 'out>(&mut self, kind: &str, json: &Json) -> Option<HBox<'out, T>> {
+        let val = match kind {
+            "marker" => Ptr::new(MARKER) as HBox<'out, T>,
+            "remove" => Ptr::new(REMOVE),
+            "na" => Ptr::new(NA),
+            "number" => {
+                let num = match json.get("val")? {
+                    Json::Num(n) => n.as_ref(),
+                    Json::Str(s) => s.as_str(),
+                    _ => return None,
+                };
+                Ptr::new(number_with_unit::<T>(num, json.get_str("unit"))?)
+            }
+            "ref" => Ptr::new(HRef::new(
+                json.get_str("val")?.to_owned(),
+                json.get_str("dis").map(|s| s.to_owned()),
+            )),
+            "symbol" => Ptr::new(HSymbol::new(json.get_str("val")?.to_owned())),
+            "uri" => Ptr::new(HUri::new(json.get_str("val")?).ok()?),
+            "date" => Ptr::new(all_consuming(date).parse(json.get_str("val")?).ok()?.1),
+            "time" => Ptr::new(all_consuming(time).parse(json.get_str("val")?).ok()?.1),
+            "dateTime" => {
+                let val = json.get_str("val")?;
+                let dt = match json.get_str("tz") {
+                    Some(tz) => format!("{} {}", val, tz),
+                    None => with_gmt_tz(val)?,
+                };
+                Ptr::new(self.datetime(&dt)?)
+            }
+            "coord" => {
+                let deg = |key| match json.get(key) {
+                    Some(Json::Num(n)) => n.parse::<T>().ok(),
+                    _ => None,
+                };
+                Ptr::new(HCoord::new(deg("lat")?, deg("lng")?))
+            }
+            "xstr" => Ptr::new(HXStr::new(
+                json.get_str("type")?.to_owned(),
+                json.get_str("val")?.to_owned(),
+            )),
+            "dict" => Ptr::new(HDict::from_map(self.tags::<T>(json)?)),
+            "grid" => Ptr::new(self.grid::<T>(json)?),
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn datetime(&mut self, input: &str) -> Option<HDateTime> {
+        all_consuming(zinc::datetime(&mut self.parse_hint))
+            .parse(input)
+            .ok()
+            .map(|(_, dt)| dt)
+    }
+
+    fn tags<'out, T: NumTrait + 'out>(
+        &mut self,
+        json: &Json,
+    ) -> Option<HashMap<String, HBox<'out, T>>> {
+        let Json::Object(entries) = json else {
+            return None;
+        };
+
+        let mut map = HashMap::with_capacity(entries.len());
+        for (k, v) in entries {
+            if self.version == JsonVersion::V4 && k == "_kind" {
+                continue;
+            }
+            map.insert(k.clone(), self.val::<T>(v)?);
+        }
+        Some(map)
+    }
+
+    fn list<'out, T: NumTrait + 'out>(&mut self, items: &[Json]) -> Option<HList<'out, T>> {
+        let vals = items
+            .iter()
+            .map(|v| self.val::<T>(v))
+            .collect::<Option<Vec<_>>>()?;
+        Some(HList::from_vec(vals))
+    }
+
+    fn grid<'out, T: NumTrait + 'out>(&mut self, json: &Json) -> Option<HGrid<'out, T>> {
+        let mut meta = match json.get("meta") {
+            Some(meta) => self.tags::<T>(meta)?,
+            None => HashMap::new(),
+        };
+        // The version is part of the encoding rather than the grid's own metadata
+        meta.remove("ver");
+
+        if meta.contains_key("err") {
+            let dis = meta.get("dis")?.get_string()?.clone_into_string();
+            let err_trace = match meta.get("errTrace") {
+                Some(trace) => Some(trace.get_string()?.clone_into_string()),
+                None => None,
+            };
+            return Some(HGrid::Error {
+                dis,
+                errTrace: err_trace,
+            });
+        }
+
+        let (Some(Json::Array(cols)), Some(Json::Array(rows))) =
+            (json.get("cols"), json.get("rows"))
+        else {
+            return None;
+        };
+
+        let mut columns = Vec::with_capacity(cols.len());
+        for col in cols {
+            let name = col.get_str("name")?.to_owned();
+            if columns.iter().any(|(n, _): &(String, _)| *n == name) {
+                return None;
+            }
+
+            let col_meta = match (self.version, col) {
+                (JsonVersion::V4, _) => match col.get("meta") {
+                    Some(meta) => Some(self.tags::<T>(meta)?),
+                    None => None,
+                },
+                (JsonVersion::V3, Json::Object(entries)) => {
+                    let mut col_meta = HashMap::new();
+                    for (k, v) in entries.iter().filter(|(k, _)| k != "name") {
+                        col_meta.insert(k.clone(), self.val::<T>(v)?);
+                    }
+                    Some(col_meta).filter(|m| !m.is_empty())
+                }
+                _ => return None,
+            };
+            columns.push((name, col_meta));
+        }
+
+        if columns.is_empty() && rows.is_empty() {
+            return Some(HGrid::Empty { meta: Some(meta) });
+        }
+
+        let mut grid_rows = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut tags = self.tags::<T>(row)?;
+            grid_rows.push(
+                columns
+                    .iter()
+                    .map(|(name, _)| tags.remove(name))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        HGrid::from_row_vec(columns, grid_rows).add_meta(meta).ok()
+    }
+}
+
+fn number_with_unit<T: NumTrait>(num: &str, unit: Option<&str>) -> Option<HNumber<T>> {
+    let val = match num {
+        "INF" => T::infinity(),
+        "-INF" => T::neg_infinity(),
+        "NaN" => T::nan(),
+        _ => num.parse::<T>().ok()?,
+    };
+    Some(HNumber::new(val, unit.map(|u| HUnit::new(u.to_owned()))))
+}
+
+/// Hayson allows a `dateTime` without a `tz`, in which case the fixed offset maps onto an `Etc/GMT` zone
+fn with_gmt_tz(val: &str) -> Option<String> {
+    if val.ends_with('Z') {
+        return Some(val.to_owned());
+    }
+
+    let offset = val.get(val.len().checked_sub(6)?..)?;
+    let (sign, hours, minutes) = match offset.as_bytes() {
+        [s @ (b'+' | b'-'), _, _, b':', _, _] => (*s as char, &offset[1..3], &offset[4..6]),
+        _ => return None,
+    };
+    if minutes != "00" {
+        return None;
+    }
+
+    let hours = hours.parse::<u32>().ok()?;
+    let tz = match (hours, sign) {
+        (0, _) => "UTC".to_owned(),
+        // Etc/GMT zones use POSIX signs, so a negative offset is GMT+N
+        (h, '-') => format!("GMT+{}", h),
+        (h, _) => format!("GMT-{}", h),
+    };
+    Some(format!("{} {}", val, tz))
+}
+
+/// Parses a single JSON value, detecting the encoding when `version` is `None`
+pub fn literal<'out, T: NumTrait + 'out>(
+    version: Option<JsonVersion>,
+) -> impl FnMut(&str) -> IResult<&str, HBox<'out, T>> {
+    move |input: &str| {
+        let (rest, json) = document(input)?;
+        let version = version.unwrap_or_else(|| JsonVersion::detect(&json));
+        let val = Decoder::new(version)
+            .val::<T>(&json)
+            .ok_or_else(|| invalid(input))?;
+        Ok((rest, val))
+    }
+}
+
+/// Parses a single JSON value into an owned [`Value`], detecting the encoding when `version` is `None`
+pub fn literal_value<T: NumTrait>(
+    version: Option<JsonVersion>,
+) -> impl FnMut(&str) -> IResult<&str, Value<T>> {
+    move |input: &str| {
+        let (rest, val) = literal::<T>(version)(input)?;
+        Ok((rest, Value::from(val)))
+    }
+}
+
+pub fn dict<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HDict<'out, T>> {
+    let (rest, json) = document(input)?;
+    let mut decoder = Decoder::new(JsonVersion::detect(&json));
+    let tags = decoder.tags::<T>(&json).ok_or_else(|| invalid(input))?;
+    Ok((rest, HDict::from_map(tags)))
+}
+
+pub fn list<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HList<'out, T>> {
+    let (rest, json) = document(input)?;
+    let Json::Array(items) = &json else {
+        return Err(invalid(input));
+    };
+    let mut decoder = Decoder::new(JsonVersion::detect(&json));
+    let list = decoder.list::<T>(items).ok_or_else(|| invalid(input))?;
+    Ok((rest, list))
+}
+
+pub fn grid<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HGrid<'out, T>> {
+    let (rest, json) = document(input)?;
+    let mut decoder = Decoder::new(JsonVersion::detect(&json));
+    let grid = decoder.grid::<T>(&json).ok_or_else(|| invalid(input))?;
+    Ok((rest, grid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::write::ZincWriter;
+    use std::fmt::Write;
+
+    fn to_zinc<'a>(val: &HBox<'a, f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(val.as_ref())).unwrap();
+        buf
+    }
+
+    fn parse(input: &str) -> HBox<'static, f64> {
+        let (rest, val) = literal::<f64>(None)(input).unwrap();
+        assert_eq!(rest, "");
+        val
+    }
+
+    #[test]
+    fn parse_json_string() {
+        assert_eq!(
+            json_str(r#""a\"b\\c\n\u00e9\ud83d\ude00""#),
+            Ok(("", "a\"b\\c\né😀".to_owned()))
+        );
+        assert!(json_str("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn parse_v3_scalars() {
+        assert_eq!(parse("\"m:\"").haystack_type(), crate::HType::Marker);
+        assert_eq!(parse("\"-:\"").haystack_type(), crate::HType::Remove);
+        assert_eq!(parse("\"z:\"").haystack_type(), crate::HType::NA);
+        assert_eq!(parse("null").haystack_type(), crate::HType::Null);
+        assert_eq!(parse("true").get_bool(), Some(&HBool(true)));
+        assert_eq!(
+            parse("\"n:42.5 kW\"").get_number(),
+            Some(&HNumber::new(42.5, Some(HUnit::new("kW".to_owned()))))
+        );
+        assert_eq!(parse("12").get_number(), Some(&HNumber::new(12.0, None)));
+        assert!(parse("\"n:INF\"").get_number().unwrap().val().is_infinite());
+        assert_eq!(
+            parse("\"r:abc Main Site\"").get_ref(),
+            Some(&HRef::new("abc".to_owned(), Some("Main Site".to_owned())))
+        );
+        assert_eq!(
+            parse("\"s:r:not a ref\"").get_string(),
+            Some(&HStr("r:not a ref".to_owned()))
+        );
+        assert_eq!(
+            parse("\"plain\"").get_string(),
+            Some(&HStr("plain".to_owned()))
+        );
+        assert_eq!(to_zinc(&parse("\"d:2024-02-29\"")), "2024-02-29");
+        assert_eq!(to_zinc(&parse("\"h:08:30:00\"")), "08:30:00");
+        assert_eq!(
+            to_zinc(&parse("\"u:http://example.com/\"")),
+            "`http://example.com/`"
+        );
+        assert_eq!(to_zinc(&parse("\"c:37.5,-77.4\"")), "C(37.5,-77.4)");
+        assert_eq!(
+            to_zinc(&parse("\"x:Bin:text/plain\"")),
+            "Bin(\"text/plain\")"
+        );
+        assert_eq!(to_zinc(&parse("\"y:elec-meter\"")), "^elec-meter");
+    }
+
+    #[test]
+    fn parse_v3_datetime() {
+        let val = parse("\"t:2015-06-08T15:47:41-04:00 New_York\"");
+        let dt = val.get_datetime().unwrap();
+        assert_eq!(dt.tz_id(), chrono_tz::America::New_York);
+        assert_eq!(dt.offset().local_minus_utc(), -4 * 3600);
+        assert_eq!(dt.hour(), 15);
+    }
+
+    #[test]
+    fn parse_v4_scalars() {
+        assert_eq!(
+            parse(r#"{"_kind":"marker"}"#).haystack_type(),
+            crate::HType::Marker
+        );
+        assert_eq!(
+            parse(r#"{"_kind":"number","val":42.5,"unit":"kW"}"#).get_number(),
+            Some(&HNumber::new(42.5, Some(HUnit::new("kW".to_owned()))))
+        );
+        assert!(
+            parse(r#"{"_kind":"number","val":"NaN"}"#)
+                .get_number()
+                .unwrap()
+                .val()
+                .is_nan()
+        );
+        assert_eq!(
+            parse(r#"{"_kind":"ref","val":"abc","dis":"Main Site"}"#).get_ref(),
+            Some(&HRef::new("abc".to_owned(), Some("Main Site".to_owned())))
+        );
+        assert_eq!(
+            to_zinc(&parse(r#"{"_kind":"date","val":"2024-02-29"}"#)),
+            "2024-02-29"
+        );
+        assert_eq!(
+            to_zinc(&parse(r#"{"_kind":"coord","lat":37.5,"lng":-77.4}"#)),
+            "C(37.5,-77.4)"
+        );
+        assert_eq!(
+            to_zinc(&parse(
+                r#"{"_kind":"xstr","type":"Bin","val":"text/plain"}"#
+            )),
+            "Bin(\"text/plain\")"
+        );
+
+        let val =
+            parse(r#"{"_kind":"dateTime","val":"2015-06-08T15:47:41-04:00","tz":"New_York"}"#);
+        let dt = val.get_datetime().unwrap();
+        assert_eq!(dt.tz_id(), chrono_tz::America::New_York);
+
+        let val = parse(r#"{"_kind":"dateTime","val":"2015-06-08T15:47:41-04:00"}"#);
+        let dt = val.get_datetime().unwrap();
+        assert_eq!(dt.tz_id(), chrono_tz::Etc::GMTPlus4);
+        assert_eq!(dt.offset().local_minus_utc(), -4 * 3600);
+
+        // Plain strings are never type-prefixed in Hayson
+        let val = literal::<f64>(Some(JsonVersion::V4))("\"n:42\"").unwrap().1;
+        assert_eq!(val.get_string(), Some(&HStr("n:42".to_owned())));
+        assert!(literal::<f64>(None)(r#"{"_kind":"unknown"}"#).is_err());
+    }
+
+    #[test]
+    fn parse_collections() {
+        let (_, d) =
+            dict::<f64>(r#"{"site":"m:","area":"n:1000 ft²","tags":["s:a","n:1"]}"#).unwrap();
+        assert_eq!(d.len(), 3);
+        assert!(d.get("site").unwrap().get_marker().is_some());
+        assert_eq!(d.get("tags").unwrap().get_list().unwrap().len(), 2);
+
+        let (_, d) =
+            dict::<f64>(r#"{"_kind":"dict","site":{"_kind":"marker"},"dis":"n:1"}"#).unwrap();
+        assert_eq!(d.len(), 2);
+        assert_eq!(
+            d.get("dis").unwrap().get_string(),
+            Some(&HStr("n:1".to_owned()))
+        );
+
+        let (_, l) = list::<f64>(r#"[1, "n:2", null]"#).unwrap();
+        assert_eq!(l.len(), 3);
+        assert!(list::<f64>("{}").is_err());
+    }
+
+    fn grid_zinc(grid: &HGrid<f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(grid)).unwrap();
+        buf
+    }
+
+    #[test]
+    fn parse_v3_grid_matches_zinc() {
+        let json = r#"{
+            "meta": {"ver":"3.0", "view":"s:chart"},
+            "cols": [{"name":"id"}, {"name":"dis", "dis":"s:Display"}, {"name":"area"}],
+            "rows": [
+                {"id":"r:a Alpha", "dis":"s:Alpha", "area":"n:1200 ft²"},
+                {"id":"r:b", "dis":"s:Beta"}
+            ]
+        }"#;
+        let zinc = "ver:\"3.0\" view:\"chart\"\nid,dis dis:\"Display\",area\n@a \"Alpha\",\"Alpha\",1200ft²\n@b,\"Beta\",";
+        let (_, from_json) = grid::<f64>(json).unwrap();
+        let (_, from_zinc) = zinc::grid::<f64>(zinc).unwrap();
+        assert_eq!(grid_zinc(&from_json), grid_zinc(&from_zinc));
+        assert_eq!(from_json.len(), 2);
+        assert!(from_json.get(1).unwrap().get("area").is_none());
+    }
+
+    #[test]
+    fn parse_v4_grid_matches_zinc() {
+        let json = r#"{
+            "_kind": "grid",
+            "meta": {"ver":"3.0"},
+            "cols": [{"name":"id"}, {"name":"curVal", "meta":{"unit":"kW"}}],
+            "rows": [
+                {"id":{"_kind":"ref","val":"a"}, "curVal":{"_kind":"number","val":12,"unit":"kW"}}
+            ]
+        }"#;
+        let zinc = "ver:\"3.0\"\nid,curVal unit:\"kW\"\n@a,12kW";
+        let (_, from_json) = grid::<f64>(json).unwrap();
+        let (_, from_zinc) = zinc::grid::<f64>(zinc).unwrap();
+        assert_eq!(grid_zinc(&from_json), grid_zinc(&from_zinc));
+    }
+
+    #[test]
+    fn parse_error_and_empty_grids() {
+        let json = r#"{"meta":{"ver":"3.0","err":"m:","dis":"s:Not found"},"cols":[{"name":"empty"}],"rows":[]}"#;
+        match grid::<f64>(json).unwrap().1 {
+            HGrid::Error { dis, errTrace } => {
+                assert_eq!(dis, "Not found");
+                assert_eq!(errTrace, None);
+            }
+            _ => panic!("Expected error grid"),
+        }
+
+        let json = r#"{"_kind":"grid","meta":{"ver":"3.0"},"cols":[],"rows":[]}"#;
+        assert!(matches!(grid::<f64>(json).unwrap().1, HGrid::Empty { .. }));
+
+        let json = r#"{"meta":{},"cols":[{"name":"a"},{"name":"a"}],"rows":[]}"#;
+        assert!(grid::<f64>(json).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use super::*;
+use nom::combinator::all_consuming;
+
+use crate::h_grid::HCol;
+use crate::h_marker::MARKER;
+use crate::h_val::HVal;
+
+fn invalid(input: &str) -> nom::Err<Error<&str>> {
+    nom::Err::Error(Error {
+        input,
+        code: ErrorKind::Verify,
+    })
+}
+
+fn is_separator(line: &str) -> bool {
+    line.len() >= 3 && line.chars().all(|c| c == '-')
+}
+
+fn is_indented(line: &str) -> bool {
+    line.starts_with(' ') || line.starts_with('\t')
+}
+
+fn unindent(line: &str) -> &str {
+    line.strip_prefix("  ")
+        .or_else(|| line.strip_prefix('\t'))
+        .unwrap_or(line.trim_start())
+}
+
+/// Splits the input into lines, accepting both `\n` and `\r\n` endings
+fn lines(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+}
+
+/// Parses a tag value, falling back to a bare string when it isn't a zinc scalar
+fn scalar<'out, T: NumTrait + 'out>(input: &str, parse_hint: &mut ParseHint) -> HBox<'out, T> {
+    match all_consuming(zinc::literal::<T>(parse_hint)).parse(input) {
+        Ok((_, val)) => val,
+        Err(_) => Ptr::new(HStr(input.to_owned())),
+    }
+}
+
+fn nested_grid<'out, T: NumTrait + 'out>(input: &str) -> Option<HGrid<'out, T>> {
+    let (rest, grid) = zinc::grid::<T>(input).ok()?;
+    rest.trim().is_empty().then_some(grid)
+}
+
+/// Reads the record starting at the first line of `input`, returning the tags in file order
+fn tags<'i, 'out, T: NumTrait + 'out>(
+    input: &'i str,
+    parse_hint: &mut ParseHint,
+) -> IResult<&'i str, Vec<(String, HBox<'out, T>)>> {
+    let mut tags: Vec<(String, HBox<'out, T>)> = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (line, next) = match rest.find('\n') {
+            Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+            None => (rest, ""),
+        };
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if is_separator(line) {
+            rest = next;
+            break;
+        }
+        if line.trim().is_empty() || line.starts_with("//") {
+            rest = next;
+            continue;
+        }
+
+        let (after_name, name) = id(line)?;
+        if tags.iter().any(|(n, _)| n == name) {
+            return Err(invalid(line));
+        }
+
+        let val = match after_name.strip_prefix(':') {
+            None if after_name.trim().is_empty() => MARKER.to_hbox(),
+            None => return Err(invalid(after_name)),
+            Some(val) => {
+                let val = val.trim();
+                if val.is_empty() || val == "Zinc:" {
+                    // Indented lines following the tag make up its value
+                    let block_len = next
+                        .split('\n')
+                        .take_while(|l| is_indented(l))
+                        .map(|l| l.len() + 1)
+                        .sum::<usize>();
+                    let (block, after) = next.split_at(block_len.min(next.len()));
+                    let text = lines(block.trim_end_matches('\n'))
+                        .map(unindent)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    rest = after;
+
+                    if val == "Zinc:" {
+                        let grid = nested_grid::<T>(&format!("{}\n", text))
+                            .ok_or_else(|| invalid(line))?;
+                        tags.push((name.to_owned(), Ptr::new(grid)));
+                    } else {
+                        tags.push((name.to_owned(), Ptr::new(HStr(text))));
+                    }
+                    continue;
+                }
+                scalar::<T>(val, parse_hint)
+            }
+        };
+
+        tags.push((name.to_owned(), val));
+        rest = next;
+    }
+
+    Ok((rest, tags))
+}
+
+/// Parses a single `---` terminated record
+pub fn dict<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HDict<'out, T>> {
+    let mut parse_hint = ParseHint::default();
+    let (rest, tags) = tags::<T>(input, &mut parse_hint)?;
+    Ok((rest, HDict::from_map(tags.into_iter().collect())))
+}
+
+/// Parses every record in the input into the rows of a grid, columns follow first appearance
+pub fn grid<'out, T: NumTrait + 'out>(input: &str) -> IResult<&str, HGrid<'out, T>> {
+    let mut parse_hint = ParseHint::default();
+    let mut cols: Vec<HCol<'out, T>> = Vec::new();
+    let mut rows = Vec::new();
+    let mut rest = input;
+
+    while !rest.trim().is_empty() {
+        let (next, tags) = tags::<T>(rest, &mut parse_hint)?;
+        rest = next;
+        if tags.is_empty() {
+            continue;
+        }
+
+        for (name, _) in tags.iter() {
+            if !cols.iter().any(|c| &c.name == name) {
+                cols.push(HCol::new(name.clone(), None));
+            }
+        }
+        rows.push(tags.into_iter().collect::<HashMap<_, _>>());
+    }
+
+    Ok(("", HGrid::new(Some(cols), rows)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_ref::HRef;
+    use crate::io::write::{TrioWriter, ZincWriter};
+    use std::fmt::Write;
+
+    const SITES: &str = "// Demo sites
+dis: \"Site A\"
+site
+area: 1000ft²
+geoCity: Richmond
+---
+dis: Site B
+site
+siteRef: @s1 \"Site A\"
+tz: New_York
+doc:
+  First line
+  Second line
+-----
+";
+
+    fn zinc(val: &HBox<f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(val.as_ref())).unwrap();
+        buf
+    }
+
+    #[test]
+    fn parse_records() {
+        let (rest, grid) = grid::<f64>(SITES).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(grid.len(), 2);
+
+        let names: Vec<_> = grid.iter_cols().map(|c| c.name.clone()).collect();
+        assert_eq!(
+            names,
+            ["dis", "site", "area", "geoCity", "siteRef", "tz", "doc"]
+        );
+
+        let site_a = grid.get(0).unwrap();
+        assert_eq!(zinc(&site_a.get("dis").unwrap()), "\"Site A\"");
+        assert_eq!(zinc(&site_a.get("site").unwrap()), "M");
+        assert_eq!(zinc(&site_a.get("area").unwrap()), "1000ft²");
+        assert_eq!(zinc(&site_a.get("geoCity").unwrap()), "\"Richmond\"");
+        assert!(site_a.get("doc").is_none());
+
+        let site_b = grid.get(1).unwrap();
+        assert_eq!(zinc(&site_b.get("dis").unwrap()), "\"Site B\"");
+        assert_eq!(
+            site_b.get("doc").unwrap().get_string(),
+            Some(&HStr("First line\nSecond line".to_owned()))
+        );
+    }
+
+    #[test]
+    fn parse_nested_values() {
+        let input = "id: @p1 \"Point 1\"\n\
+                     list: [1, 2, \"three\"]\n\
+                     tags: {point his}\n\
+                     empty:\n\
+                     ts: 2024-01-01T00:00:00Z UTC\n\
+                     table: Zinc:\n  ver:\"3.0\"\n  a,b\n  1,2\n  3,4\n";
+        let (_, dict) = dict::<f64>(input).unwrap();
+
+        assert_eq!(
+            dict.get("id").unwrap().get_ref(),
+            Some(&HRef::new("p1".to_owned(), Some("Point 1".to_owned())))
+        );
+        assert_eq!(dict.get("list").unwrap().get_list().unwrap().len(), 3);
+        assert!(dict.get("tags").unwrap().get_dict().is_some());
+        assert!(dict.get("ts").unwrap().get_datetime().is_some());
+        assert_eq!(
+            dict.get("empty").unwrap().get_string(),
+            Some(&HStr(String::new()))
+        );
+
+        let table = dict.get("table").unwrap().get_grid().unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(zinc(&table.get(1).unwrap().get("b").unwrap()), "4");
+    }
+
+    #[test]
+    fn parse_dict_stops_at_separator() {
+        let (rest, dict) = dict::<f64>("a\n---\nb\n").unwrap();
+        assert_eq!(rest, "b\n");
+        assert!(dict.has("a"));
+        assert!(!dict.has("b"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(grid::<f64>("Bad: 1\n").is_err());
+        assert!(grid::<f64>("a: 1\na: 2\n").is_err());
+        assert!(grid::<f64>("a b\n").is_err());
+        assert!(grid::<f64>("t: Zinc:\n  not zinc\n").is_err());
+    }
+
+    #[test]
+    fn round_trip_writer() {
+        let (_, grid) = grid::<f64>(SITES).unwrap();
+        let mut buf = String::new();
+        write!(buf, "{}", TrioWriter::new(&grid)).unwrap();
+
+        let (_, parsed) = super::grid::<f64>(&buf).unwrap();
+        assert_eq!(parsed.len(), grid.len());
+        for (expected, actual) in grid.iter().zip(parsed.iter()) {
+            let (expected, actual) = (expected.to_dict(), actual.to_dict());
+            assert_eq!(expected.len(), actual.len());
+            for (k, v) in expected.iter() {
+                assert_eq!(zinc(v), zinc(actual.get(k).unwrap()));
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use super::*;
+use nom::character::complete::space0;
+use nom::combinator::all_consuming;
+use nom::sequence::delimited;
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+use super::zinc::{cols, grid_meta, literal, row_err};
+use crate::HType;
+use crate::err::ZincParseErr;
+use crate::h_grid::HCol;
+
+#[derive(Debug)]
+pub enum ZincStreamErr {
+    Io(io::Error),
+    Parse(ZincParseErr),
+    ErrGrid {
+        dis: String,
+        err_trace: Option<String>,
+    },
+}
+
+impl fmt::Display for ZincStreamErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZincStreamErr::Io(e) => write!(f, "Error: Failed to read grid: {}", e),
+            ZincStreamErr::Parse(e) => e.fmt(f),
+            ZincStreamErr::ErrGrid { dis, .. } => write!(f, "Error: Error grid: {}", dis),
+        }
+    }
+}
+
+impl error::Error for ZincStreamErr {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ZincStreamErr::Io(e) => Some(e),
+            ZincStreamErr::Parse(e) => Some(e),
+            ZincStreamErr::ErrGrid { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ZincStreamErr {
+    fn from(e: io::Error) -> Self {
+        ZincStreamErr::Io(e)
+    }
+}
+
+const VERSION: &str = "the grid version and meta, ie. ver:\"3.0\"";
+const COLS: &str = "comma separated column names";
+
+/// Net number of `<<` nested grids opened by a line, ignoring the contents of strings and uris
+fn grid_depth(line: &str) -> isize {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"') | (None, '`') => quote = Some(c),
+            (None, '<') if chars.peek() == Some(&'<') => {
+                chars.next();
+                depth += 1;
+            }
+            (None, '>') if chars.peek() == Some(&'>') => {
+                chars.next();
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    depth
+}
+
+/// Pull based zinc grid reader, parses the header up front then reads one row per `next`
+///
+/// Only the current row is held in memory, so grids of any size can be read from a socket or file
+pub struct ZincStreamReader<'a, R, T: NumTrait + 'a> {
+    reader: R,
+    meta: HDict<'a, T>,
+    cols: Vec<HCol<'a, T>>,
+    line: usize,
+    buf: String,
+    parse_hint: ParseHint,
+    done: bool,
+}
+
+impl<'a, R: Read, T: NumTrait + 'a> ZincStreamReader<'a, BufReader<R>, T> {
+    pub fn from_read(reader: R) -> Result<Self, ZincStreamErr> {
+        Self::new(BufReader::new(reader))
+    }
+}
+
+impl<'a, R: BufRead, T: NumTrait + 'a> ZincStreamReader<'a, R, T> {
+    /// Reads the version, grid meta and columns, error grids are returned as `ZincStreamErr::ErrGrid`
+    pub fn new(reader: R) -> Result<Self, ZincStreamErr> {
+        let mut stream = Self {
+            reader,
+            meta: HDict::new(),
+            cols: Vec::new(),
+            line: 0,
+            buf: String::new(),
+            parse_hint: ParseHint::default(),
+            done: false,
+        };
+
+        if !stream.read_line()? {
+            return Err(stream.parse_err("", VERSION));
+        }
+        let (_, meta) = all_consuming(delimited(
+            (tag("ver:\""), recognize(double), tag("\"")),
+            opt(preceded(space1, grid_meta::<T>)),
+            space0,
+        ))
+        .parse(stream.buf.as_str())
+        .map_err(|e| stream.nom_err(e, VERSION))?;
+        stream.meta = HDict::from_map(meta.unwrap_or_default());
+
+        if stream.meta.has("err") {
+            let text = |key| {
+                stream
+                    .meta
+                    .get(key)
+                    .and_then(|v| v.get_string())
+                    .map(|s| s.clone_into_string())
+            };
+            return Err(ZincStreamErr::ErrGrid {
+                dis: text("dis").unwrap_or_default(),
+                err_trace: text("errTrace"),
+            });
+        }
+
+        if !stream.read_line()? {
+            return Err(stream.parse_err("", COLS));
+        }
+        if stream.buf == "empty" {
+            stream.done = true;
+            return Ok(stream);
+        }
+        let (_, cols) = all_consuming(terminated(cols::<T>, space0))
+            .parse(stream.buf.as_str())
+            .map_err(|e| stream.nom_err(e, COLS))?;
+        stream.cols = cols
+            .into_iter()
+            .map(|(name, meta)| HCol::new(name, meta))
+            .collect();
+
+        Ok(stream)
+    }
+
+    pub fn meta(&self) -> &HDict<'a, T> {
+        &self.meta
+    }
+
+    pub fn cols(&self) -> &[HCol<'a, T>] {
+        &self.cols
+    }
+
+    /// Line number of the last line read, starting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Line number of the first line in the buffer
+    fn buf_line(&self) -> usize {
+        self.line - self.buf.matches('\n').count()
+    }
+
+    /// `pos` must be a slice of the buffer, the end of the buffer when it is empty
+    fn parse_err(&self, pos: &str, expected: &str) -> ZincStreamErr {
+        let pos = match pos.is_empty() {
+            true => &self.buf[self.buf.len()..],
+            false => pos,
+        };
+        let err = ZincParseErr::new(&self.buf, pos, expected);
+        ZincStreamErr::Parse(err.offset_lines(self.buf_line().saturating_sub(1)))
+    }
+
+    fn nom_err(&self, err: nom::Err<Error<&str>>, expected: &str) -> ZincStreamErr {
+        match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => self.parse_err(e.input, expected),
+            nom::Err::Incomplete(_) => self.parse_err("", expected),
+        }
+    }
+
+    /// Replaces the buffer with the next line, without its line ending
+    fn read_line(&mut self) -> Result<bool, ZincStreamErr> {
+        self.buf.clear();
+        self.append_line()
+    }
+
+    fn append_line(&mut self) -> Result<bool, ZincStreamErr> {
+        let start = self.buf.len();
+        if self.reader.read_line(&mut self.buf)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+
+        let trimmed = self.buf[start..].trim_end_matches(['\n', '\r']).len();
+        self.buf.truncate(start + trimmed);
+        Ok(true)
+    }
+
+    /// Reads the next row, `None` once the grid ends at a blank line or the end of input
+    pub fn next_row(&mut self) -> Result<Option<HDict<'a, T>>, ZincStreamErr> {
+        if self.done {
+            return Ok(None);
+        }
+        if !self.read_line()? || self.buf.is_empty() {
+            self.done = true;
+            return Ok(None);
+        }
+
+        // Nested grids carry on over the following lines
+        let mut depth = grid_depth(&self.buf);
+        while depth > 0 {
+            let start = self.buf.len() + 1;
+            self.buf.push('\n');
+            if !self.append_line()? {
+                return Err(self.parse_err("", "'>>' to close the nested grid"));
+            }
+            depth += grid_depth(&self.buf[start..]);
+        }
+
+        let width = self.cols.len();
+        let cells = all_consuming(separated_list1(
+            tag(","),
+            opt(literal::<T>(&mut self.parse_hint)),
+        ))
+        .parse(self.buf.as_str())
+        .map(|(_, cells)| cells)
+        .ok()
+        .filter(|cells| cells.len() == width);
+
+        let Some(cells) = cells else {
+            let err = row_err::<T>(&self.buf, &self.buf, width);
+            return Err(ZincStreamErr::Parse(
+                err.offset_lines(self.buf_line().saturating_sub(1)),
+            ));
+        };
+
+        let mut row = HDict::new();
+        for (col, cell) in self.cols.iter().zip(cells) {
+            if let Some(v) = cell
+                && v.haystack_type() != HType::Null
+            {
+                row.set(col.name.clone(), v);
+            }
+        }
+        Ok(Some(row))
+    }
+}
+
+impl<'a, R: BufRead, T: NumTrait + 'a> Iterator for ZincStreamReader<'a, R, T> {
+    type Item = Result<HDict<'a, T>, ZincStreamErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.next_row();
+        if res.is_err() {
+            self.done = true;
+        }
+        res.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::write::ZincWriter;
+    use std::fmt::Write;
+
+    const HIS: &str = "ver:\"3.0\" hisStart:2024-01-01T00:00:00Z UTC\n\
+                       ts,val unit:\"kW\"\n\
+                       2024-01-01T00:00:00Z UTC,1kW\n\
+                       2024-01-01T00:01:00Z UTC,\n\
+                       2024-01-01T00:02:00Z UTC,3kW\n";
+
+    fn zinc(val: &HBox<f64>) -> String {
+        let mut buf = String::new();
+        write!(buf, "{}", ZincWriter::new(val.as_ref())).unwrap();
+        buf
+    }
+
+    #[test]
+    fn stream_rows() {
+        let mut reader = ZincStreamReader::<_, f64>::new(HIS.as_bytes()).unwrap();
+        assert!(reader.meta().has("hisStart"));
+        let names: Vec<_> = reader.cols().iter().map(|c| c.name.clone()).collect();
+        assert_eq!(names, ["ts", "val"]);
+        assert!(reader.cols()[1].has("unit"));
+
+        let first = reader.next_row().unwrap().unwrap();
+        assert_eq!(zinc(first.get("val").unwrap()), "1kW");
+        assert_eq!(reader.line(), 3);
+
+        let rest: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(rest.len(), 2);
+        assert!(!rest[0].has("val"));
+        assert_eq!(zinc(rest[1].get("val").unwrap()), "3kW");
+    }
+
+    #[test]
+    fn stream_from_read() {
+        let rows = ZincStreamReader::<_, f64>::from_read(HIS.as_bytes())
+            .unwrap()
+            .count();
+        assert_eq!(rows, 3);
+    }
+
+    #[test]
+    fn stream_ends_at_blank_line() {
+        let input = "ver:\"3.0\" \r\na\r\n1\r\n\r\n2\r\n";
+        let rows: Vec<_> = ZincStreamReader::<_, f64>::new(input.as_bytes())
+            .unwrap()
+            .collect();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn stream_nested_grid() {
+        let input = "ver:\"3.0\"\n\
+                     id,data\n\
+                     @a,<<ver:\"3.0\"\nx,y\n1,\"<<\"\n2,3\n>>\n\
+                     @b,\"end >>\"\n";
+        let rows: Vec<_> = ZincStreamReader::<_, f64>::new(input.as_bytes())
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        let nested = rows[0].get("data").unwrap().get_grid().unwrap();
+        assert_eq!(nested.len(), 2);
+        assert_eq!(zinc(rows[1].get("data").unwrap()), "\"end >>\"");
+    }
+
+    #[test]
+    fn stream_empty_and_err_grids() {
+        let mut reader =
+            ZincStreamReader::<_, f64>::new("ver:\"3.0\"\nempty\n".as_bytes()).unwrap();
+        assert!(reader.cols().is_empty());
+        assert!(reader.next().is_none());
+
+        let input = "ver:\"3.0\" err dis:\"Not found\"\nempty\n";
+        match ZincStreamReader::<_, f64>::new(input.as_bytes()) {
+            Err(ZincStreamErr::ErrGrid { dis, err_trace }) => {
+                assert_eq!(dis, "Not found");
+                assert_eq!(err_trace, None);
+            }
+            _ => panic!("Expected an error grid"),
+        }
+    }
+
+    #[test]
+    fn stream_errors() {
+        let input = "ver:\"3.0\"\na,b\n1,2\n1,2,3\n4,5\n";
+        let mut reader = ZincStreamReader::<_, f64>::new(input.as_bytes()).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next() {
+            Some(Err(ZincStreamErr::Parse(e))) => {
+                assert_eq!((e.line, e.col), (4, 1));
+                assert_eq!(e.expected, "a row of 2 cells but found 3");
+            }
+            _ => panic!("Expected a parse error"),
+        }
+        assert!(reader.next().is_none());
+
+        let input = "ver:\"3.0\"\na,b\n1,\"open\n";
+        match ZincStreamReader::<_, f64>::new(input.as_bytes())
+            .unwrap()
+            .next()
+        {
+            Some(Err(ZincStreamErr::Parse(e))) => assert_eq!((e.line, e.col), (3, 3)),
+            _ => panic!("Expected a parse error"),
+        }
+
+        assert!(ZincStreamReader::<_, f64>::new("".as_bytes()).is_err());
+        assert!(ZincStreamReader::<_, f64>::new("ver:3\na\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,579 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use nom::Parser;
+use nom::combinator::all_consuming;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::h_val::{HBox, HType, HVal, Ptr};
+use crate::io::JsonVersion;
+use crate::io::parse::json::{Decoder, Json, document};
+use crate::io::write::{HaysonWriter, ZincWriter};
+use crate::{
+    h_bool::HBool,
+    h_coord::HCoord,
+    h_date::HDate,
+    h_datetime::HDateTime,
+    h_dict::HDict,
+    h_grid::HGrid,
+    h_list::HList,
+    h_marker::HMarker,
+    h_na::HNA,
+    h_null::HNull,
+    h_number::{HNumber, NumTrait},
+    h_ref::HRef,
+    h_remove::HRemove,
+    h_str::HStr,
+    h_symbol::HSymbol,
+    h_time::HTime,
+    h_uri::HUri,
+    h_xstr::HXStr,
+};
+
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Json::Null => serializer.serialize_unit(),
+            Json::Bool(b) => serializer.serialize_bool(*b),
+            Json::Num(n) => {
+                if let Ok(i) = n.parse::<i64>() {
+                    serializer.serialize_i64(i)
+                } else if let Ok(u) = n.parse::<u64>() {
+                    serializer.serialize_u64(u)
+                } else {
+                    let f = n.parse::<f64>().map_err(ser::Error::custom)?;
+                    serializer.serialize_f64(f)
+                }
+            }
+            Json::Str(s) => serializer.serialize_str(s),
+            Json::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Json::Object(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = Json<'static>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a hayson value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Json::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Json::Num(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Json::Num(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Json::Num(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Json::Str(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Json::Str(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element_seed(JsonSeed)? {
+            items.push(item);
+        }
+        Ok(Json::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key::<String>()? {
+            entries.push((key, map.next_value_seed(JsonSeed)?));
+        }
+        Ok(Json::Object(entries))
+    }
+}
+
+struct JsonSeed;
+
+impl<'de> de::DeserializeSeed<'de> for JsonSeed {
+    type Value = Json<'static>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+/// Writes a scalar as a `_kind` object, leaving out missing fields
+fn scalar<S: Serializer>(
+    serializer: S,
+    kind: &str,
+    fields: &[(&str, Option<&str>)],
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry("_kind", kind)?;
+    for (k, v) in fields {
+        if let Some(v) = v {
+            map.serialize_entry(k, v)?;
+        }
+    }
+    map.end()
+}
+
+/// Numbers go through their text as the Hayson writer gives it, so whole numbers stay integers
+fn number<T: NumTrait>(val: T) -> Json<'static> {
+    Json::Num(Cow::Owned(val.to_string()))
+}
+
+/// A value in the Hayson structure, written straight to the serializer
+struct Hayson<'v, 'a, T: NumTrait + 'a>(&'v (dyn HVal<'a, T> + 'a));
+
+/// The tags of a dict, null tags are left out as in Hayson
+struct Tags<'v, 'a, T: NumTrait + 'a>(&'v HDict<'a, T>);
+
+/// Grid meta, led by the version
+struct GridMeta<'v, 'a, T: NumTrait + 'a>(&'v HDict<'a, T>);
+
+struct GridCol<'v, 'a, T: NumTrait + 'a>(&'v str, Option<HDict<'a, T>>);
+
+struct GridCols<'v, 'a, T: NumTrait + 'a>(&'v HGrid<'a, T>);
+
+struct GridRows<'v, 'a, T: NumTrait + 'a>(&'v HGrid<'a, T>);
+
+impl<'a, T: NumTrait + 'a> Serialize for Hayson<'_, 'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let val = self.0;
+        match val.haystack_type() {
+            HType::Null => serializer.serialize_unit(),
+            HType::Marker => scalar(serializer, "marker", &[]),
+            HType::Remove => scalar(serializer, "remove", &[]),
+            HType::NA => scalar(serializer, "na", &[]),
+            HType::Bool => serializer.serialize_bool(val.get_bool().unwrap().0),
+            HType::Str => serializer.serialize_str(val.get_string().unwrap().as_str()),
+            HType::Number => {
+                let num = val.get_number().unwrap();
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("_kind", "number")?;
+                match num.val() {
+                    v if v.is_finite() => map.serialize_entry("val", &number(v))?,
+                    v if v.is_nan() => map.serialize_entry("val", "NaN")?,
+                    v if v > T::zero() => map.serialize_entry("val", "INF")?,
+                    _ => map.serialize_entry("val", "-INF")?,
+                }
+                if let Some(unit) = num.unit() {
+                    map.serialize_entry("unit", unit.as_str())?;
+                }
+                map.end()
+            }
+            HType::Ref => {
+                let r = val.get_ref().unwrap();
+                scalar(
+                    serializer,
+                    "ref",
+                    &[("val", Some(&r.id)), ("dis", r.dis.as_deref())],
+                )
+            }
+            HType::Symbol => {
+                let symbol = val.get_symbol().unwrap().as_str();
+                scalar(serializer, "symbol", &[("val", Some(symbol))])
+            }
+            HType::Uri => {
+                let uri = val.get_uri().unwrap().to_owned_string();
+                scalar(serializer, "uri", &[("val", Some(&uri))])
+            }
+            HType::Date => {
+                let date = val.get_date().unwrap().val().format("%Y-%m-%d").to_string();
+                scalar(serializer, "date", &[("val", Some(&date))])
+            }
+            HType::Time => {
+                let time = ZincWriter::new(val.get_time().unwrap()).to_string();
+                scalar(serializer, "time", &[("val", Some(&time))])
+            }
+            HType::DateTime => {
+                let dt = val.get_datetime().unwrap();
+                let tz = crate::tz::name(dt.tz_id());
+                scalar(
+                    serializer,
+                    "dateTime",
+                    &[("val", Some(&dt.iso())), ("tz", Some(tz))],
+                )
+            }
+            HType::Coord => {
+                let coord = val.get_coord().unwrap();
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("_kind", "coord")?;
+                map.serialize_entry("lat", &number(coord.lat()))?;
+                map.serialize_entry("lng", &number(coord.long()))?;
+                map.end()
+            }
+            HType::XStr => {
+                let xstr = val.get_xstr().unwrap();
+                scalar(
+                    serializer,
+                    "xstr",
+                    &[("type", Some(xstr.xtype())), ("val", Some(xstr.xval()))],
+                )
+            }
+            HType::List => {
+                let list = val.get_list().unwrap();
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for item in list.iter() {
+                    seq.serialize_element(&Hayson(item.as_ref()))?;
+                }
+                seq.end()
+            }
+            HType::Dict => Tags(val.get_dict().unwrap()).serialize(serializer),
+            HType::Grid => {
+                let grid = val.get_grid().unwrap();
+                let meta = match grid {
+                    HGrid::Grid { .. } => grid.meta(),
+                    HGrid::Error { dis, errTrace } => {
+                        let mut meta = HDict::new();
+                        meta.set("err".to_owned(), Ptr::new(HMarker));
+                        meta.set("dis".to_owned(), Ptr::new(HStr(dis.clone())));
+                        if let Some(trace) = errTrace {
+                            meta.set("errTrace".to_owned(), Ptr::new(HStr(trace.clone())));
+                        }
+                        meta
+                    }
+                    HGrid::Empty { meta } => HDict::from_map(meta.clone().unwrap_or_default()),
+                };
+                let mut map = serializer.serialize_map(Some(4))?;
+                map.serialize_entry("_kind", "grid")?;
+                map.serialize_entry("meta", &GridMeta(&meta))?;
+                map.serialize_entry("cols", &GridCols(grid))?;
+                map.serialize_entry("rows", &GridRows(grid))?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Serialize for Tags<'_, 'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (k, v) in self.0.iter().filter(|(_, v)| v.get_null().is_none()) {
+            map.serialize_entry(k, &Hayson(v.as_ref()))?;
+        }
+        map.end()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Serialize for GridMeta<'_, 'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("ver", "3.0")?;
+        for (k, v) in self
+            .0
+            .iter()
+            .filter(|(k, v)| *k != "ver" && v.get_null().is_none())
+        {
+            map.serialize_entry(k, &Hayson(v.as_ref()))?;
+        }
+        map.end()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Serialize for GridCol<'_, 'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", self.0)?;
+        if let Some(meta) = self.1.as_ref().filter(|meta| !meta.is_empty()) {
+            map.serialize_entry("meta", &Tags(meta))?;
+        }
+        map.end()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Serialize for GridCols<'_, 'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        match self.0 {
+            HGrid::Grid { .. } => {
+                for col in self.0.iter_cols() {
+                    seq.serialize_element(&GridCol(&col.name, Some(col.meta())))?;
+                }
+            }
+            HGrid::Error { .. } => seq.serialize_element(&GridCol::<T>("empty", None))?,
+            HGrid::Empty { .. } => (),
+        }
+        seq.end()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> Serialize for GridRows<'_, 'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        if let HGrid::Grid { .. } = self.0 {
+            for row in self.0.iter() {
+                seq.serialize_element(&Tags(&row.to_dict()))?;
+            }
+        }
+        seq.end()
+    }
+}
+
+/// Human readable formats get the Hayson structure, compact formats such as bincode can't
+/// describe it so they get the Hayson document as a string
+fn serialize_hayson<'a, T, S>(
+    val: &(dyn HVal<'a, T> + 'a),
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: NumTrait + 'a,
+    S: Serializer,
+{
+    if !serializer.is_human_readable() {
+        return serializer.serialize_str(&HaysonWriter::new(val).to_string());
+    }
+    Hayson(val).serialize(serializer)
+}
+
+fn deserialize_hayson<'de, 'a, T, D>(deserializer: D) -> Result<HBox<'a, T>, D::Error>
+where
+    T: NumTrait + 'a,
+    D: Deserializer<'de>,
+{
+    let val = if deserializer.is_human_readable() {
+        let json = deserializer.deserialize_any(JsonVisitor)?;
+        Decoder::new(JsonVersion::V4).val::<T>(&json)
+    } else {
+        let hayson = String::deserialize(deserializer)?;
+        all_consuming(document)
+            .parse(&hayson)
+            .ok()
+            .and_then(|(_, json)| Decoder::new(JsonVersion::V4).val::<T>(&json))
+    };
+    val.ok_or_else(|| de::Error::custom("Error: Invalid hayson"))
+}
+
+fn expected<E: de::Error>(h_type: HType) -> E {
+    E::custom(format!("Error: Expected {} in hayson", h_type))
+}
+
+impl<'a, T: NumTrait + 'a> Serialize for dyn HVal<'a, T> + 'a {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_hayson(self, serializer)
+    }
+}
+
+macro_rules! impl_serde {
+    ($h_type:ty, $get_method:ident, $expected:expr) => {
+        impl Serialize for $h_type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize_hayson::<f64, S>(self, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $h_type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_hayson::<f64, D>(deserializer)?
+                    .$get_method()
+                    .cloned()
+                    .ok_or_else(|| expected($expected))
+            }
+        }
+    };
+    ($h_type:ty, $get_method:ident, $expected:expr, $num_trait:ident) => {
+        impl<'a, T: $num_trait + 'a> Serialize for $h_type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize_hayson::<T, S>(self, serializer)
+            }
+        }
+
+        impl<'de, 'a, T: $num_trait + 'a> Deserialize<'de> for $h_type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_hayson::<T, D>(deserializer)?
+                    .$get_method()
+                    .cloned()
+                    .ok_or_else(|| expected($expected))
+            }
+        }
+    };
+}
+
+impl_serde!(HNull, get_null, HType::Null);
+impl_serde!(HMarker, get_marker, HType::Marker);
+impl_serde!(HRemove, get_remove, HType::Remove);
+impl_serde!(HNA, get_na, HType::NA);
+impl_serde!(HBool, get_bool, HType::Bool);
+impl_serde!(HStr, get_string, HType::Str);
+impl_serde!(HXStr, get_xstr, HType::XStr);
+impl_serde!(HUri, get_uri, HType::Uri);
+impl_serde!(HRef, get_ref, HType::Ref);
+impl_serde!(HSymbol, get_symbol, HType::Symbol);
+impl_serde!(HDate, get_date, HType::Date);
+impl_serde!(HTime, get_time, HType::Time);
+impl_serde!(HDateTime, get_datetime, HType::DateTime);
+impl_serde!(HNumber<T>, get_number, HType::Number, NumTrait);
+impl_serde!(HCoord<T>, get_coord, HType::Coord, NumTrait);
+impl_serde!(HDict<'a, T>, get_dict, HType::Dict, NumTrait);
+impl_serde!(HList<'a, T>, get_list, HType::List, NumTrait);
+impl_serde!(HGrid<'a, T>, get_grid, HType::Grid, NumTrait);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_marker::MARKER;
+    use crate::h_number::HUnit;
+    use crate::h_remove::REMOVE;
+    use crate::io::parse::zinc::parse_grid;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn scalars_to_json() {
+        let num = HNumber::new(21.5, Some(HUnit::new("°C".to_owned())));
+        assert_eq!(
+            serde_json::to_value(&num).unwrap(),
+            json!({"_kind": "number", "val": 21.5, "unit": "°C"})
+        );
+        assert_eq!(
+            serde_json::to_value(HRef::new("p1".into(), Some("Point".into()))).unwrap(),
+            json!({"_kind": "ref", "val": "p1", "dis": "Point"})
+        );
+        assert_eq!(
+            serde_json::to_value(MARKER).unwrap(),
+            json!({"_kind": "marker"})
+        );
+        assert_eq!(
+            serde_json::to_value(HStr("hi".into())).unwrap(),
+            json!("hi")
+        );
+    }
+
+    #[test]
+    fn scalars_round_trip() {
+        let num: HNumber<f64> =
+            serde_json::from_value(json!({"_kind": "number", "val": "INF", "unit": "kW"})).unwrap();
+        assert_eq!(num.val(), f64::INFINITY);
+        assert_eq!(num.unit().as_ref().map(|u| u.as_str()), Some("kW"));
+
+        let plain: HNumber<f64> = serde_json::from_str("42").unwrap();
+        assert_eq!(plain, HNumber::new(42.0, None));
+
+        let dt: HDateTime = serde_json::from_value(json!({
+            "_kind": "dateTime",
+            "val": "2024-01-01T10:00:00+11:00",
+            "tz": "Sydney"
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::from_value::<HDateTime>(serde_json::to_value(&dt).unwrap()).unwrap(),
+            dt
+        );
+
+        let err = serde_json::from_value::<HRef>(json!("p1")).unwrap_err();
+        assert_eq!(err.to_string(), "Error: Expected Ref in hayson");
+    }
+
+    #[test]
+    fn collections_round_trip() {
+        let mut tags: HashMap<String, HBox<f64>> = HashMap::new();
+        tags.insert("site".into(), MARKER.to_hbox());
+        tags.insert(
+            "area".into(),
+            HNumber::new(1200.0, Some(HUnit::new("m²".into()))).to_hbox(),
+        );
+        tags.insert(
+            "tags".into(),
+            HList::from_vec(vec![HStr("a".into()).to_hbox()]).to_hbox(),
+        );
+        let dict = HDict::from_map(tags);
+
+        let json = serde_json::to_string(&dict).unwrap();
+        let back: HDict<f64> = serde_json::from_str(&json).unwrap();
+        assert!(back.has("site"));
+        assert_eq!(
+            back.get("area").unwrap().get_number().unwrap().val(),
+            1200.0
+        );
+        assert_eq!(back.get("tags").unwrap().get_list().unwrap().len(), 1);
+
+        let grid: HGrid<f64> = serde_json::from_value(json!({
+            "_kind": "grid",
+            "meta": {"ver": "3.0"},
+            "cols": [{"name": "id"}, {"name": "dis"}],
+            "rows": [{"id": {"_kind": "ref", "val": "a"}, "dis": "Alpha"}]
+        }))
+        .unwrap();
+        let json = serde_json::to_value(&grid).unwrap();
+        assert_eq!(json["rows"][0]["dis"], json!("Alpha"));
+        let back: HGrid<f64> = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            ZincWriter::new(&back).to_string(),
+            ZincWriter::new(&grid).to_string()
+        );
+
+        let val: &dyn HVal<f64> = &dict;
+        assert_eq!(
+            serde_json::to_string(val).unwrap(),
+            serde_json::to_string(&dict).unwrap()
+        );
+    }
+
+    #[test]
+    fn serializes_as_the_hayson_writer_does() {
+        fn same<'a>(val: &(dyn HVal<'a, f64> + 'a)) {
+            let written: serde_json::Value =
+                serde_json::from_str(&HaysonWriter::new(val).to_string()).unwrap();
+            assert_eq!(serde_json::to_value(val).unwrap(), written);
+        }
+        let grid = parse_grid::<f64>(
+            "ver:\"3.0\" view:\"chart\"\n\
+             id dis:\"Id\",val,ts,day,at,loc,u,flag,na\n\
+             @a \"Alpha\",1.5kW,2024-01-01T10:00:00+11:00 Sydney,2024-01-01,10:30:00,\
+             C(1.5,-2),`http://x.com`,T,NA\n\
+             @b,7,,,,,,F,\n",
+        )
+        .unwrap();
+        same(&grid);
+        let err: HGrid<f64> = HGrid::Error {
+            dis: "Boom".into(),
+            errTrace: Some("trace".into()),
+        };
+        same(&err);
+        same(&HList::from_vec(vec![
+            HXStr::new("Bin".into(), "text/plain".into()).to_hbox(),
+            HSymbol::new("elec-meter".into()).to_hbox(),
+            HNumber::new(f64::NAN, None).to_hbox(),
+            HNumber::new(f64::NEG_INFINITY, Some(HUnit::new("kW".into()))).to_hbox(),
+            HNumber::new(42.0, None).to_hbox(),
+            REMOVE.to_hbox(),
+            Ptr::new(grid),
+        ]));
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use crate::{
+    h_bool::HBool,
+    h_coord::HCoord,
+    h_date::HDate,
+    h_datetime::HDateTime,
+    h_dict::HDict,
+    h_grid::HGrid,
+    h_list::HList,
+    h_marker::HMarker,
+    h_na::HNA,
+    h_null::HNull,
+    h_number::{HNumber, NumTrait},
+    h_ref::HRef,
+    h_remove::HRemove,
+    h_str::HStr,
+    h_symbol::HSymbol,
+    h_time::HTime,
+    h_uri::HUri,
+    h_xstr::HXStr,
+};
+
+/// Control bytes leading each Brio value
+pub(crate) mod ctrl {
+    pub const NULL: u8 = 0x00;
+    pub const MARKER: u8 = 0x01;
+    pub const NA: u8 = 0x02;
+    pub const REMOVE: u8 = 0x03;
+    pub const FALSE: u8 = 0x04;
+    pub const TRUE: u8 = 0x05;
+    pub const NUMBER_I2: u8 = 0x06;
+    pub const NUMBER_I4: u8 = 0x07;
+    pub const NUMBER_F8: u8 = 0x08;
+    pub const STR: u8 = 0x09;
+    pub const REF_STR: u8 = 0x0a;
+    pub const REF_I8: u8 = 0x0b;
+    pub const URI: u8 = 0x0c;
+    pub const DATE: u8 = 0x0d;
+    pub const TIME: u8 = 0x0e;
+    pub const DATETIME_I4: u8 = 0x0f;
+    pub const DATETIME_I8: u8 = 0x10;
+    pub const COORD: u8 = 0x11;
+    pub const XSTR: u8 = 0x12;
+    pub const BUF: u8 = 0x13;
+    pub const DICT_EMPTY: u8 = 0x14;
+    pub const DICT: u8 = 0x15;
+    pub const LIST_EMPTY: u8 = 0x16;
+    pub const LIST: u8 = 0x17;
+    pub const GRID: u8 = 0x18;
+    pub const SYMBOL: u8 = 0x19;
+}
+
+/// Brio timestamps count from 2000-01-01T00:00:00Z
+pub(crate) fn epoch() -> chrono::NaiveDateTime {
+    chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// Refs with ids like `1deb31b8-7508b187` are written as the 64 bit handle the id spells out
+pub(crate) fn ref_handle(id: &str) -> Option<i64> {
+    let (hi, lo) = id.split_once('-')?;
+    let is_hex = |s: &str| {
+        s.len() == 8
+            && s.bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    };
+    if !is_hex(hi) || !is_hex(lo) {
+        return None;
+    }
+    let hi = u32::from_str_radix(hi, 16).ok()? as u64;
+    let lo = u32::from_str_radix(lo, 16).ok()? as u64;
+    Some((hi << 32 | lo) as i64)
+}
+
+/// Strings shared by reader and writer, written as their position in the table instead of
+/// inline. Both ends must use the same table, ie. the `brio-consts.txt` of the Haxall release
+/// on the other end.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BrioConsts {
+    strs: Vec<String>,
+    codes: HashMap<String, usize>,
+}
+
+impl BrioConsts {
+    pub fn new<S: Into<String>>(strs: impl IntoIterator<Item = S>) -> Self {
+        let strs: Vec<String> = strs.into_iter().map(Into::into).collect();
+        let codes = strs
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(code, s)| (s.clone(), code))
+            .collect();
+        Self { strs, codes }
+    }
+
+    pub fn get(&self, code: usize) -> Option<&str> {
+        self.strs.get(code).map(String::as_str)
+    }
+
+    pub fn code(&self, s: &str) -> Option<usize> {
+        self.codes.get(s).copied()
+    }
+}
+
+/// Collects the bytes of Brio values, used by each type's `to_brio`
+pub struct BrioEncoder<'c> {
+    out: Vec<u8>,
+    consts: Option<&'c BrioConsts>,
+}
+
+impl<'c> BrioEncoder<'c> {
+    pub fn new(consts: Option<&'c BrioConsts>) -> Self {
+        Self {
+            out: Vec::new(),
+            consts,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.out
+    }
+
+    pub fn u8(&mut self, val: u8) {
+        self.out.push(val);
+    }
+
+    pub fn i16(&mut self, val: i16) {
+        self.out.extend_from_slice(&val.to_be_bytes());
+    }
+
+    pub fn i32(&mut self, val: i32) {
+        self.out.extend_from_slice(&val.to_be_bytes());
+    }
+
+    pub fn i64(&mut self, val: i64) {
+        self.out.extend_from_slice(&val.to_be_bytes());
+    }
+
+    pub fn f64(&mut self, val: f64) {
+        self.out.extend_from_slice(&val.to_be_bytes());
+    }
+
+    /// Lengths and const codes, in 1, 2, 4 or 9 bytes, with `-1` for an inline string
+    pub fn var_int(&mut self, val: i64) {
+        match val {
+            ..0 => self.u8(0xff),
+            0..=0x7f => self.u8(val as u8),
+            0x80..=0x3fff => self
+                .out
+                .extend_from_slice(&(val as u16 | 0x8000).to_be_bytes()),
+            0x4000..=0x1fff_ffff => self
+                .out
+                .extend_from_slice(&(val as u32 | 0xc000_0000).to_be_bytes()),
+            _ => {
+                self.u8(0xe0);
+                self.i64(val);
+            }
+        }
+    }
+
+    /// A string from the const table as its code, otherwise inline
+    pub fn str(&mut self, val: &str) {
+        match self.consts.and_then(|consts| consts.code(val)) {
+            Some(code) => self.var_int(code as i64),
+            None => {
+                self.var_int(-1);
+                self.chars(val);
+            }
+        }
+    }
+
+    /// A string inline, as its length in chars and its UTF-8 bytes
+    pub fn chars(&mut self, val: &str) {
+        self.var_int(val.chars().count() as i64);
+        self.out.extend_from_slice(val.as_bytes());
+    }
+}
+
+/// Writes values in the Brio binary encoding, with no const table by default so every string is
+/// written inline
+pub struct BrioWriter<'a, T>
+where
+    T: ?Sized + 'a,
+{
+    value: &'a T,
+    consts: Option<&'a BrioConsts>,
+}
+
+impl<'a, T: ?Sized> BrioWriter<'a, T> {
+    pub fn new(value: &'a T) -> Self {
+        Self {
+            value,
+            consts: None,
+        }
+    }
+
+    pub fn with_consts(mut self, consts: &'a BrioConsts) -> Self {
+        self.consts = Some(consts);
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    where
+        T: BrioWritable,
+    {
+        let mut enc = BrioEncoder::new(self.consts);
+        self.value.to_brio(&mut enc);
+        enc.into_bytes()
+    }
+}
+
+pub trait BrioWritable {
+    fn to_brio(&self, enc: &mut BrioEncoder);
+}
+
+macro_rules! impl_brio_writable {
+    ($h_type:ty) => {
+        impl BrioWritable for $h_type {
+            fn to_brio(&self, enc: &mut BrioEncoder) {
+                <$h_type>::to_brio(self, enc)
+            }
+        }
+    };
+    ($h_type:ty, $num_trait:ident) => {
+        impl<'a, T: $num_trait + 'a> BrioWritable for $h_type {
+            fn to_brio(&self, enc: &mut BrioEncoder) {
+                <$h_type>::to_brio(self, enc)
+            }
+        }
+    };
+}
+
+impl_brio_writable!(HNull);
+impl_brio_writable!(HMarker);
+impl_brio_writable!(HRemove);
+impl_brio_writable!(HNA);
+impl_brio_writable!(HBool);
+impl_brio_writable!(HStr);
+impl_brio_writable!(HXStr);
+impl_brio_writable!(HUri);
+impl_brio_writable!(HDate);
+impl_brio_writable!(HDateTime);
+impl_brio_writable!(HTime);
+impl_brio_writable!(HRef);
+impl_brio_writable!(HSymbol);
+impl_brio_writable!(HCoord<T>, NumTrait);
+impl_brio_writable!(HNumber<T>, NumTrait);
+impl_brio_writable!(HDict<'a, T>, NumTrait);
+impl_brio_writable!(HList<'a, T>, NumTrait);
+impl_brio_writable!(HGrid<'a, T>, NumTrait);
@@ -0,0 +1,391 @@
+use std::fmt::{self, Display, Write};
+
+use crate::HType;
+use crate::h_grid::{HCol, HGrid};
+use crate::h_number::NumTrait;
+use crate::h_val::HVal;
+use crate::io::write::ZincWriter;
+
+/// Source of the header row
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvHeader {
+    #[default]
+    Name,
+    /// The `dis` col meta, falling back to the name
+    Dis,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvNumber {
+    Value,
+    #[default]
+    ValueWithUnit,
+    /// Follows each number column with a `<header> unit` column
+    UnitColumn,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvRef {
+    #[default]
+    Id,
+    /// The display string, falling back to the id
+    Dis,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvMarker {
+    #[default]
+    Check,
+    Zinc,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvDateTime {
+    #[default]
+    Iso,
+    /// Milliseconds since the Unix epoch
+    Epoch,
+}
+
+/// Rendering choices, the defaults follow the Haystack CSV rules
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CsvOptions {
+    pub header: CsvHeader,
+    pub number: CsvNumber,
+    pub reference: CsvRef,
+    pub marker: CsvMarker,
+    pub datetime: CsvDateTime,
+}
+
+pub struct CsvWriter<'a, T>
+where
+    T: ?Sized + 'a,
+{
+    value: &'a T,
+    options: CsvOptions,
+}
+
+impl<'a, T: ?Sized> CsvWriter<'a, T> {
+    pub fn new(value: &'a T) -> Self {
+        Self {
+            value,
+            options: CsvOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: CsvOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_header(mut self, header: CsvHeader) -> Self {
+        self.options.header = header;
+        self
+    }
+
+    pub fn with_numbers(mut self, number: CsvNumber) -> Self {
+        self.options.number = number;
+        self
+    }
+
+    pub fn with_refs(mut self, reference: CsvRef) -> Self {
+        self.options.reference = reference;
+        self
+    }
+
+    pub fn with_markers(mut self, marker: CsvMarker) -> Self {
+        self.options.marker = marker;
+        self
+    }
+
+    pub fn with_datetimes(mut self, datetime: CsvDateTime) -> Self {
+        self.options.datetime = datetime;
+        self
+    }
+}
+
+impl<'a, T: ?Sized> Display for CsvWriter<'a, T>
+where
+    T: CsvWritable + 'a,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.to_csv(f, &self.options)
+    }
+}
+
+pub trait CsvWritable {
+    fn to_csv(&self, f: &mut fmt::Formatter<'_>, options: &CsvOptions) -> fmt::Result;
+}
+
+/// Writes a cell, quoting it when it holds a comma, quote, line break or surrounding whitespace
+pub fn write_cell(f: &mut fmt::Formatter<'_>, cell: &str) -> fmt::Result {
+    let needs_quotes = cell.contains([',', '"', '\n', '\r'])
+        || cell.starts_with(char::is_whitespace)
+        || cell.ends_with(char::is_whitespace);
+
+    if !needs_quotes {
+        return f.write_str(cell);
+    }
+
+    f.write_char('"')?;
+    for c in cell.chars() {
+        if c == '"' {
+            f.write_char('"')?;
+        }
+        f.write_char(c)?;
+    }
+    f.write_char('"')
+}
+
+fn write_row(f: &mut fmt::Formatter<'_>, cells: &[String]) -> fmt::Result {
+    let mut iter = cells.iter().peekable();
+    while let Some(cell) = iter.next() {
+        write_cell(f, cell)?;
+        if iter.peek().is_some() {
+            f.write_char(',')?;
+        }
+    }
+    f.write_char('\n')
+}
+
+fn header<'a, T: NumTrait + 'a>(col: &HCol<'a, T>, options: &CsvOptions) -> String {
+    match options.header {
+        CsvHeader::Name => col.name.clone(),
+        CsvHeader::Dis => col
+            .meta()
+            .get("dis")
+            .and_then(|v| v.get_string().map(|s| s.as_str().to_owned()))
+            .unwrap_or_else(|| col.name.clone()),
+    }
+}
+
+/// Writes non-finite numbers as Haystack spells them rather than `inf` and `NaN`
+fn number<T: NumTrait>(val: T) -> String {
+    if val.is_nan() {
+        "NaN".to_owned()
+    } else if val.is_infinite() {
+        if val > T::zero() { "INF" } else { "-INF" }.to_owned()
+    } else {
+        val.to_string()
+    }
+}
+
+/// Renders a value as cell text, numbers exclude the unit when it gets its own column
+pub fn cell<'a, T: NumTrait + 'a>(val: &dyn HVal<'a, T>, options: &CsvOptions) -> String {
+    match val.haystack_type() {
+        HType::Null => String::new(),
+        HType::Marker => match options.marker {
+            CsvMarker::Check => "\u{2713}".to_owned(),
+            CsvMarker::Zinc => "M".to_owned(),
+        },
+        HType::Bool => val.get_bool().unwrap().0.to_string(),
+        HType::Str => val.get_string().unwrap().as_str().to_owned(),
+        HType::Uri => val.get_uri().unwrap().to_owned_string(),
+        HType::Number => {
+            let num = val.get_number().unwrap();
+            match (options.number, num.unit()) {
+                (CsvNumber::ValueWithUnit, Some(unit)) => {
+                    format!("{}{}", number(num.val()), unit.as_str())
+                }
+                _ => number(num.val()),
+            }
+        }
+        HType::Ref => {
+            let r = val.get_ref().unwrap();
+            match (options.reference, &r.dis) {
+                (CsvRef::Dis, Some(dis)) => dis.clone(),
+                _ => format!("@{}", r.id),
+            }
+        }
+        HType::DateTime => {
+            let dt = val.get_datetime().unwrap();
+            match options.datetime {
+                CsvDateTime::Iso => {
+                    let offset = match dt.offset().local_minus_utc() {
+                        0 => "Z".to_owned(),
+                        _ => dt.offset().to_string(),
+                    };
+                    format!("{}{}", dt.val().format("%Y-%m-%dT%H:%M:%S%.f"), offset)
+                }
+                CsvDateTime::Epoch => (dt.val() - dt.offset())
+                    .and_utc()
+                    .timestamp_millis()
+                    .to_string(),
+            }
+        }
+        _ => ZincWriter::new(val).to_string(),
+    }
+}
+
+impl<'a, T: NumTrait + 'a> CsvWritable for HGrid<'a, T> {
+    fn to_csv(&self, f: &mut fmt::Formatter<'_>, options: &CsvOptions) -> fmt::Result {
+        match self {
+            HGrid::Grid { .. } => {
+                let cols: Vec<HCol<'a, T>> = self.iter_cols().collect();
+
+                // Only columns holding a number with a unit get a unit column
+                let unit_cols: Vec<bool> = cols
+                    .iter()
+                    .map(|col| {
+                        options.number == CsvNumber::UnitColumn
+                            && self.iter().any(|row| {
+                                row.get(&col.name)
+                                    .and_then(|v| v.get_number().map(|n| n.unit().is_some()))
+                                    .unwrap_or(false)
+                            })
+                    })
+                    .collect();
+
+                let mut cells = Vec::with_capacity(cols.len());
+                for (col, has_unit) in cols.iter().zip(unit_cols.iter()) {
+                    let name = header(col, options);
+                    if *has_unit {
+                        cells.push(name.clone());
+                        cells.push(format!("{} unit", name));
+                    } else {
+                        cells.push(name);
+                    }
+                }
+                write_row(f, &cells)?;
+
+                for row in self.iter() {
+                    cells.clear();
+                    for (col, has_unit) in cols.iter().zip(unit_cols.iter()) {
+                        let val = row.get(&col.name);
+                        cells.push(
+                            val.as_ref()
+                                .map(|v| cell(v.as_ref(), options))
+                                .unwrap_or_default(),
+                        );
+                        if *has_unit {
+                            let unit = val
+                                .as_ref()
+                                .and_then(|v| v.get_number())
+                                .and_then(|n| n.unit().as_ref())
+                                .map(|u| u.as_str().to_owned());
+                            cells.push(unit.unwrap_or_default());
+                        }
+                    }
+                    write_row(f, &cells)?;
+                }
+                Ok(())
+            }
+            HGrid::Error { dis, errTrace } => {
+                write_row(f, &["dis".to_owned(), "errTrace".to_owned()])?;
+                write_row(f, &[dis.clone(), errTrace.clone().unwrap_or_default()])
+            }
+            HGrid::Empty { .. } => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_marker::MARKER;
+    use crate::h_number::HNumber;
+    use crate::h_ref::HRef;
+    use crate::h_str::HStr;
+    use crate::h_val::HBox;
+    use crate::io::ParseHint;
+    use crate::io::parse::zinc::datetime;
+    use std::collections::HashMap;
+
+    fn grid() -> HGrid<'static, f64> {
+        let (_, ts) =
+            datetime(&mut ParseHint::default())("2024-01-01T10:00:00+11:00 Sydney").unwrap();
+        let mut row: HashMap<String, HBox<f64>> = HashMap::new();
+        row.insert(
+            "id".into(),
+            HRef::new("p1".into(), Some("Point, 1".into())).to_hbox(),
+        );
+        row.insert("point".into(), MARKER.to_hbox());
+        row.insert(
+            "curVal".into(),
+            HNumber::new(21.5, Some("°C".to_owned().into())).to_hbox(),
+        );
+        row.insert("ts".into(), ts.to_hbox());
+        let mut other: HashMap<String, HBox<f64>> = HashMap::new();
+        other.insert("id".into(), HRef::new("p2".into(), None).to_hbox());
+        other.insert("curVal".into(), HNumber::new(3.0, None).to_hbox());
+        other.insert("dis".into(), HStr("Say \"hi\"".into()).to_hbox());
+
+        let cols = ["id", "dis", "point", "curVal", "ts"]
+            .iter()
+            .map(|n| HCol::new(n.to_string(), None))
+            .collect();
+        HGrid::new(Some(cols), vec![row, other])
+    }
+
+    #[test]
+    fn write_defaults() {
+        let grid = grid();
+        assert_eq!(
+            CsvWriter::new(&grid).to_string(),
+            "id,dis,point,curVal,ts\n\
+             @p1,,\u{2713},21.5°C,2024-01-01T10:00:00+11:00\n\
+             @p2,\"Say \"\"hi\"\"\",,3,\n"
+        );
+    }
+
+    #[test]
+    fn write_variants() {
+        let grid = grid();
+        let csv = CsvWriter::new(&grid)
+            .with_numbers(CsvNumber::UnitColumn)
+            .with_refs(CsvRef::Dis)
+            .with_markers(CsvMarker::Zinc)
+            .with_datetimes(CsvDateTime::Epoch)
+            .to_string();
+        assert_eq!(
+            csv,
+            "id,dis,point,curVal,curVal unit,ts\n\
+             \"Point, 1\",,M,21.5,°C,1704063600000\n\
+             @p2,\"Say \"\"hi\"\"\",,3,,\n"
+        );
+
+        let csv = CsvWriter::new(&grid)
+            .with_numbers(CsvNumber::Value)
+            .to_string();
+        assert!(csv.contains(",21.5,"));
+    }
+
+    #[test]
+    fn write_dis_header() {
+        let mut meta: HashMap<String, HBox<f64>> = HashMap::new();
+        meta.insert("dis".into(), HStr("Current Value".into()).to_hbox());
+        let grid = grid().add_col_meta("curVal", meta).unwrap();
+        let csv = CsvWriter::new(&grid)
+            .with_header(CsvHeader::Dis)
+            .to_string();
+        assert!(csv.starts_with("id,dis,point,Current Value,ts\n"));
+    }
+
+    #[test]
+    fn write_error_and_empty() {
+        let err: HGrid<f64> = HGrid::Error {
+            dis: "Bad, request".into(),
+            errTrace: None,
+        };
+        assert_eq!(
+            CsvWriter::new(&err).to_string(),
+            "dis,errTrace\n\"Bad, request\",\n"
+        );
+        let empty: HGrid<f64> = HGrid::Empty { meta: None };
+        assert_eq!(CsvWriter::new(&empty).to_string(), "");
+    }
+
+    #[test]
+    fn write_special_numbers() {
+        let num = |val: f64| HNumber::new(val, Some("kW".to_owned().into()));
+        let options = CsvOptions::default();
+        assert_eq!(cell(&num(f64::INFINITY), &options), "INFkW");
+        assert_eq!(cell(&num(f64::NEG_INFINITY), &options), "-INFkW");
+        assert_eq!(cell(&num(f64::NAN), &options), "NaNkW");
+        let options = CsvOptions {
+            number: CsvNumber::Value,
+            ..options
+        };
+        assert_eq!(cell(&num(f64::INFINITY), &options), "INF");
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt::{self, Display, Write};
+
+use crate::common::escape_str_no_escape_unicode;
+use crate::io::JsonVersion;
+use crate::{
+    h_bool::HBool,
+    h_coord::HCoord,
+    h_date::HDate,
+    h_datetime::HDateTime,
+    h_dict::HDict,
+    h_grid::HGrid,
+    h_list::HList,
+    h_marker::HMarker,
+    h_na::HNA,
+    h_null::HNull,
+    h_number::{HNumber, NumTrait},
+    h_ref::HRef,
+    h_remove::HRemove,
+    h_str::HStr,
+    h_symbol::HSymbol,
+    h_time::HTime,
+    h_uri::HUri,
+    h_xstr::HXStr,
+};
+
+/// Writes complete JSON documents, as Hayson by default or in the Haystack 3 encoding
+pub struct HaysonWriter<'a, T>
+where
+    T: ?Sized + 'a,
+{
+    value: &'a T,
+    version: JsonVersion,
+}
+
+impl<'a, T: ?Sized> HaysonWriter<'a, T> {
+    pub fn new(value: &'a T) -> Self {
+        Self {
+            value,
+            version: JsonVersion::V4,
+        }
+    }
+
+    pub fn with_version(mut self, version: JsonVersion) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+impl<'a, T: ?Sized> Display for HaysonWriter<'a, T>
+where
+    T: HaysonWritable + 'a,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.to_hayson(f, self.version)
+    }
+}
+
+pub trait HaysonWritable {
+    fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result;
+}
+
+macro_rules! impl_hayson_writable {
+    ($h_type:ty) => {
+        impl HaysonWritable for $h_type {
+            fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+                <$h_type>::to_hayson(self, f, version)
+            }
+        }
+    };
+    ($h_type:ty, $num_trait:ident) => {
+        impl<'a, T: $num_trait + 'a> HaysonWritable for $h_type {
+            fn to_hayson(&self, f: &mut fmt::Formatter<'_>, version: JsonVersion) -> fmt::Result {
+                <$h_type>::to_hayson(self, f, version)
+            }
+        }
+    };
+}
+
+impl_hayson_writable!(HNull);
+impl_hayson_writable!(HMarker);
+impl_hayson_writable!(HRemove);
+impl_hayson_writable!(HNA);
+impl_hayson_writable!(HBool);
+impl_hayson_writable!(HStr);
+impl_hayson_writable!(HXStr);
+impl_hayson_writable!(HUri);
+impl_hayson_writable!(HDate);
+impl_hayson_writable!(HDateTime);
+impl_hayson_writable!(HTime);
+impl_hayson_writable!(HRef);
+impl_hayson_writable!(HSymbol);
+impl_hayson_writable!(HCoord<T>, NumTrait);
+impl_hayson_writable!(HNumber<T>, NumTrait);
+impl_hayson_writable!(HDict<'a, T>, NumTrait);
+impl_hayson_writable!(HList<'a, T>, NumTrait);
+impl_hayson_writable!(HGrid<'a, T>, NumTrait);
+
+/// Writes `s` as a quoted and escaped JSON string
+pub fn write_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    s.chars()
+        .try_for_each(|c| escape_str_no_escape_unicode(c, f))?;
+    f.write_char('"')
+}
+
+/// Writes a scalar as a `_kind` object in Hayson or a type-prefixed string in Haystack 3
+pub fn write_scalar(
+    f: &mut fmt::Formatter<'_>,
+    version: JsonVersion,
+    kind: &str,
+    fields: &[(&str, Option<&str>)],
+) -> fmt::Result {
+    match version {
+        JsonVersion::V4 => {
+            write!(f, "{{\"_kind\":\"{}\"", kind)?;
+            for (k, v) in fields {
+                if let Some(v) = v {
+                    write!(f, ",\"{}\":", k)?;
+                    write_str(f, v)?;
+                }
+            }
+            write!(f, "}}")
+        }
+        JsonVersion::V3 => {
+            let prefix = match kind {
+                "marker" => "m",
+                "remove" => "-",
+                "na" => "z",
+                "ref" => "r",
+                "symbol" => "y",
+                "date" => "d",
+                "time" => "h",
+                "dateTime" => "t",
+                "uri" => "u",
+                "xstr" => "x",
+                _ => return Err(fmt::Error),
+            };
+            let sep = if kind == "xstr" { ":" } else { " " };
+            let body = fields
+                .iter()
+                .filter_map(|(_, v)| *v)
+                .collect::<Vec<_>>()
+                .join(sep);
+            write_str(f, &format!("{}:{}", prefix, body))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_datetime::IntoTimezone;
+    use crate::h_val::{HBox, HVal};
+    use crate::io::parse::json;
+    use crate::io::write::ZincWriter;
+    use chrono::FixedOffset;
+    use std::collections::HashMap;
+
+    fn hayson<V: HaysonWritable + ?Sized>(val: &V) -> String {
+        format!("{}", HaysonWriter::new(val))
+    }
+
+    fn v3<V: HaysonWritable + ?Sized>(val: &V) -> String {
+        format!("{}", HaysonWriter::new(val).with_version(JsonVersion::V3))
+    }
+
+    #[test]
+    fn write_scalars() {
+        assert_eq!(hayson(&HNull), "null");
+        assert_eq!(hayson(&HMarker), "{\"_kind\":\"marker\"}");
+        assert_eq!(v3(&HMarker), "\"m:\"");
+        assert_eq!(hayson(&HRemove), "{\"_kind\":\"remove\"}");
+        assert_eq!(hayson(&HNA), "{\"_kind\":\"na\"}");
+        assert_eq!(v3(&HNA), "\"z:\"");
+        assert_eq!(hayson(&HBool(true)), "true");
+        assert_eq!(
+            hayson(&HStr::new("say \"hi\"".into())),
+            "\"say \\\"hi\\\"\""
+        );
+        assert_eq!(v3(&HStr::new("n:42".into())), "\"s:n:42\"");
+        assert_eq!(v3(&HStr::new("a:b:c".into())), "\"s:a:b:c\"");
+        assert_eq!(v3(&HStr::new("key: value".into())), "\"key: value\"");
+        assert_eq!(
+            hayson(&HRef::new("abc".into(), Some("Main".into()))),
+            "{\"_kind\":\"ref\",\"val\":\"abc\",\"dis\":\"Main\"}"
+        );
+        assert_eq!(
+            v3(&HRef::new("abc".into(), Some("Main".into()))),
+            "\"r:abc Main\""
+        );
+        assert_eq!(
+            hayson(&HRef::new("abc".into(), None)),
+            "{\"_kind\":\"ref\",\"val\":\"abc\"}"
+        );
+        assert_eq!(
+            hayson(&HSymbol::new("elec-meter".into())),
+            "{\"_kind\":\"symbol\",\"val\":\"elec-meter\"}"
+        );
+        assert_eq!(
+            hayson(&HUri::new("http://example.com").unwrap()),
+            "{\"_kind\":\"uri\",\"val\":\"http://example.com/\"}"
+        );
+        assert_eq!(
+            hayson(&HDate::new(2024, 2, 29).unwrap()),
+            "{\"_kind\":\"date\",\"val\":\"2024-02-29\"}"
+        );
+        assert_eq!(v3(&HTime::new(8, 30, 0, 0).unwrap()), "\"h:08:30:00\"");
+        assert_eq!(
+            hayson(&HXStr::new("Bin".into(), "text/plain".into())),
+            "{\"_kind\":\"xstr\",\"type\":\"Bin\",\"val\":\"text/plain\"}"
+        );
+        assert_eq!(
+            v3(&HXStr::new("Bin".into(), "text/plain".into())),
+            "\"x:Bin:text/plain\""
+        );
+    }
+
+    #[test]
+    fn write_numbers() {
+        assert_eq!(
+            hayson(&HNumber::new(42.5, Some("kW".to_owned().into()))),
+            "{\"_kind\":\"number\",\"val\":42.5,\"unit\":\"kW\"}"
+        );
+        assert_eq!(
+            hayson(&HNumber::new(42.0, None)),
+            "{\"_kind\":\"number\",\"val\":42}"
+        );
+        assert_eq!(
+            hayson(&HNumber::new(f64::NEG_INFINITY, None)),
+            "{\"_kind\":\"number\",\"val\":\"-INF\"}"
+        );
+        assert_eq!(v3(&HNumber::new(f64::NAN, None)), "\"n:NaN\"");
+        assert_eq!(
+            v3(&HNumber::new(-1.5, Some("°F".to_owned().into()))),
+            "\"n:-1.5 °F\""
+        );
+        assert_eq!(
+            hayson(&HCoord::new(37.5, -77.4)),
+            "{\"_kind\":\"coord\",\"lat\":37.5,\"lng\":-77.4}"
+        );
+        assert_eq!(v3(&HCoord::new(37.5, -77.4)), "\"c:37.5,-77.4\"");
+    }
+
+    #[test]
+    fn write_datetime() {
+        let tz = (
+            FixedOffset::west_opt(4 * 3600).unwrap(),
+            chrono_tz::America::New_York,
+        );
+        let dt = HDateTime::new(2015, 6, 8, 15, 47, 41, 500_000_000, tz.into_timezone()).unwrap();
+        assert_eq!(
+            hayson(&dt),
+            "{\"_kind\":\"dateTime\",\"val\":\"2015-06-08T15:47:41.500-04:00\",\"tz\":\"New_York\"}"
+        );
+        assert_eq!(v3(&dt), "\"t:2015-06-08T15:47:41.500-04:00 New_York\"");
+
+        let utc = HDateTime::new(2020, 1, 1, 0, 0, 0, 0, Default::default()).unwrap();
+        assert_eq!(v3(&utc), "\"t:2020-01-01T00:00:00Z UTC\"");
+    }
+
+    #[test]
+    fn write_collections() {
+        let mut list = HList::<f64>::new();
+        list.push(HStr::new("item1".into()).to_hbox());
+        list.push(HNumber::new(42.0, None).to_hbox());
+        assert_eq!(
+            hayson(&list),
+            "[\"item1\",{\"_kind\":\"number\",\"val\":42}]"
+        );
+        assert_eq!(v3(&list), "[\"item1\",\"n:42\"]");
+
+        let mut dict = HDict::<f64>::new();
+        dict.set("site".into(), HMarker.to_hbox());
+        dict.set("empty".into(), HNull.to_hbox());
+        dict.set("nested".into(), HDict::<f64>::new().to_hbox());
+        let out = v3(&dict);
+        assert!(out.contains("\"site\":\"m:\""));
+        assert!(out.contains("\"nested\":{}"));
+        assert!(!out.contains("empty"));
+    }
+
+    fn sample_grid() -> HGrid<'static, f64> {
+        let mut row: HashMap<String, HBox<f64>> = HashMap::new();
+        row.insert(
+            "id".into(),
+            HRef::new("a".into(), Some("Alpha \"1\"".into())).to_hbox(),
+        );
+        row.insert(
+            "curVal".into(),
+            HNumber::new(12.5, Some("kW".to_owned().into())).to_hbox(),
+        );
+        let mut row_2: HashMap<String, HBox<f64>> = HashMap::new();
+        row_2.insert("id".into(), HRef::new("b".into(), None).to_hbox());
+
+        let mut meta: HashMap<String, HBox<f64>> = HashMap::new();
+        meta.insert("view".into(), HStr::new("chart".into()).to_hbox());
+        let mut col_meta: HashMap<String, HBox<f64>> = HashMap::new();
+        col_meta.insert("unit".into(), HStr::new("kW".into()).to_hbox());
+
+        HGrid::new(None, vec![row, row_2])
+            .add_meta(meta)
+            .unwrap()
+            .add_col_meta("curVal", col_meta)
+            .unwrap()
+    }
+
+    fn zinc(grid: &HGrid<f64>) -> String {
+        format!("{}", ZincWriter::new(grid))
+    }
+
+    #[test]
+    fn write_grid_round_trip() {
+        let grid = sample_grid();
+
+        for version in [JsonVersion::V4, JsonVersion::V3] {
+            let out = format!("{}", HaysonWriter::new(&grid).with_version(version));
+            let (rest, parsed) = json::grid::<f64>(&out).unwrap();
+            assert_eq!(rest, "");
+            assert_eq!(zinc(&parsed), zinc(&grid));
+        }
+
+        let out = hayson(&grid);
+        assert!(out.starts_with("{\"_kind\":\"grid\",\"meta\":{\"ver\":\"3.0\""));
+        assert!(out.contains("{\"name\":\"curVal\",\"meta\":{\"unit\":\"kW\"}}"));
+        let out = v3(&grid);
+        assert!(out.contains("{\"name\":\"curVal\",\"unit\":\"kW\"}"));
+    }
+
+    #[test]
+    fn write_error_and_empty_grids() {
+        let err: HGrid<f64> = HGrid::Error {
+            dis: "Not found".into(),
+            errTrace: None,
+        };
+        let out = v3(&err);
+        assert!(out.starts_with("{\"meta\":{\"ver\":\"3.0\","));
+        assert!(out.contains("\"err\":\"m:\""));
+        assert!(out.contains("\"dis\":\"Not found\""));
+        assert!(out.ends_with("\"cols\":[{\"name\":\"empty\"}],\"rows\":[]}"));
+        match json::grid::<f64>(&hayson(&err)).unwrap().1 {
+            HGrid::Error { dis, .. } => assert_eq!(dis, "Not found"),
+            _ => panic!("Expected error grid"),
+        }
+
+        let empty: HGrid<f64> = HGrid::Empty { meta: None };
+        assert_eq!(
+            hayson(&empty),
+            "{\"_kind\":\"grid\",\"meta\":{\"ver\":\"3.0\"},\"cols\":[],\"rows\":[]}"
+        );
+        assert!(matches!(
+            json::grid::<f64>(&hayson(&empty)).unwrap().1,
+            HGrid::Empty { .. }
+        ));
+    }
+}
@@ -96,13 +96,13 @@ mod tests {
         let date = HDate::new(2023, 10, 5).unwrap();
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&date)).unwrap();
-        assert_eq!(buf, "d:2023-10-05");
+        assert_eq!(buf, "\"d:2023-10-05\"");
         buf.clear();
 
         let mut buf = String::new();
         let date = HDate::new(2023, 12, 25).unwrap();
         write!(buf, "{}", JsonWriter::new(&date)).unwrap();
-        assert_eq!(buf, "d:2023-12-25");
+        assert_eq!(buf, "\"d:2023-12-25\"");
     }
 
     #[test]
@@ -111,7 +111,7 @@ mod tests {
         let datetime = HDateTime::new(2023, 10, 5, 14, 30, 45, 123456789, tz.clone()).unwrap();
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&datetime)).unwrap();
-        assert_eq!(buf, "t:2023-10-05T14:30:45.123456789");
+        assert_eq!(buf, "\"t:2023-10-05T14:30:45.123456789Z UTC\"");
     }
 
     #[test]
@@ -125,21 +125,21 @@ mod tests {
     fn test_marker() {
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&HMarker)).unwrap();
-        assert_eq!(buf, "m:");
+        assert_eq!(buf, "\"m:\"");
     }
 
     #[test]
     fn test_remove() {
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&HRemove)).unwrap();
-        assert_eq!(buf, "-:");
+        assert_eq!(buf, "\"-:\"");
     }
 
     #[test]
     fn test_na() {
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&HNA)).unwrap();
-        assert_eq!(buf, "z:");
+        assert_eq!(buf, "\"z:\"");
     }
 
     #[test]
@@ -158,31 +158,35 @@ mod tests {
         let coord = HCoord::new(10.5, 20.5);
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&coord)).unwrap();
-        assert_eq!(buf, "c:10.5,20.5");
+        assert_eq!(buf, "\"c:10.5,20.5\"");
     }
 
     #[test]
     fn test_str() {
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&HStr::new("hello world".into()))).unwrap();
-        assert_eq!(buf, "hello world");
+        assert_eq!(buf, "\"hello world\"");
 
         let hstr = HStr::new("hello".into());
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&hstr)).unwrap();
-        assert_eq!(buf, "hello");
+        assert_eq!(buf, "\"hello\"");
 
         let hstr_with_colon = HStr::new("key:value".into());
         let mut buf_with_colon = String::new();
         write!(buf_with_colon, "{}", JsonWriter::new(&hstr_with_colon)).unwrap();
-        assert_eq!(buf_with_colon, "s:key:value");
+        assert_eq!(buf_with_colon, "\"key:value\"");
+
+        let mut buf = String::new();
+        write!(buf, "{}", JsonWriter::new(&HStr::new("a:b".into()))).unwrap();
+        assert_eq!(buf, "\"s:a:b\"");
     }
 
     #[test]
     fn test_number() {
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&HNumber::new(42.0, None))).unwrap();
-        assert_eq!(buf, "n:42");
+        assert_eq!(buf, "\"n:42\"");
 
         buf.clear();
         write!(
@@ -191,14 +195,14 @@ mod tests {
             JsonWriter::new(&HNumber::new(42.2, Some("°F".to_owned().into())))
         )
         .unwrap();
-        assert_eq!(buf, "n:42.2 °F");
+        assert_eq!(buf, "\"n:42.2 °F\"");
     }
 
     #[test]
     fn test_ref() {
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&HRef::new("site1".into(), None))).unwrap();
-        assert_eq!(buf, "r:site1");
+        assert_eq!(buf, "\"r:site1\"");
 
         buf.clear();
         write!(
@@ -207,7 +211,7 @@ mod tests {
             JsonWriter::new(&HRef::new("site1".into(), Some("Site 1".into())))
         )
         .unwrap();
-        assert_eq!(buf, "r:site1 Site 1");
+        assert_eq!(buf, "\"r:site1 Site 1\"");
     }
 
     #[test]
@@ -216,7 +220,7 @@ mod tests {
         let mut buf = String::new();
         let symbol_hval = HVal::<f64>::as_hval(&symbol);
         write!(buf, "{}", JsonWriter::new(symbol_hval)).unwrap();
-        assert_eq!(buf, "y:example");
+        assert_eq!(buf, "\"y:example\"");
     }
 
     #[test]
@@ -228,7 +232,7 @@ mod tests {
             JsonWriter::new(&HUri::new("http://example.com").unwrap())
         )
         .unwrap();
-        assert_eq!(buf, "u:http://example.com/");
+        assert_eq!(buf, "\"u:http://example.com/\"");
     }
 
     #[test]
@@ -240,12 +244,12 @@ mod tests {
             JsonWriter::new(&HTime::new(14, 30, 0, 0).unwrap())
         )
         .unwrap();
-        assert_eq!(buf, "h:14:30:00");
+        assert_eq!(buf, "\"h:14:30:00\"");
 
         let time = HTime::new(12, 34, 56, 789_000_000).unwrap();
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&time)).unwrap();
-        assert_eq!(buf, "h:12:34:56.789000000");
+        assert_eq!(buf, "\"h:12:34:56.789000000\"");
     }
 
     #[test]
@@ -253,7 +257,7 @@ mod tests {
         let xhstr = HXStr::new("custom".to_string(), "hello".into());
         let mut buf = String::new();
         write!(buf, "{}", JsonWriter::new(&xhstr)).unwrap();
-        assert_eq!(buf, "x:custom:hello");
+        assert_eq!(buf, "\"x:custom:hello\"");
     }
 
     #[test]
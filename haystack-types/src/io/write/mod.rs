@@ -6,3 +6,12 @@ pub use trio::TrioWriter;
 
 pub mod json;
 pub use json::JsonWriter;
+
+pub mod csv;
+pub use csv::CsvWriter;
+
+pub mod hayson;
+pub use hayson::HaysonWriter;
+
+pub mod brio;
+pub use brio::BrioWriter;
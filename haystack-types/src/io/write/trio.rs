@@ -148,7 +148,7 @@ mod tests {
         let datetime = HDateTime::new(2023, 10, 5, 14, 30, 45, 123456789, tz.clone()).unwrap();
         let mut buf = String::new();
         write!(buf, "{}", TrioWriter::new(&datetime)).unwrap();
-        assert_eq!(buf, "2023-10-05T14:30:45.123456789");
+        assert_eq!(buf, "2023-10-05T14:30:45.123456789Z UTC");
     }
 
     #[test]
@@ -1,6 +1,7 @@
 use std::fmt::{self, Display};
 
 use crate::{
+    filter::HFilter,
     h_bool::HBool,
     h_coord::HCoord,
     h_date::HDate,
@@ -82,6 +83,7 @@ impl_zinc_writable!(HNumber<T>, NumTrait);
 impl_zinc_writable!(HDict<'a, T>, NumTrait);
 impl_zinc_writable!(HList<'a, T>, NumTrait);
 impl_zinc_writable!(HGrid<'a, T>, NumTrait);
+impl_zinc_writable!(HFilter<'a, T>, NumTrait);
 
 #[cfg(test)]
 mod tests {
@@ -188,7 +190,7 @@ mod tests {
         let datetime = HDateTime::new(2023, 10, 5, 14, 30, 45, 123456789, tz.clone()).unwrap();
         let mut buf = String::new();
         write!(buf, "{}", ZincWriter::new(&datetime)).unwrap();
-        assert_eq!(buf, "2023-10-05T14:30:45.123456789");
+        assert_eq!(buf, "2023-10-05T14:30:45.123456789Z UTC");
     }
 
     #[test]
@@ -1,5 +1,5 @@
 mod err;
-pub use err::HError;
+pub use err::{HError, UnitErr, ZincParseErr};
 
 mod common;
 pub use common::{Txt, ZincReader};
@@ -30,6 +30,8 @@ pub mod h_number;
 pub use h_number::{HUnit as Unit, NumTrait, Number};
 pub use num::Float;
 
+pub mod units;
+
 pub mod h_str;
 pub use h_str::Str;
 
@@ -54,11 +56,22 @@ pub use h_time::Time;
 pub mod h_datetime;
 pub use h_datetime::DateTime;
 
+pub mod tz;
+
+pub mod date_span;
+pub use date_span::DateSpan;
+
+pub mod defs;
+pub use defs::{DefsErr, Namespace};
+
+pub mod his;
+pub use his::{AlignedHis, HisErr, HisSeries};
+
 pub mod h_coord;
 pub use h_coord::Coord;
 
 pub mod h_grid;
-pub use h_grid::{HCol, HGrid, HRow};
+pub use h_grid::{GridBuilder, HCol, HGrid, HRow};
 
 pub mod h_dict;
 pub use h_dict::Dict;
@@ -68,4 +81,10 @@ pub use h_list::List;
 
 pub mod io;
 
+pub mod value;
+pub use value::{Value, ValueCol, ValueGrid};
+
+pub mod filter;
+pub use filter::{Filter, GridResolver, HFilter, HFilterErr, Resolver};
+
 pub use nom::Parser;
@@ -0,0 +1,150 @@
+//! Haystack timezones, named by the city (last segment) of their IANA name, ie. `New_York` for
+//! `America/New_York` and `GMT+5` for `Etc/GMT+5`. Zones whose city names another zone keep
+//! their full IANA name, ie. `US/Pacific` where `Pacific` is `Canada/Pacific`.
+
+use chrono_tz::{TZ_VARIANTS, Tz};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// IANA regions whose zones take their city name over zones elsewhere
+const REGIONS: [&str; 10] = [
+    "Africa",
+    "America",
+    "Antarctica",
+    "Asia",
+    "Atlantic",
+    "Australia",
+    "Etc",
+    "Europe",
+    "Indian",
+    "Pacific",
+];
+
+/// City names shared by several IANA zones, resolved to the canonical zone
+const AMBIGUOUS: [(&str, Tz); 9] = [
+    ("Buenos_Aires", Tz::America__Argentina__Buenos_Aires),
+    ("Catamarca", Tz::America__Argentina__Catamarca),
+    ("Cordoba", Tz::America__Argentina__Cordoba),
+    ("Indianapolis", Tz::America__Indiana__Indianapolis),
+    ("Istanbul", Tz::Europe__Istanbul),
+    ("Jujuy", Tz::America__Argentina__Jujuy),
+    ("Louisville", Tz::America__Kentucky__Louisville),
+    ("Mendoza", Tz::America__Argentina__Mendoza),
+    ("Nicosia", Tz::Asia__Nicosia),
+];
+
+fn city(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+fn by_name() -> &'static HashMap<&'static str, Tz> {
+    static BY_NAME: OnceLock<HashMap<&'static str, Tz>> = OnceLock::new();
+    BY_NAME.get_or_init(|| {
+        // Zones named exactly the city first, then those in a Haystack region, then the first
+        let rank = |tz: &Tz| match tz.name().split_once('/') {
+            None => 0,
+            Some((region, _)) if REGIONS.contains(&region) => 1,
+            Some(_) => 2,
+        };
+        let mut map: HashMap<&'static str, Tz> = HashMap::new();
+        for tz in TZ_VARIANTS.iter() {
+            let taken = map.get(city(tz.name()));
+            if taken.is_none_or(|taken| rank(tz) < rank(taken)) {
+                map.insert(city(tz.name()), *tz);
+            }
+        }
+        map.extend(AMBIGUOUS);
+        map
+    })
+}
+
+/// Looks up a timezone by its Haystack name, falling back to the full IANA name
+pub fn find(name: &str) -> Option<Tz> {
+    by_name()
+        .get(name)
+        .copied()
+        .or_else(|| Tz::from_str(name).ok())
+}
+
+/// The Haystack name of a timezone, its full IANA name when the city names another zone
+pub fn name(tz: Tz) -> &'static str {
+    let city = city(tz.name());
+    match by_name().get(city) == Some(&tz) {
+        true => city,
+        false => tz.name(),
+    }
+}
+
+/// Every Haystack timezone name with its zone
+pub fn names() -> impl Iterator<Item = (&'static str, Tz)> {
+    by_name().iter().map(|(name, tz)| (*name, *tz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_datetime::HDateTime;
+    use crate::io::ParseHint;
+    use crate::io::parse::zinc;
+    use crate::io::write::ZincWriter;
+    use chrono::TimeZone;
+
+    #[test]
+    fn find_by_city() {
+        assert_eq!(find("New_York"), Some(Tz::America__New_York));
+        assert_eq!(find("America/New_York"), Some(Tz::America__New_York));
+        assert_eq!(find("Sydney"), Some(Tz::Australia__Sydney));
+        assert_eq!(find("UTC"), Some(Tz::UTC));
+        assert_eq!(find("Nowhere"), None);
+    }
+
+    #[test]
+    fn find_etc_zones() {
+        assert_eq!(find("GMT+5"), Some(Tz::Etc__GMTPlus5));
+        assert_eq!(find("GMT-10"), Some(Tz::Etc__GMTMinus10));
+        assert_eq!(name(Tz::Etc__GMTPlus5), "GMT+5");
+    }
+
+    #[test]
+    fn ambiguous_names_are_canonical() {
+        assert_eq!(find("Istanbul"), Some(Tz::Europe__Istanbul));
+        assert_eq!(
+            find("Buenos_Aires"),
+            Some(Tz::America__Argentina__Buenos_Aires)
+        );
+        assert_eq!(name(Tz::America__Argentina__Buenos_Aires), "Buenos_Aires");
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for (city, tz) in names() {
+            assert_eq!(name(tz), city, "{}", tz.name());
+        }
+        for tz in TZ_VARIANTS.iter() {
+            assert_eq!(find(name(*tz)), Some(*tz), "{}", tz.name());
+        }
+    }
+
+    #[test]
+    fn shared_cities_keep_the_full_name() {
+        assert_eq!(name(Tz::Canada__Eastern), "Eastern");
+        assert_eq!(find("Eastern"), Some(Tz::Canada__Eastern));
+        assert_eq!(name(Tz::US__Eastern), "US/Eastern");
+        assert_eq!(find("US/Eastern"), Some(Tz::US__Eastern));
+        assert_eq!(name(Tz::Canada__Pacific), "Pacific");
+        assert_eq!(name(Tz::US__Pacific), "US/Pacific");
+        assert_eq!(name(Tz::Etc__UTC), "Etc/UTC");
+        assert_eq!(find("UTC"), Some(Tz::UTC));
+
+        let dt = HDateTime::from(
+            Tz::US__Eastern
+                .with_ymd_and_hms(2025, 7, 1, 9, 0, 0)
+                .unwrap(),
+        );
+        let zinc = ZincWriter::new(&dt).to_string();
+        assert_eq!(zinc, "2025-07-01T09:00:00-04:00 US/Eastern");
+        let (_, back) = zinc::literal::<f64>(&mut ParseHint::default())(&zinc).unwrap();
+        assert_eq!(back.get_datetime(), Some(&dt));
+    }
+}
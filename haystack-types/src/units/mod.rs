@@ -0,0 +1,360 @@
+//! The standard Haystack units database, with the quantity, dimension and SI conversion of each unit
+
+use crate::err::UnitErr;
+use crate::h_number::cast;
+use num::Float;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::ops::{Div, Mul};
+use std::sync::OnceLock;
+
+const UNITS_TXT: &str = include_str!("units.txt");
+
+/// SI base units in the order their exponents are stored
+const BASE_UNITS: [&str; 7] = ["kg", "m", "sec", "K", "A", "mol", "cd"];
+
+/// Exponents of the SI base units, all zero when dimensionless
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Dimension([i8; 7]);
+
+impl Dimension {
+    fn parse(s: &str) -> Option<Self> {
+        let mut exps = [0; 7];
+        for part in s.split('*').map(str::trim).filter(|p| !p.is_empty()) {
+            let split = part.find(|c: char| c == '-' || c.is_ascii_digit())?;
+            let idx = BASE_UNITS.iter().position(|b| *b == &part[..split])?;
+            exps[idx] = part[split..].parse().ok()?;
+        }
+        Some(Dimension(exps))
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.iter().all(|e| *e == 0)
+    }
+
+    /// Exponent of an SI base unit, one of kg, m, sec, K, A, mol or cd
+    pub fn exp(&self, base: &str) -> i8 {
+        BASE_UNITS
+            .iter()
+            .position(|b| *b == base)
+            .map_or(0, |idx| self.0[idx])
+    }
+}
+
+impl Mul for Dimension {
+    type Output = Dimension;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Dimension(std::array::from_fn(|idx| self.0[idx] + rhs.0[idx]))
+    }
+}
+
+impl Div for Dimension {
+    type Output = Dimension;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Dimension(std::array::from_fn(|idx| self.0[idx] - rhs.0[idx]))
+    }
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (base, exp) in BASE_UNITS.iter().zip(self.0).filter(|(_, e)| *e != 0) {
+            if !first {
+                write!(f, "*")?;
+            }
+            write!(f, "{}{}", base, exp)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// A unit in the database, converting to SI as `val * scale + offset`
+#[derive(Debug, PartialEq)]
+pub struct UnitDef {
+    ids: Vec<&'static str>,
+    quantity: &'static str,
+    dim: Dimension,
+    scale: f64,
+    offset: f64,
+}
+
+impl UnitDef {
+    pub fn name(&self) -> &'static str {
+        self.ids[0]
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        self.ids[self.ids.len() - 1]
+    }
+
+    /// The name, aliases and symbol of the unit
+    pub fn ids(&self) -> &[&'static str] {
+        &self.ids
+    }
+
+    pub fn quantity(&self) -> &'static str {
+        self.quantity
+    }
+
+    pub fn dim(&self) -> Dimension {
+        self.dim
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// The word setting the quantity apart from another of the same dimension, ie. `apparent`
+    /// for `apparent power` beside `power`
+    pub fn qualifier(&self) -> Option<&'static str> {
+        let (word, base) = self.quantity.split_once(' ')?;
+        quantity(base)
+            .next()
+            .filter(|unit| unit.dim == self.dim)
+            .map(|_| word)
+    }
+
+    /// Units convert within their quantity, so active, apparent and reactive power never do,
+    /// and currencies never
+    pub fn is_convertible(&self, to: &UnitDef) -> bool {
+        if std::ptr::eq(self, to) {
+            return true;
+        }
+        self.quantity == to.quantity && self.dim == to.dim && self.quantity != "currency"
+    }
+
+    pub fn convert<T: Float>(&self, val: T, to: &UnitDef) -> Result<T, UnitErr> {
+        if std::ptr::eq(self, to) {
+            return Ok(val);
+        }
+        if !self.is_convertible(to) {
+            return Err(UnitErr::Incompatible(
+                self.symbol().to_owned(),
+                to.symbol().to_owned(),
+            ));
+        }
+        let si = val * cast(self.scale) + cast(self.offset);
+        Ok((si - cast::<_, T>(to.offset)) / cast(to.scale))
+    }
+}
+
+impl Display for UnitDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+struct UnitDb {
+    units: Vec<UnitDef>,
+    by_id: HashMap<&'static str, usize>,
+}
+
+fn parse_unit(line: &'static str, quantity: &'static str) -> Option<UnitDef> {
+    let mut fields = line.split(';').map(str::trim);
+    let ids: Vec<_> = fields.next()?.split(',').map(str::trim).collect();
+    let dim = Dimension::parse(fields.next().unwrap_or(""))?;
+    let mut factor = |default: f64| match fields.next() {
+        Some(s) if !s.is_empty() => s.parse().ok(),
+        _ => Some(default),
+    };
+    let scale = factor(1.0)?;
+    let offset = factor(0.0)?;
+
+    Some(UnitDef {
+        ids,
+        quantity,
+        dim,
+        scale,
+        offset,
+    })
+}
+
+fn db() -> &'static UnitDb {
+    static DB: OnceLock<UnitDb> = OnceLock::new();
+    DB.get_or_init(|| {
+        let mut units = Vec::new();
+        let mut quantity = "";
+        for line in UNITS_TXT.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            match line.strip_prefix("--") {
+                Some(name) => quantity = name.trim(),
+                None => units.push(
+                    parse_unit(line, quantity)
+                        .unwrap_or_else(|| panic!("Invalid line in units database: {}", line)),
+                ),
+            }
+        }
+
+        let by_id = units
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, unit)| unit.ids.iter().map(move |id| (*id, idx)))
+            .collect();
+        UnitDb { units, by_id }
+    })
+}
+
+/// Looks up a unit by its name, symbol or one of its aliases
+pub fn find(id: &str) -> Option<&'static UnitDef> {
+    let db = db();
+    db.by_id.get(id).map(|idx| &db.units[*idx])
+}
+
+/// The first unit in the database with the dimension and scale, and no offset, in a quantity
+/// with the qualifier. Dimensions no quantity is qualified for take any unqualified unit.
+pub fn find_by_dim(
+    dim: Dimension,
+    scale: f64,
+    qualifier: Option<&str>,
+) -> Option<&'static UnitDef> {
+    let with_dim = || {
+        units().filter(move |unit| {
+            unit.dim == dim && unit.offset == 0.0 && !unit.dim.is_dimensionless()
+        })
+    };
+    let qualifier = match qualifier {
+        Some(q) if !with_dim().any(|unit| unit.qualifier() == Some(q)) => None,
+        q => q,
+    };
+    with_dim().find(|unit| {
+        unit.qualifier() == qualifier && (unit.scale - scale).abs() <= scale.abs() * 1e-9
+    })
+}
+
+/// Every unit in the database, grouped by quantity
+pub fn units() -> impl Iterator<Item = &'static UnitDef> {
+    db().units.iter()
+}
+
+/// The quantity names in database order
+pub fn quantities() -> impl Iterator<Item = &'static str> {
+    let mut last = None;
+    units().filter_map(move |unit| {
+        let quantity = unit.quantity;
+        (last.replace(quantity) != Some(quantity)).then_some(quantity)
+    })
+}
+
+/// The units measuring the named quantity
+pub fn quantity(name: &str) -> impl Iterator<Item = &'static UnitDef> + '_ {
+    units().filter(move |unit| unit.quantity == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_ids_are_unique() {
+        let total: usize = units().map(|unit| unit.ids().len()).sum();
+        assert_eq!(db().by_id.len(), total);
+    }
+
+    #[test]
+    fn find_by_any_id() {
+        let celsius = find("°C").unwrap();
+        assert_eq!(celsius.name(), "celsius");
+        assert!(std::ptr::eq(celsius, find("degC").unwrap()));
+        assert!(std::ptr::eq(celsius, find("celsius").unwrap()));
+        assert_eq!(celsius.quantity(), "temperature");
+        assert_eq!(celsius.dim().to_string(), "K1");
+        assert_eq!(find("kWh").unwrap().dim().to_string(), "kg1*m2*sec-2");
+        assert!(find("furlong").is_none());
+    }
+
+    #[test]
+    fn find_derived_unit() {
+        let kw = find("kW").unwrap();
+        let h = find("h").unwrap();
+        let kwh = find_by_dim(kw.dim() * h.dim(), kw.scale() * h.scale(), None).unwrap();
+        assert_eq!(kwh.symbol(), "kWh");
+        let kvah = find_by_dim(kw.dim() * h.dim(), kw.scale() * h.scale(), Some("apparent"));
+        assert_eq!(kvah.unwrap().symbol(), "kVAh");
+        let m = find("m").unwrap();
+        let s = find("s").unwrap();
+        assert_eq!(
+            find_by_dim(m.dim() / s.dim(), 1.0, None).unwrap().symbol(),
+            "m/s"
+        );
+        assert_eq!(
+            find_by_dim(m.dim() / s.dim(), 1.0, Some("reactive"))
+                .unwrap()
+                .symbol(),
+            "m/s"
+        );
+        assert!(find_by_dim(m.dim() * s.dim(), 1.0, None).is_none());
+    }
+
+    #[test]
+    fn quantities_in_order() {
+        let names: Vec<_> = quantities().collect();
+        assert_eq!(names[0], "dimensionless");
+        assert!(names.contains(&"temperature"));
+        assert_eq!(
+            names.len(),
+            names.iter().collect::<std::collections::HashSet<_>>().len()
+        );
+        assert!(quantity("length").any(|unit| unit.symbol() == "ft"));
+    }
+
+    #[test]
+    fn convert_between_units() {
+        let c = find("°C").unwrap();
+        let f = find("°F").unwrap();
+        assert!((f.convert(212.0, c).unwrap() - 100.0).abs() < 1e-9);
+        assert!((c.convert(-40.0, f).unwrap() + 40.0).abs() < 1e-9);
+
+        let kwh = find("kWh").unwrap();
+        let btu = find("BTU").unwrap();
+        assert!((kwh.convert(1.0, btu).unwrap() - 3412.14163).abs() < 1e-4);
+        assert_eq!(
+            kwh.convert(1.0, c),
+            Err(UnitErr::Incompatible("kWh".into(), "°C".into()))
+        );
+    }
+
+    #[test]
+    fn dimensionless_convert_within_quantity() {
+        let percent = find("%").unwrap();
+        assert!((percent.convert(50.0, find("ppu").unwrap()).unwrap() - 0.5).abs() < 1e-12);
+        assert!(!percent.is_convertible(find("rad").unwrap()));
+        assert!(!find("$").unwrap().is_convertible(find("€").unwrap()));
+        assert!(find("$").unwrap().is_convertible(find("USD").unwrap()));
+    }
+
+    #[test]
+    fn hvac_units() {
+        let kw_per_ton = find("kW/ton").unwrap();
+        assert_eq!(kw_per_ton.quantity(), "cooling power consumption");
+        assert!(!kw_per_ton.is_convertible(find("COP").unwrap()));
+        let eer = find("EER").unwrap();
+        assert!(
+            (eer.convert(10.0, find("COP").unwrap()).unwrap() - 2.930710701722222).abs() < 1e-9
+        );
+        let dd = find("°daysF").unwrap();
+        assert!((dd.convert(9.0, find("°daysC").unwrap()).unwrap() - 5.0).abs() < 1e-9);
+        assert!(!dd.is_convertible(find("Δ°F").unwrap()));
+    }
+
+    #[test]
+    fn quantities_sharing_a_dimension_never_convert() {
+        let kva = find("kVA").unwrap();
+        assert_eq!(kva.qualifier(), Some("apparent"));
+        assert_eq!(find("kvarh").unwrap().qualifier(), Some("reactive"));
+        assert_eq!(find("kW").unwrap().qualifier(), None);
+        assert_eq!(find("Δ°C").unwrap().qualifier(), None);
+        assert!(!kva.is_convertible(find("kW").unwrap()));
+        assert!(!find("kWh").unwrap().is_convertible(find("kVAh").unwrap()));
+        assert!(!find("°C").unwrap().is_convertible(find("Δ°C").unwrap()));
+        assert!(kva.is_convertible(find("VA").unwrap()));
+    }
+}
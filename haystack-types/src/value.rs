@@ -0,0 +1,413 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::h_grid::builder::is_col_name;
+use crate::h_grid::{GridBuilder, HGridErr};
+use crate::h_val::{HBox, HType, HVal, Ptr};
+use crate::{
+    h_bool::HBool, h_coord::HCoord, h_date::HDate, h_datetime::HDateTime, h_dict::HDict,
+    h_grid::HGrid, h_list::HList, h_marker::MARKER, h_na::NA, h_null::NULL, h_number::HNumber,
+    h_number::NumTrait, h_ref::HRef, h_remove::REMOVE, h_str::HStr, h_symbol::HSymbol,
+    h_time::HTime, h_uri::HUri, h_xstr::HXStr,
+};
+
+/// An owned Haystack value, free of the lifetimes and downcasts that come with `HBox`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value<T: NumTrait> {
+    Null,
+    Marker,
+    Remove,
+    NA,
+    Bool(bool),
+    Number(HNumber<T>),
+    Str(String),
+    Uri(HUri),
+    Ref(HRef),
+    Symbol(HSymbol),
+    Date(HDate),
+    Time(HTime),
+    DateTime(HDateTime),
+    Coord(HCoord<T>),
+    XStr(HXStr),
+    List(Vec<Value<T>>),
+    Dict(HashMap<String, Value<T>>),
+    Grid(ValueGrid<T>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueCol<T: NumTrait> {
+    pub name: String,
+    pub meta: HashMap<String, Value<T>>,
+}
+
+/// A grid of owned values, error grids carry `err`, `dis` and `errTrace` in the meta like zinc.
+/// The columns are checked when the grid is made, so it always converts to an `HGrid`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueGrid<T: NumTrait> {
+    pub meta: HashMap<String, Value<T>>,
+    cols: Vec<ValueCol<T>>,
+    /// Each row holds the cells that aren't missing
+    pub rows: Vec<HashMap<String, Value<T>>>,
+}
+
+impl<T: NumTrait> Eq for Value<T> {}
+impl<T: NumTrait> Eq for ValueCol<T> {}
+impl<T: NumTrait> Eq for ValueGrid<T> {}
+
+impl<T: NumTrait> Value<T> {
+    pub fn haystack_type(&self) -> HType {
+        match self {
+            Value::Null => HType::Null,
+            Value::Marker => HType::Marker,
+            Value::Remove => HType::Remove,
+            Value::NA => HType::NA,
+            Value::Bool(_) => HType::Bool,
+            Value::Number(_) => HType::Number,
+            Value::Str(_) => HType::Str,
+            Value::Uri(_) => HType::Uri,
+            Value::Ref(_) => HType::Ref,
+            Value::Symbol(_) => HType::Symbol,
+            Value::Date(_) => HType::Date,
+            Value::Time(_) => HType::Time,
+            Value::DateTime(_) => HType::DateTime,
+            Value::Coord(_) => HType::Coord,
+            Value::XStr(_) => HType::XStr,
+            Value::List(_) => HType::List,
+            Value::Dict(_) => HType::Dict,
+            Value::Grid(_) => HType::Grid,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Looks up a tag when the value is a dict
+    pub fn get(&self, key: &str) -> Option<&Value<T>> {
+        match self {
+            Value::Dict(tags) => tags.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn to_hbox<'a>(&self) -> HBox<'a, T>
+    where
+        T: 'a,
+    {
+        match self {
+            Value::Null => Ptr::new(NULL),
+            Value::Marker => Ptr::new(MARKER),
+            Value::Remove => Ptr::new(REMOVE),
+            Value::NA => Ptr::new(NA),
+            Value::Bool(b) => Ptr::new(HBool(*b)),
+            Value::Number(n) => Ptr::new(n.clone()),
+            Value::Str(s) => Ptr::new(HStr(s.clone())),
+            Value::Uri(u) => Ptr::new(u.clone()),
+            Value::Ref(r) => Ptr::new(r.clone()),
+            Value::Symbol(s) => Ptr::new(s.clone()),
+            Value::Date(d) => Ptr::new(d.clone()),
+            Value::Time(t) => Ptr::new(t.clone()),
+            Value::DateTime(dt) => Ptr::new(dt.clone()),
+            Value::Coord(c) => Ptr::new(c.clone()),
+            Value::XStr(x) => Ptr::new(x.clone()),
+            Value::List(items) => {
+                Ptr::new(HList::from_vec(items.iter().map(Value::to_hbox).collect()))
+            }
+            Value::Dict(tags) => Ptr::new(to_dict(tags)),
+            Value::Grid(grid) => Ptr::new(grid.to_grid()),
+        }
+    }
+}
+
+fn to_map<'a, T: NumTrait + 'a>(tags: &HashMap<String, Value<T>>) -> HashMap<String, HBox<'a, T>> {
+    tags.iter().map(|(k, v)| (k.clone(), v.to_hbox())).collect()
+}
+
+fn from_map<'a, T: NumTrait + 'a>(dict: &HDict<'a, T>) -> HashMap<String, Value<T>> {
+    dict.iter()
+        .map(|(k, v)| (k.clone(), Value::from(v.as_ref())))
+        .collect()
+}
+
+pub fn to_dict<'a, T: NumTrait + 'a>(tags: &HashMap<String, Value<T>>) -> HDict<'a, T> {
+    HDict::from_map(to_map(tags))
+}
+
+impl<T: NumTrait> ValueGrid<T> {
+    /// Fails on duplicate or invalid column names, as `GridBuilder` does
+    pub fn new(
+        meta: HashMap<String, Value<T>>,
+        cols: Vec<ValueCol<T>>,
+        rows: Vec<HashMap<String, Value<T>>>,
+    ) -> Result<Self, HGridErr> {
+        let mut seen = HashSet::new();
+        for col in cols.iter() {
+            if !is_col_name(&col.name) {
+                return Err(HGridErr::InvalidColName(col.name.clone()));
+            }
+            if !seen.insert(col.name.as_str()) {
+                return Err(HGridErr::DuplicateCol(col.name.clone()));
+            }
+        }
+        Ok(ValueGrid { meta, cols, rows })
+    }
+
+    pub fn cols(&self) -> &[ValueCol<T>] {
+        &self.cols
+    }
+
+    pub fn to_grid<'a>(&self) -> HGrid<'a, T>
+    where
+        T: 'a,
+    {
+        if self.meta.contains_key("err") {
+            let string = |key| match self.meta.get(key) {
+                Some(Value::Str(s)) => Some(s.clone()),
+                _ => None,
+            };
+            return HGrid::Error {
+                dis: string("dis").unwrap_or_default(),
+                errTrace: string("errTrace"),
+            };
+        }
+
+        if self.cols.is_empty() && self.rows.is_empty() {
+            let meta = Some(to_map(&self.meta)).filter(|m| !m.is_empty());
+            return HGrid::Empty { meta };
+        }
+
+        let valid = "columns are checked by ValueGrid::new";
+        let mut builder = GridBuilder::new().extend_meta(to_dict(&self.meta));
+        for col in self.cols.iter() {
+            builder = builder.add_col(&col.name, to_dict(&col.meta)).expect(valid);
+        }
+        for row in self.rows.iter() {
+            let cells: Vec<_> = self
+                .cols
+                .iter()
+                .map(|c| row.get(&c.name).map(Value::to_hbox))
+                .collect();
+            builder = builder.add_row(cells).expect(valid);
+        }
+        builder.build()
+    }
+}
+
+impl<'a, 'b, T: NumTrait + 'a> From<&'b (dyn HVal<'a, T> + 'a)> for Value<T> {
+    fn from(val: &'b (dyn HVal<'a, T> + 'a)) -> Self {
+        match val.haystack_type() {
+            HType::Null => Value::Null,
+            HType::Marker => Value::Marker,
+            HType::Remove => Value::Remove,
+            HType::NA => Value::NA,
+            HType::Bool => Value::Bool(val.get_bool().unwrap().0),
+            HType::Number => Value::Number(val.get_number().unwrap().clone()),
+            HType::Str => Value::Str(val.get_string().unwrap().as_str().to_owned()),
+            HType::Uri => Value::Uri(val.get_uri().unwrap().clone()),
+            HType::Ref => Value::Ref(val.get_ref().unwrap().clone()),
+            HType::Symbol => Value::Symbol(val.get_symbol().unwrap().clone()),
+            HType::Date => Value::Date(val.get_date().unwrap().clone()),
+            HType::Time => Value::Time(val.get_time().unwrap().clone()),
+            HType::DateTime => Value::DateTime(val.get_datetime().unwrap().clone()),
+            HType::Coord => Value::Coord(val.get_coord().unwrap().clone()),
+            HType::XStr => Value::XStr(val.get_xstr().unwrap().clone()),
+            HType::List => Value::List(
+                val.get_list()
+                    .unwrap()
+                    .iter()
+                    .map(|v| Value::from(v.as_ref()))
+                    .collect(),
+            ),
+            HType::Dict => Value::Dict(from_map(val.get_dict().unwrap())),
+            HType::Grid => Value::Grid(ValueGrid::from(val.get_grid().unwrap())),
+        }
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<HBox<'a, T>> for Value<T> {
+    fn from(val: HBox<'a, T>) -> Self {
+        Value::from(val.as_ref())
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<&HBox<'a, T>> for Value<T> {
+    fn from(val: &HBox<'a, T>) -> Self {
+        Value::from(val.as_ref())
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<Value<T>> for HBox<'a, T> {
+    fn from(val: Value<T>) -> Self {
+        val.to_hbox()
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<&HDict<'a, T>> for Value<T> {
+    fn from(dict: &HDict<'a, T>) -> Self {
+        Value::Dict(from_map(dict))
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<&HGrid<'a, T>> for ValueGrid<T> {
+    fn from(grid: &HGrid<'a, T>) -> Self {
+        match grid {
+            HGrid::Grid { .. } => ValueGrid {
+                meta: from_map(&grid.meta()),
+                cols: grid
+                    .iter_cols()
+                    .map(|c| ValueCol {
+                        meta: from_map(&c.meta()),
+                        name: c.name,
+                    })
+                    .collect(),
+                rows: grid.iter().map(|row| from_map(&row.to_dict())).collect(),
+            },
+            HGrid::Error { dis, errTrace } => {
+                let mut meta = HashMap::from([
+                    ("err".to_owned(), Value::Marker),
+                    ("dis".to_owned(), Value::Str(dis.clone())),
+                ]);
+                if let Some(trace) = errTrace {
+                    meta.insert("errTrace".to_owned(), Value::Str(trace.clone()));
+                }
+                ValueGrid {
+                    meta,
+                    cols: Vec::new(),
+                    rows: Vec::new(),
+                }
+            }
+            HGrid::Empty { meta } => ValueGrid {
+                meta: meta
+                    .iter()
+                    .flatten()
+                    .map(|(k, v)| (k.clone(), Value::from(v.as_ref())))
+                    .collect(),
+                cols: Vec::new(),
+                rows: Vec::new(),
+            },
+        }
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<&HGrid<'a, T>> for Value<T> {
+    fn from(grid: &HGrid<'a, T>) -> Self {
+        Value::Grid(ValueGrid::from(grid))
+    }
+}
+
+impl<'a, T: NumTrait + 'a> From<ValueGrid<T>> for HGrid<'a, T> {
+    fn from(grid: ValueGrid<T>) -> Self {
+        grid.to_grid()
+    }
+}
+
+impl<T: NumTrait> From<bool> for Value<T> {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl<T: NumTrait> From<&str> for Value<T> {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_owned())
+    }
+}
+
+impl<T: NumTrait> From<String> for Value<T> {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl<T: NumTrait> From<HNumber<T>> for Value<T> {
+    fn from(n: HNumber<T>) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl<T: NumTrait> From<HRef> for Value<T> {
+    fn from(r: HRef) -> Self {
+        Value::Ref(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::parse::json::literal_value;
+    use crate::io::parse::zinc::{parse_grid, parse_value};
+    use crate::io::write::ZincWriter;
+
+    #[test]
+    fn value_from_hbox() {
+        let val: Value<f64> = parse_value("{site dis:\"Main\" area:1200m² tags:[1, @a]}").unwrap();
+        let Value::Dict(tags) = &val else {
+            panic!("expected a dict");
+        };
+        assert_eq!(tags["site"], Value::Marker);
+        assert_eq!(tags["dis"], Value::from("Main"));
+        match &tags["area"] {
+            Value::Number(n) => assert_eq!(n.val(), 1200.0),
+            v => panic!("expected a number, found {:?}", v),
+        }
+        assert_eq!(
+            val.get("tags"),
+            Some(&Value::List(vec![
+                Value::Number(HNumber::new(1.0, None)),
+                Value::Ref(HRef::new("a".into(), None)),
+            ]))
+        );
+
+        let back: HBox<f64> = val.clone().into();
+        assert_eq!(Value::from(&back), val);
+
+        let (_, json) =
+            literal_value::<f64>(None)(r#"{"dis": "Main", "site": {"_kind": "marker"}}"#).unwrap();
+        assert_eq!(json.get("site"), Some(&Value::Marker));
+    }
+
+    #[test]
+    fn grid_round_trip() {
+        let zinc = "ver:\"3.0\" view:\"chart\"\nid dis:\"Id\",val\n@a,1kW\n@b,\n";
+        let grid = parse_grid::<f64>(zinc).unwrap();
+        let val = Value::from(&grid);
+        let Value::Grid(vgrid) = &val else {
+            panic!("expected a grid");
+        };
+        assert_eq!(vgrid.cols()[0].meta["dis"], Value::from("Id"));
+        assert_eq!(vgrid.rows.len(), 2);
+        assert!(!vgrid.rows[1].contains_key("val"));
+
+        let back: HGrid<f64> = vgrid.clone().into();
+        assert_eq!(
+            ZincWriter::new(&back).to_string(),
+            ZincWriter::new(&grid).to_string()
+        );
+
+        let err = parse_value::<f64>("ver:\"3.0\" err dis:\"Boom\"\nempty\n").unwrap();
+        match err.to_hbox().get_grid() {
+            Some(HGrid::Error { dis, .. }) => assert_eq!(dis, "Boom"),
+            _ => panic!("expected an error grid"),
+        }
+    }
+
+    #[test]
+    fn invalid_cols_are_errors() {
+        let col = |name: &str| ValueCol {
+            name: name.to_owned(),
+            meta: HashMap::new(),
+        };
+        let row = || vec![HashMap::from([("id".to_owned(), Value::from("a"))])];
+        assert!(matches!(
+            ValueGrid::<f64>::new(HashMap::new(), vec![col("id"), col("id")], row()),
+            Err(HGridErr::DuplicateCol(name)) if name == "id"
+        ));
+        assert!(matches!(
+            ValueGrid::<f64>::new(HashMap::new(), vec![col("id"), col("Bad Name")], row()),
+            Err(HGridErr::InvalidColName(name)) if name == "Bad Name"
+        ));
+
+        let vgrid = ValueGrid::<f64>::new(HashMap::new(), vec![col("id")], row()).unwrap();
+        let grid: HGrid<f64> = vgrid.into();
+        assert_eq!(grid.len(), 1);
+    }
+}